@@ -1,24 +1,71 @@
-use std::collections::HashMap;
+use std::{fmt, ops::Deref};
 
 #[derive(Debug)]
-pub struct Ast(pub Vec<Box<Stmt>>, pub Option<Box<Expr>>);
+pub struct Ast(pub Vec<Located<Stmt>>, pub Option<Located<Expr>>);
+
+/// A node along with the line and column of source code it starts on.
+///
+/// Nodes built by hand (rather than parsed) are given line and column 0.
+pub struct Located<T> {
+  pub line: usize,
+  /// The (1-based) byte offset into the line.
+  pub col: usize,
+  pub node: Box<T>,
+}
+impl<T> Located<T> {
+  pub fn new(line: usize, col: usize, node: Box<T>) -> Self {
+    Self { line, col, node }
+  }
+}
+impl<T> From<Box<T>> for Located<T> {
+  fn from(node: Box<T>) -> Self {
+    Self::new(0, 0, node)
+  }
+}
+impl<T> Deref for Located<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.node
+  }
+}
+impl<T: fmt::Debug> fmt::Debug for Located<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.node.fmt(f)
+  }
+}
+impl<T: fmt::Display> fmt::Display for Located<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.node.fmt(f)
+  }
+}
 
 #[derive(Debug)]
 pub enum Stmt {
-  Function(String, Vec<String>, Box<Expr>),
+  /// A function declaration, which is last given whether it's marked `pure`.
+  Function(String, Vec<String>, Box<Expr>, bool),
   Const(String, Box<Expr>),
   Let(String, Option<Box<Expr>>),
   Return(Option<Box<Expr>>),
-  Break(Option<Box<Expr>>),
+  /// Exits the loop with the given label (or the innermost loop),
+  /// optionally giving that loop a value.
+  Break(Option<String>, Option<Box<Expr>>),
+  /// Skips to the next iteration of the loop with the given label (or the innermost loop).
+  Continue(Option<String>),
+  /// A lone semicolon, which does nothing.
+  Empty,
   Expression(Box<Expr>),
 }
 
 #[derive(Debug)]
 pub enum Expr {
   Error,
-  /// Outputs the value of the contained [Expr] as a log.
-  Log(Box<Expr>),
-  Block(Vec<Box<Stmt>>, Option<Box<Expr>>),
+  /// Outputs the values of the contained [Expr]s as a single log.
+  ///
+  /// A single value is logged as-is, while any other number of values
+  /// are logged as one string of each of them separated by spaces.
+  Log(Vec<Box<Expr>>),
+  Block(Vec<Located<Stmt>>, Option<Located<Expr>>),
   /// The first [Expr] is the condition, the second the "true" block,
   /// and the third the "false" block.
   ///
@@ -30,25 +77,84 @@ pub enum Expr {
     /* false branch: */ Option<Box<Expr>>,
   ),
   /// While [Expr] is true, do [Stmt]s.
-  While(Box<Expr>, Vec<Box<Stmt>>),
+  ///
+  /// The loop can optionally be labeled (without the leading `'`),
+  /// so that `break` and `continue` can target it from within nested loops.
+  While(Box<Expr>, Vec<Located<Stmt>>, Option<String>),
+  /// Like [Expr::While], but evaluates to a tuple of the value of the block on each iteration,
+  /// rather than to unit.
+  ///
+  /// A `break` without a value ends the loop with whatever has been collected so far.
+  Collect(Box<Expr>, Box<Expr>, Option<String>),
+  /// Compares the value of [Expr] against each arm's [Pattern] from top to bottom,
+  /// evaluating to the expression of the first arm that matches.
+  Match(Box<Expr>, Vec<(Pattern, Box<Expr>)>),
+  /// Evaluates to the first block, unless it raises an error, in which case it evaluates to
+  /// the second block, with a record describing that error bound to the given name.
+  Try(Box<Expr>, String, Box<Expr>),
   Assign(Box<Expr>, AssignOp, Box<Expr>),
   Binary(Box<Expr>, BinaryOp, Box<Expr>),
   Unary(UnaryOp, Box<Expr>),
   Property(Box<Expr>, String),
+  /// Indexes into a tuple or string, where negative indices count back from the end.
   Index(Box<Expr>, Box<Expr>),
   Call(Box<Expr>, Vec<Box<Expr>>),
+  /// An anonymous function, whose body is always a block (even if it was written with `=>`).
+  Function(Vec<String>, Box<Expr>),
+  /// A string with expressions embedded in it using `${...}`, split into its parts.
+  ///
+  /// The first part is always a string, and each part is converted to a string and joined onto it.
+  Interpolation(Vec<Box<Expr>>),
+  /// `...tuple`, which can only be an item of a tuple literal, where it's replaced by
+  /// the elements of the tuple it evaluates to.
+  Spread(Box<Expr>),
   Constant(Constant),
 }
 
 #[derive(Debug)]
 pub enum Constant {
+  Unit,
   True,
   False,
+  /// `none`, the optional value that's missing (which isn't the same as unit).
+  None,
+  /// `some(x)`, an optional value that's present.
+  Some(Box<Expr>),
   Ident(Vec<String>, String),
+  /// A number written without a decimal point.
+  Int(i64),
   Number(f64),
   String(String),
   Tuple(Vec<Box<Expr>>),
-  Record(HashMap<String, Box<Expr>>),
+  /// Fields are kept in the order they were written in.
+  Record(Vec<Field>),
+}
+
+/// An entry in a record literal.
+#[derive(Debug)]
+pub enum Field {
+  /// `name: value`, where the name may also be written as a string literal.
+  Named(String, Box<Expr>),
+  /// `[key]: value`, where the key may be any expression, such as a number or tuple.
+  Keyed(Box<Expr>, Box<Expr>),
+  /// `...record`, which copies in every field of the record it evaluates to,
+  /// replacing the values of any fields written before it.
+  Spread(Box<Expr>),
+}
+
+/// What a value is compared against in an arm of a match expression.
+#[derive(Debug)]
+pub enum Pattern {
+  /// `_`, which matches anything without binding it.
+  Wildcard,
+  /// Matches anything, binding it to a variable with the given name.
+  Binding(String),
+  /// Matches values equal to the given unit, boolean, `none`, number, or string constant.
+  Literal(Constant),
+  /// Matches tuples of the same length whose items match each of the patterns.
+  Tuple(Vec<Pattern>),
+  /// Matches optional values that are present and hold a value matching the pattern.
+  Some(Box<Pattern>),
 }
 
 #[derive(Debug)]
@@ -59,6 +165,8 @@ pub enum UnaryOp {
 
 #[derive(Debug)]
 pub enum BinaryOp {
+  /// `a ?? b`, which is `b` if `a` is unit, and `a` otherwise.
+  Coalesce,
   Or,
   And,
   Equal,
@@ -71,6 +179,7 @@ pub enum BinaryOp {
   Subtract,
   Multiply,
   Divide,
+  Modulo,
   Exponent,
 }
 
@@ -85,3 +194,463 @@ pub enum AssignOp {
   OrAssign,
   AndAssign,
 }
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+  items
+    .iter()
+    .map(|item| item.to_string())
+    .collect::<Vec<String>>()
+    .join(separator)
+}
+
+fn fmt_block(
+  f: &mut fmt::Formatter<'_>,
+  stmts: &[Located<Stmt>],
+  expr: Option<&Expr>,
+) -> fmt::Result {
+  let mut parts: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+  if let Some(expr) = expr {
+    parts.push(expr.to_string());
+  }
+
+  if parts.is_empty() {
+    write!(f, "{{}}")
+  } else {
+    write!(f, "{{ {} }}", parts.join(" "))
+  }
+}
+
+impl Expr {
+  /// Writes the given operand, wrapping it in parentheses if it would otherwise
+  /// bind differently than it does in the tree.
+  fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, min_precedence: u8) -> fmt::Result {
+    let needs_parens = match self {
+      Expr::Assign(..) => true,
+      Expr::Binary(_, op, _) => op.precedence() < min_precedence,
+      _ => false,
+    };
+
+    if needs_parens {
+      write!(f, "({})", self)
+    } else {
+      write!(f, "{}", self)
+    }
+  }
+}
+
+impl fmt::Display for Ast {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Ast(stmts, expr) = self;
+    let mut parts: Vec<String> = stmts.iter().map(|stmt| stmt.to_string()).collect();
+    if let Some(expr) = expr {
+      parts.push(expr.to_string());
+    }
+    write!(f, "{}", parts.join(" "))
+  }
+}
+
+impl fmt::Display for Stmt {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Stmt::Function(name, params, body, pure) => {
+        if *pure {
+          write!(f, "pure ")?;
+        }
+        write!(f, "fn {}({}) {};", name, params.join(", "), body)
+      }
+      Stmt::Const(name, expr) => write!(f, "const {} = {};", name, expr),
+      Stmt::Let(name, Some(expr)) => write!(f, "let {} = {};", name, expr),
+      Stmt::Let(name, None) => write!(f, "let {};", name),
+      Stmt::Return(Some(expr)) => write!(f, "return {};", expr),
+      Stmt::Return(None) => write!(f, "return;"),
+      Stmt::Break(label, expr) => {
+        write!(f, "break")?;
+        if let Some(label) = label {
+          write!(f, " '{}", label)?;
+        }
+        if let Some(expr) = expr {
+          write!(f, " {}", expr)?;
+        }
+        write!(f, ";")
+      }
+      Stmt::Continue(Some(label)) => write!(f, "continue '{};", label),
+      Stmt::Continue(None) => write!(f, "continue;"),
+      Stmt::Empty => write!(f, ";"),
+      Stmt::Expression(expr) => write!(f, "{};", expr),
+    }
+  }
+}
+
+impl fmt::Display for Expr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Expr::Error => write!(f, "<error>"),
+      Expr::Log(args) => write!(f, "log({})", join(args, ", ")),
+      Expr::Block(stmts, expr) => fmt_block(f, stmts, expr.as_deref()),
+      Expr::If(condition, true_branch, false_branch) => {
+        write!(f, "if {} {}", condition, true_branch)?;
+        match false_branch {
+          Some(false_branch) => write!(f, " else {}", false_branch),
+          None => Ok(()),
+        }
+      }
+      Expr::While(condition, stmts, label) => {
+        if let Some(label) = label {
+          write!(f, "'{}: ", label)?;
+        }
+        write!(f, "while {} ", condition)?;
+        fmt_block(f, stmts, None)
+      }
+      Expr::Collect(condition, block, label) => {
+        if let Some(label) = label {
+          write!(f, "'{}: ", label)?;
+        }
+        write!(f, "collect while {} {}", condition, block)
+      }
+      Expr::Match(expr, arms) => {
+        let arms: Vec<String> = arms
+          .iter()
+          .map(|(pattern, expr)| format!("{} => {}", pattern, expr))
+          .collect();
+        write!(f, "match {} {{ {} }}", expr, arms.join(", "))
+      }
+      Expr::Try(body, name, catch) => write!(f, "try {} catch {} {}", body, name, catch),
+      Expr::Assign(target, op, expr) => write!(f, "{} {} {}", target, op, expr),
+      Expr::Binary(lhs, op, rhs) => {
+        // all binary operators are left-associative
+        lhs.fmt_operand(f, op.precedence())?;
+        write!(f, " {} ", op)?;
+        rhs.fmt_operand(f, op.precedence() + 1)
+      }
+      Expr::Unary(op, expr) => {
+        write!(f, "{}", op)?;
+        expr.fmt_operand(f, u8::MAX)
+      }
+      Expr::Property(expr, name) => {
+        expr.fmt_operand(f, u8::MAX)?;
+        write!(f, ".{}", name)
+      }
+      Expr::Index(expr, index) => {
+        expr.fmt_operand(f, u8::MAX)?;
+        write!(f, "[{}]", index)
+      }
+      Expr::Call(function, args) => {
+        function.fmt_operand(f, u8::MAX)?;
+        write!(f, "({})", join(args, ", "))
+      }
+      Expr::Function(params, body) => write!(f, "fn({}) {}", params.join(", "), body),
+      Expr::Spread(expr) => write!(f, "...{}", expr),
+      Expr::Interpolation(parts) => {
+        write!(f, "\"")?;
+        for part in parts {
+          match &**part {
+            Expr::Constant(Constant::String(string)) => {
+              write!(f, "{}", &string[1..(string.len() - 1)])?
+            }
+            expr => write!(f, "${{{}}}", expr)?,
+          }
+        }
+        write!(f, "\"")
+      }
+      Expr::Constant(constant) => write!(f, "{}", constant),
+    }
+  }
+}
+
+impl fmt::Display for Constant {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Constant::Unit => write!(f, "()"),
+      Constant::True => write!(f, "true"),
+      Constant::False => write!(f, "false"),
+      Constant::None => write!(f, "none"),
+      Constant::Some(expr) => write!(f, "some({})", expr),
+      Constant::Ident(path, name) => {
+        for part in path {
+          write!(f, "{}::", part)?;
+        }
+        write!(f, "{}", name)
+      }
+      Constant::Int(num) => write!(f, "{}", num),
+      // floats keep their decimal point, so they aren't read back in as integers
+      Constant::Number(num) => write!(f, "{:?}", num),
+      // strings still contain their quotation marks from the source
+      Constant::String(str) => write!(f, "{}", str),
+      Constant::Tuple(tuple) => write!(f, "#[{}]", join(tuple, ", ")),
+      Constant::Record(record) => write!(f, "#{{{}}}", join(record, ", ")),
+    }
+  }
+}
+
+impl fmt::Display for Field {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Field::Named(name, expr) => write!(f, "{}: {}", name, expr),
+      Field::Keyed(key, expr) => write!(f, "[{}]: {}", key, expr),
+      Field::Spread(expr) => write!(f, "...{}", expr),
+    }
+  }
+}
+
+impl Pattern {
+  /// Returns whether this pattern matches every value.
+  pub fn is_irrefutable(&self) -> bool {
+    matches!(self, Pattern::Wildcard | Pattern::Binding(_))
+  }
+}
+
+impl fmt::Display for Pattern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Pattern::Wildcard => write!(f, "_"),
+      Pattern::Binding(name) => write!(f, "{}", name),
+      Pattern::Literal(constant) => write!(f, "{}", constant),
+      Pattern::Tuple(patterns) => write!(f, "#[{}]", join(patterns, ", ")),
+      Pattern::Some(pattern) => write!(f, "some({})", pattern),
+    }
+  }
+}
+
+impl fmt::Display for UnaryOp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      UnaryOp::Negate => write!(f, "-"),
+      UnaryOp::Not => write!(f, "!"),
+    }
+  }
+}
+
+impl BinaryOp {
+  /// How tightly the operator binds, from loosest (`??`) to tightest (`^`).
+  pub fn precedence(&self) -> u8 {
+    match self {
+      BinaryOp::Coalesce => 1,
+      BinaryOp::Or => 2,
+      BinaryOp::And => 3,
+      BinaryOp::Equal | BinaryOp::NotEqual => 4,
+      BinaryOp::GreaterThan | BinaryOp::GreaterEqual | BinaryOp::LessThan | BinaryOp::LessEqual => {
+        5
+      }
+      BinaryOp::Add | BinaryOp::Subtract => 6,
+      BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 7,
+      BinaryOp::Exponent => 8,
+    }
+  }
+}
+
+impl fmt::Display for BinaryOp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let op = match self {
+      BinaryOp::Coalesce => "??",
+      BinaryOp::Or => "||",
+      BinaryOp::And => "&&",
+      BinaryOp::Equal => "==",
+      BinaryOp::NotEqual => "!=",
+      BinaryOp::GreaterThan => ">",
+      BinaryOp::GreaterEqual => ">=",
+      BinaryOp::LessThan => "<",
+      BinaryOp::LessEqual => "<=",
+      BinaryOp::Add => "+",
+      BinaryOp::Subtract => "-",
+      BinaryOp::Multiply => "*",
+      BinaryOp::Divide => "/",
+      BinaryOp::Modulo => "%",
+      BinaryOp::Exponent => "^",
+    };
+    write!(f, "{}", op)
+  }
+}
+
+impl fmt::Display for AssignOp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let op = match self {
+      AssignOp::Assign => "=",
+      AssignOp::AddAssign => "+=",
+      AssignOp::SubtractAssign => "-=",
+      AssignOp::MultiplyAssign => "*=",
+      AssignOp::DivideAssign => "/=",
+      AssignOp::ExponentAssign => "^=",
+      AssignOp::OrAssign => "||=",
+      AssignOp::AndAssign => "&&=",
+    };
+    write!(f, "{}", op)
+  }
+}
+
+/// Helpers for building an [Ast] by hand, without going through source code.
+///
+/// Every function here returns a boxed node, so they can be nested directly:
+///
+/// ```
+/// use bobascript_parser::ast::{mk, Ast, BinaryOp};
+///
+/// // 1 + 2
+/// let ast = Ast(vec![], Some(mk::binary(mk::number(1.0), BinaryOp::Add, mk::number(2.0)).into()));
+/// ```
+///
+/// Statements and trailing expressions are wrapped with `.into()`, which gives them line and column 0.
+/// The resulting [Ast] can then be handed to `bobascript::compiler::compile_ast`.
+pub mod mk {
+  use super::{AssignOp, BinaryOp, Constant, Expr, Field, Located, Pattern, Stmt, UnaryOp};
+
+  pub fn function_stmt(name: &str, params: &[&str], body: Box<Expr>) -> Box<Stmt> {
+    Box::new(Stmt::Function(
+      name.to_string(),
+      params.iter().map(|p| p.to_string()).collect(),
+      body,
+      false,
+    ))
+  }
+
+  pub fn pure_function_stmt(name: &str, params: &[&str], body: Box<Expr>) -> Box<Stmt> {
+    Box::new(Stmt::Function(
+      name.to_string(),
+      params.iter().map(|p| p.to_string()).collect(),
+      body,
+      true,
+    ))
+  }
+
+  pub fn const_stmt(name: &str, expr: Box<Expr>) -> Box<Stmt> {
+    Box::new(Stmt::Const(name.to_string(), expr))
+  }
+
+  pub fn let_stmt(name: &str, expr: Option<Box<Expr>>) -> Box<Stmt> {
+    Box::new(Stmt::Let(name.to_string(), expr))
+  }
+
+  pub fn return_stmt(expr: Option<Box<Expr>>) -> Box<Stmt> {
+    Box::new(Stmt::Return(expr))
+  }
+
+  pub fn break_stmt(label: Option<&str>, expr: Option<Box<Expr>>) -> Box<Stmt> {
+    Box::new(Stmt::Break(label.map(|l| l.to_string()), expr))
+  }
+
+  pub fn continue_stmt(label: Option<&str>) -> Box<Stmt> {
+    Box::new(Stmt::Continue(label.map(|l| l.to_string())))
+  }
+
+  pub fn expression_stmt(expr: Box<Expr>) -> Box<Stmt> {
+    Box::new(Stmt::Expression(expr))
+  }
+
+  pub fn log(args: Vec<Box<Expr>>) -> Box<Expr> {
+    Box::new(Expr::Log(args))
+  }
+
+  pub fn block(stmts: Vec<Box<Stmt>>, expr: Option<Box<Expr>>) -> Box<Expr> {
+    Box::new(Expr::Block(
+      stmts.into_iter().map(Located::from).collect(),
+      expr.map(Located::from),
+    ))
+  }
+
+  pub fn if_expr(
+    condition: Box<Expr>,
+    true_branch: Box<Expr>,
+    false_branch: Option<Box<Expr>>,
+  ) -> Box<Expr> {
+    Box::new(Expr::If(condition, true_branch, false_branch))
+  }
+
+  pub fn while_expr(condition: Box<Expr>, stmts: Vec<Box<Stmt>>, label: Option<&str>) -> Box<Expr> {
+    Box::new(Expr::While(
+      condition,
+      stmts.into_iter().map(Located::from).collect(),
+      label.map(|l| l.to_string()),
+    ))
+  }
+
+  pub fn match_expr(expr: Box<Expr>, arms: Vec<(Pattern, Box<Expr>)>) -> Box<Expr> {
+    Box::new(Expr::Match(expr, arms))
+  }
+
+  pub fn try_expr(body: Box<Expr>, name: &str, catch: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Try(body, name.to_string(), catch))
+  }
+
+  pub fn assign(target: Box<Expr>, op: AssignOp, expr: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Assign(target, op, expr))
+  }
+
+  pub fn binary(lhs: Box<Expr>, op: BinaryOp, rhs: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Binary(lhs, op, rhs))
+  }
+
+  pub fn unary(op: UnaryOp, expr: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Unary(op, expr))
+  }
+
+  pub fn property(expr: Box<Expr>, name: &str) -> Box<Expr> {
+    Box::new(Expr::Property(expr, name.to_string()))
+  }
+
+  pub fn index(expr: Box<Expr>, index: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Index(expr, index))
+  }
+
+  pub fn call(function: Box<Expr>, args: Vec<Box<Expr>>) -> Box<Expr> {
+    Box::new(Expr::Call(function, args))
+  }
+
+  pub fn function(params: &[&str], body: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Function(
+      params.iter().map(|p| p.to_string()).collect(),
+      body,
+    ))
+  }
+
+  pub fn unit() -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Unit))
+  }
+
+  pub fn boolean(value: bool) -> Box<Expr> {
+    Box::new(Expr::Constant(if value {
+      Constant::True
+    } else {
+      Constant::False
+    }))
+  }
+
+  pub fn ident(name: &str) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Ident(vec![], name.to_string())))
+  }
+
+  pub fn int(value: i64) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Int(value)))
+  }
+
+  pub fn number(value: f64) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Number(value)))
+  }
+
+  /// Creates a string constant from its contents.
+  ///
+  /// String constants from the parser still contain their surrounding quotation marks,
+  /// so they are added here too.
+  pub fn string(value: &str) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::String(format!("\"{}\"", value))))
+  }
+
+  pub fn none() -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::None))
+  }
+
+  pub fn some(expr: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Some(expr)))
+  }
+
+  pub fn tuple(items: Vec<Box<Expr>>) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Tuple(items)))
+  }
+
+  pub fn record(fields: Vec<(&str, Box<Expr>)>) -> Box<Expr> {
+    Box::new(Expr::Constant(Constant::Record(
+      fields
+        .into_iter()
+        .map(|(name, expr)| Field::Named(name.to_string(), expr))
+        .collect(),
+    )))
+  }
+}
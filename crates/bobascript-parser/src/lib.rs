@@ -1,6 +1,6 @@
 use std::{convert::From, fmt::Display, string::String};
 
-use ast::Ast;
+use ast::{Ast, Constant, Expr, Located};
 use lalrpop_util::{lalrpop_mod, ParseError};
 use thiserror::Error;
 
@@ -19,6 +19,8 @@ pub enum SyntaxError {
   ExtraToken(String),
   #[error("Invalid token.")]
   Invalid,
+  #[error("Expressions (or brackets) are nested more than {0} deep.")]
+  NestingTooDeep(usize),
 }
 type Result<T> = std::result::Result<T, SyntaxError>;
 
@@ -43,38 +45,285 @@ where
   }
 }
 
+/// Maps byte offsets in a piece of source code to the lines (and columns) they fall on.
+pub struct LineIndex {
+  /// The offset each line starts at.
+  starts: Vec<usize>,
+}
+impl LineIndex {
+  pub fn new(source: &str) -> Self {
+    let starts = std::iter::once(0)
+      .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+      .collect();
+    Self { starts }
+  }
+
+  /// Returns the (1-based) line the given byte offset is on.
+  pub fn line(&self, offset: usize) -> usize {
+    match self.starts.binary_search(&offset) {
+      Ok(idx) => idx + 1,
+      Err(idx) => idx,
+    }
+  }
+
+  /// Returns the (1-based) column the given byte offset is at, counted in bytes from the start of its line.
+  pub fn col(&self, offset: usize) -> usize {
+    offset - self.starts[self.line(offset) - 1] + 1
+  }
+
+  /// Wraps a node with the line and column of the byte offset it starts at.
+  pub fn locate<T>(&self, offset: usize, node: Box<T>) -> Located<T> {
+    Located::new(self.line(offset), self.col(offset), node)
+  }
+}
+
+/// Splits a string literal containing `${...}` into the literal parts of the string
+/// and the expressions embedded between them, or leaves it as a plain string if there aren't any.
+///
+/// The embedded expressions count towards how deeply the string (starting at `start`) is nested.
+pub(crate) fn interpolate(
+  literal: String,
+  start: usize,
+  nesting: &mut Nesting,
+) -> std::result::Result<Box<Expr>, &'static str> {
+  // strip the leading and trailing quotation mark off the string:
+  let raw = &literal[1..(literal.len() - 1)];
+  let mut parts = Vec::new();
+  let mut part_start = 0;
+  let mut chars = raw.char_indices().peekable();
+  let mut embedded_depth = 0;
+
+  while let Some((idx, c)) = chars.next() {
+    match c {
+      // escape sequences (including `\$`) are left for the compiler to deal with
+      '\\' => {
+        chars.next();
+      }
+      '$' if matches!(chars.peek(), Some((_, '{'))) => {
+        chars.next();
+        let mut depth = 1;
+        let end = loop {
+          match chars.next() {
+            Some((_, '{')) => depth += 1,
+            Some((end, '}')) if depth == 1 => break end,
+            Some((_, '}')) => depth -= 1,
+            Some(_) => {}
+            None => return Err("Unterminated interpolation in string."),
+          }
+        };
+
+        // the first part is always kept, even if empty, so that what follows is added onto a string
+        parts.push(string_part(&raw[part_start..idx]));
+        let mut embedded = Nesting::new(nesting.max_nesting);
+        let part = parse_embedded(&raw[(idx + 2)..end], &mut embedded);
+        nesting.too_deep |= embedded.too_deep;
+        parts.push(part?);
+        embedded_depth = embedded_depth.max(embedded.depth());
+        part_start = end + 1;
+      }
+      _ => {}
+    }
+  }
+
+  if parts.is_empty() {
+    return Ok(Box::new(Expr::Constant(Constant::String(literal))));
+  }
+  if part_start < raw.len() {
+    parts.push(string_part(&raw[part_start..]));
+  }
+  Ok(nesting.nest_over(start, embedded_depth, Box::new(Expr::Interpolation(parts))))
+}
+
+fn string_part(raw: &str) -> Box<Expr> {
+  Box::new(Expr::Constant(Constant::String(format!("\"{}\"", raw))))
+}
+
+/// Parses the source code between the braces of an interpolation, which must be a single expression.
+fn parse_embedded(
+  source: &str,
+  nesting: &mut Nesting,
+) -> std::result::Result<Box<Expr>, &'static str> {
+  let mut errors = Vec::new();
+  let lines = LineIndex::new(source);
+  match crate::grammar::AstParser::new().parse(&mut errors, &lines, nesting, source) {
+    Ok(Ast(stmts, Some(expr))) if stmts.is_empty() && errors.is_empty() => Ok(expr.node),
+    _ => Err("Expected an expression within \"${...}\"."),
+  }
+}
+
+/// How deeply nodes may be nested by default, which keeps the recursion involved in
+/// compiling (and dropping) the resulting tree well within the limits of the stack.
+pub const DEFAULT_MAX_NESTING: usize = 512;
+
+/// Keeps track of how deeply the nodes built while parsing are nested, so that a tree
+/// too deep to be walked without overflowing the stack is never built in the first place.
+///
+/// The generated parser doesn't recurse, and builds each node after every node within it,
+/// so this only needs to keep the nodes that aren't within another one yet, along with where
+/// they start: any of them starting after a new node does are within it.
+pub struct Nesting {
+  max_nesting: usize,
+  /// Where each node that isn't within another one yet starts, along with how deeply it's nested.
+  nodes: Vec<(usize, usize)>,
+  too_deep: bool,
+}
+impl Nesting {
+  pub fn new(max_nesting: usize) -> Self {
+    Self {
+      max_nesting,
+      nodes: Vec::new(),
+      too_deep: false,
+    }
+  }
+
+  /// Returns whether any node was nested deeper than the limit, in which case it was replaced with [Expr::Error].
+  pub fn too_deep(&self) -> bool {
+    self.too_deep
+  }
+
+  /// Records a node starting at the given offset, which is nested one level deeper than the nodes within it.
+  pub(crate) fn nest(&mut self, start: usize, node: Box<Expr>) -> Box<Expr> {
+    self.nest_over(start, 0, node)
+  }
+
+  /// Records a node like [Nesting::nest], where the node also contains a tree of the given depth
+  /// that was parsed on its own (such as an expression embedded in a string).
+  fn nest_over(&mut self, start: usize, inner: usize, node: Box<Expr>) -> Box<Expr> {
+    let mut depth = inner;
+    while let Some(&(node_start, node_depth)) = self.nodes.last() {
+      if node_start < start {
+        break;
+      }
+      depth = depth.max(node_depth);
+      self.nodes.pop();
+    }
+
+    if depth >= self.max_nesting {
+      // the node is dropped here, which is still safe since what's within it isn't too deep
+      self.too_deep = true;
+      return Box::new(Expr::Error);
+    }
+    self.nodes.push((start, depth + 1));
+    node
+  }
+
+  /// Returns how deeply the deepest node so far is nested.
+  fn depth(&self) -> usize {
+    self
+      .nodes
+      .iter()
+      .map(|&(_, depth)| depth)
+      .max()
+      .unwrap_or(0)
+  }
+}
+
 pub trait Parser<T> {
-  fn parse_ast(input: &'_ str) -> Result<T>;
+  /// Parses the given source code, returning the last syntax error found if there are any.
+  fn parse_ast(input: &'_ str) -> Result<T> {
+    Self::parse_ast_with_max_nesting(input, DEFAULT_MAX_NESTING)
+  }
+
+  /// Parses like [Parser::parse_ast], but with a different limit on how deeply brackets may be nested.
+  fn parse_ast_with_max_nesting(input: &'_ str, max_nesting: usize) -> Result<T> {
+    Self::parse_ast_all_with_max_nesting(input, max_nesting)
+      .map_err(|mut errors| errors.pop().unwrap())
+  }
+
+  /// Parses like [Parser::parse_ast], but returns every syntax error found (in the order they
+  /// were found) instead of only the last, so that they can all be shown at once.
+  fn parse_ast_all(input: &'_ str) -> std::result::Result<T, Vec<SyntaxError>> {
+    Self::parse_ast_all_with_max_nesting(input, DEFAULT_MAX_NESTING)
+  }
+
+  /// Parses like [Parser::parse_ast_all], but with a different limit on how deeply brackets may be nested.
+  ///
+  /// Whenever this returns an error, there's at least one in the list.
+  fn parse_ast_all_with_max_nesting(
+    input: &'_ str,
+    max_nesting: usize,
+  ) -> std::result::Result<T, Vec<SyntaxError>>;
 }
 impl Parser<Ast> for crate::grammar::AstParser {
-  fn parse_ast(input: &'_ str) -> Result<Ast> {
+  fn parse_ast_all_with_max_nesting(
+    input: &'_ str,
+    max_nesting: usize,
+  ) -> std::result::Result<Ast, Vec<SyntaxError>> {
     let parser = crate::grammar::AstParser::new();
     let mut errors = Vec::new();
-    let expr = parser.parse(&mut errors, input);
+    let lines = LineIndex::new(input);
+    let mut nesting = Nesting::new(max_nesting);
+    let expr = parser.parse(&mut errors, &lines, &mut nesting, input);
+    if nesting.too_deep() {
+      return Err(vec![SyntaxError::NestingTooDeep(max_nesting)]);
+    }
 
-    if errors.is_empty() {
-      Ok(expr.unwrap())
-    } else {
-      Err(errors.pop().unwrap().into())
+    match expr {
+      Ok(expr) if errors.is_empty() => Ok(expr),
+      // errors that can't be recovered from are returned rather than added to the list
+      Ok(_) => Err(errors.into_iter().map(SyntaxError::from).collect()),
+      Err(error) => {
+        let mut errors: Vec<SyntaxError> = errors.into_iter().map(SyntaxError::from).collect();
+        errors.push(error.into());
+        Err(errors)
+      }
     }
   }
 }
 
 mod tests {
   #![allow(unused_imports)]
-  use crate::{ast::Expr, grammar::AstParser, Parser};
+  use crate::{ast::Expr, grammar::AstParser, Parser, SyntaxError};
 
   #[test]
   fn parse_function_stmt() {
     let stmt = AstParser::parse_ast("fn test() { 3 };").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      r#"Ast([Function("test", [], Block([], Some(Constant(Number(3.0)))))], None)"#
+      r#"Ast([Function("test", [], Block([], Some(Constant(Int(3)))), false)], None)"#
     );
     let stmt = AstParser::parse_ast("fn test(t1, t2, t3,) { 3 };").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      r#"Ast([Function("test", ["t1", "t2", "t3"], Block([], Some(Constant(Number(3.0)))))], None)"#
+      r#"Ast([Function("test", ["t1", "t2", "t3"], Block([], Some(Constant(Int(3)))), false)], None)"#
+    );
+    let stmt = AstParser::parse_ast("fn test(t1) => t1 * 2;").unwrap();
+    assert_eq!(
+      &format!("{:?}", stmt),
+      r#"Ast([Function("test", ["t1"], Block([], Some(Binary(Constant(Ident([], "t1")), Multiply, Constant(Int(2))))), false)], None)"#
+    );
+    let stmt = AstParser::parse_ast("pure fn test(t1) => t1;").unwrap();
+    assert_eq!(
+      &format!("{:?}", stmt),
+      r#"Ast([Function("test", ["t1"], Block([], Some(Constant(Ident([], "t1")))), true)], None)"#
+    );
+  }
+
+  #[test]
+  fn parse_function_expr() {
+    let expr = AstParser::parse_ast("fn(a, b) { a }").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Function(["a", "b"], Block([], Some(Constant(Ident([], "a")))))))"#
+    );
+    let expr = AstParser::parse_ast("let f = fn(x) => x + 1;").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([Let("f", Some(Function(["x"], Block([], Some(Binary(Constant(Ident([], "x")), Add, Constant(Int(1))))))))], None)"#
+    );
+  }
+
+  #[test]
+  fn parse_conditional_expr() {
+    let expr = AstParser::parse_ast("a ? b : c ? d : e").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(If(Constant(Ident([], "a")), Block([], Some(Constant(Ident([], "b")))), Some(Block([], Some(If(Constant(Ident([], "c")), Block([], Some(Constant(Ident([], "d")))), Some(Block([], Some(Constant(Ident([], "e"))))))))))))"#
+    );
+    let expr = AstParser::parse_ast("x = a or b ? 1 : 2;").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([Expression(Assign(Constant(Ident([], "x")), Assign, If(Binary(Constant(Ident([], "a")), Or, Constant(Ident([], "b"))), Block([], Some(Constant(Int(1)))), Some(Block([], Some(Constant(Int(2))))))))], None)"#
     );
   }
 
@@ -83,19 +332,19 @@ mod tests {
     let stmt = AstParser::parse_ast("const test = 5.2 * 3;").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      r#"Ast([Const("test", Binary(Constant(Number(5.2)), Multiply, Constant(Number(3.0))))], None)"#
+      r#"Ast([Const("test", Binary(Constant(Number(5.2)), Multiply, Constant(Int(3))))], None)"#
     );
 
     let stmt = AstParser::parse_ast("let test = 5.2 * 3;").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      r#"Ast([Let("test", Some(Binary(Constant(Number(5.2)), Multiply, Constant(Number(3.0)))))], None)"#
+      r#"Ast([Let("test", Some(Binary(Constant(Number(5.2)), Multiply, Constant(Int(3)))))], None)"#
     );
 
     let stmt = AstParser::parse_ast("let test = 22.5 * if true {3} else {4};").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      r#"Ast([Let("test", Some(Binary(Constant(Number(22.5)), Multiply, If(Constant(True), Block([], Some(Constant(Number(3.0)))), Some(Block([], Some(Constant(Number(4.0)))))))))], None)"#
+      r#"Ast([Let("test", Some(Binary(Constant(Number(22.5)), Multiply, If(Constant(True), Block([], Some(Constant(Int(3)))), Some(Block([], Some(Constant(Int(4)))))))))], None)"#
     );
 
     let stmt = AstParser::parse_ast("let test;").unwrap();
@@ -116,7 +365,7 @@ mod tests {
     let stmt = AstParser::parse_ast("22.5 * (44 + 66);").unwrap();
     assert_eq!(
       &format!("{:?}", stmt),
-      "Ast([Expression(Binary(Constant(Number(22.5)), Multiply, Binary(Constant(Number(44.0)), Add, Constant(Number(66.0)))))], None)"
+      "Ast([Expression(Binary(Constant(Number(22.5)), Multiply, Binary(Constant(Int(44)), Add, Constant(Int(66)))))], None)"
     );
   }
 
@@ -125,7 +374,7 @@ mod tests {
     let expr = AstParser::parse_ast("{15 + 1; 3}").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      "Ast([], Some(Block([Expression(Binary(Constant(Number(15.0)), Add, Constant(Number(1.0))))], Some(Constant(Number(3.0))))))"
+      "Ast([], Some(Block([Expression(Binary(Constant(Int(15)), Add, Constant(Int(1))))], Some(Constant(Int(3))))))"
     );
   }
 
@@ -134,13 +383,13 @@ mod tests {
     let expr = AstParser::parse_ast(r#"if 3 == "3" {15 + 1; 3}"#).unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(If(Binary(Constant(Number(3.0)), Equal, Constant(String("\"3\""))), Block([Expression(Binary(Constant(Number(15.0)), Add, Constant(Number(1.0))))], Some(Constant(Number(3.0)))), None)))"#
+      r#"Ast([], Some(If(Binary(Constant(Int(3)), Equal, Constant(String("\"3\""))), Block([Expression(Binary(Constant(Int(15)), Add, Constant(Int(1))))], Some(Constant(Int(3)))), None)))"#
     );
 
     let expr = AstParser::parse_ast("if 3 {3} else if 6 {6}").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      "Ast([], Some(If(Constant(Number(3.0)), Block([], Some(Constant(Number(3.0)))), Some(If(Constant(Number(6.0)), Block([], Some(Constant(Number(6.0)))), None)))))"
+      "Ast([], Some(If(Constant(Int(3)), Block([], Some(Constant(Int(3)))), Some(If(Constant(Int(6)), Block([], Some(Constant(Int(6)))), None)))))"
     );
   }
 
@@ -149,7 +398,47 @@ mod tests {
     let expr = AstParser::parse_ast("while true {15 + 1;}").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      "Ast([], Some(While(Constant(True), [Expression(Binary(Constant(Number(15.0)), Add, Constant(Number(1.0))))])))"
+      "Ast([], Some(While(Constant(True), [Expression(Binary(Constant(Int(15)), Add, Constant(Int(1))))], None)))"
+    );
+  }
+
+  #[test]
+  fn parse_labeled_while_expr() {
+    let expr = AstParser::parse_ast("'outer: while true { break 'outer; continue; }").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(While(Constant(True), [Break(Some("outer"), None), Continue(None)], Some("outer"))))"#
+    );
+  }
+
+  #[test]
+  fn parse_break_with_value() {
+    let expr = AstParser::parse_ast("while true { break 'outer 5; break 6; }").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(While(Constant(True), [Break(Some("outer"), Some(Constant(Int(5)))), Break(None, Some(Constant(Int(6))))], None)))"#
+    );
+  }
+
+  #[test]
+  fn parse_block_stmts() {
+    let expr = AstParser::parse_ast("if c {} f();").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([Expression(If(Constant(Ident([], "c")), Block([], None), None)), Expression(Call(Constant(Ident([], "f")), []))], None)"#
+    );
+
+    let expr = AstParser::parse_ast("let x = if c {1} else {2};").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([Let("x", Some(If(Constant(Ident([], "c")), Block([], Some(Constant(Int(1)))), Some(Block([], Some(Constant(Int(2))))))))], None)"#
+    );
+
+    // a block at the start of a statement ends it, instead of being the left side of an operator
+    let expr = AstParser::parse_ast("{1} -1").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      "Ast([Expression(Block([], Some(Constant(Int(1)))))], Some(Unary(Negate, Constant(Int(1)))))"
     );
   }
 
@@ -158,7 +447,41 @@ mod tests {
     let expr = AstParser::parse_ast(r#"log(a = "arg")"#).unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Log(Assign(Constant(Ident("a")), Assign, Constant(String("\"arg\""))))))"#
+      r#"Ast([], Some(Log([Assign(Constant(Ident([], "a")), Assign, Constant(String("\"arg\"")))])))"#
+    );
+  }
+
+  #[test]
+  fn parse_multi_log_expr() {
+    let expr = AstParser::parse_ast(r#"log("x =", x, 1)"#).unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Log([Constant(String("\"x =\"")), Constant(Ident([], "x")), Constant(Int(1))])))"#
+    );
+  }
+
+  #[test]
+  fn parse_interpolated_string() {
+    let expr = AstParser::parse_ast(r#""a ${x + 1} b""#).unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Interpolation([Constant(String("\"a \"")), Binary(Constant(Ident([], "x")), Add, Constant(Int(1))), Constant(String("\" b\""))])))"#
+    );
+    assert_eq!(&expr.to_string(), r#""a ${x + 1} b""#);
+
+    let expr = AstParser::parse_ast(r#""a \${x} b""#).unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Constant(String("\"a \\${x} b\""))))"#
+    );
+  }
+
+  #[test]
+  fn parse_collect_expr() {
+    let expr = AstParser::parse_ast("'a: collect while true { 1 }").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Collect(Constant(True), Block([], Some(Constant(Int(1)))), Some("a"))))"#
     );
   }
 
@@ -167,12 +490,12 @@ mod tests {
     let expr = AstParser::parse_ast("a = 5").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Assign(Constant(Ident("a")), Assign, Constant(Number(5.0)))))"#
+      r#"Ast([], Some(Assign(Constant(Ident([], "a")), Assign, Constant(Int(5)))))"#
     );
     let expr = AstParser::parse_ast("a *= b = 5").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Assign(Constant(Ident("a")), MultiplyAssign, Assign(Constant(Ident("b")), Assign, Constant(Number(5.0))))))"#
+      r#"Ast([], Some(Assign(Constant(Ident([], "a")), MultiplyAssign, Assign(Constant(Ident([], "b")), Assign, Constant(Int(5))))))"#
     );
   }
 
@@ -181,7 +504,7 @@ mod tests {
     let expr = AstParser::parse_ast("22.5 * -44 + 66").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      "Ast([], Some(Binary(Binary(Constant(Number(22.5)), Multiply, Unary(Negate, Constant(Number(44.0)))), Add, Constant(Number(66.0)))))"
+      "Ast([], Some(Binary(Binary(Constant(Number(22.5)), Multiply, Unary(Negate, Constant(Int(44)))), Add, Constant(Int(66)))))"
     );
   }
 
@@ -190,7 +513,7 @@ mod tests {
     let expr = AstParser::parse_ast("test(3 * 5, 4,)").unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Call(Constant(Ident("test")), [Binary(Constant(Number(3.0)), Multiply, Constant(Number(5.0))), Constant(Number(4.0))])))"#
+      r#"Ast([], Some(Call(Constant(Ident([], "test")), [Binary(Constant(Int(3)), Multiply, Constant(Int(5))), Constant(Int(4))])))"#
     );
   }
 
@@ -199,14 +522,136 @@ mod tests {
     let expr = AstParser::parse_ast(r#"#[1, 3, 5, #["test", "I hope this works!!"]]"#).unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Constant(Tuple([Constant(Number(1.0)), Constant(Number(3.0)), Constant(Number(5.0)), Constant(Tuple([Constant(String("\"test\"")), Constant(String("\"I hope this works!!\""))]))]))))"#
+      r#"Ast([], Some(Constant(Tuple([Constant(Int(1)), Constant(Int(3)), Constant(Int(5)), Constant(Tuple([Constant(String("\"test\"")), Constant(String("\"I hope this works!!\""))]))]))))"#
     );
 
     let expr =
       AstParser::parse_ast(r#"#[1, 3, 5, #["test", "I hope this works!!"]][3][1]"#).unwrap();
     assert_eq!(
       &format!("{:?}", expr),
-      r#"Ast([], Some(Index(Index(Constant(Tuple([Constant(Number(1.0)), Constant(Number(3.0)), Constant(Number(5.0)), Constant(Tuple([Constant(String("\"test\"")), Constant(String("\"I hope this works!!\""))]))])), Constant(Number(3.0))), Constant(Number(1.0)))))"#
+      r#"Ast([], Some(Index(Index(Constant(Tuple([Constant(Int(1)), Constant(Int(3)), Constant(Int(5)), Constant(Tuple([Constant(String("\"test\"")), Constant(String("\"I hope this works!!\""))]))])), Constant(Int(3))), Constant(Int(1)))))"#
+    );
+  }
+
+  #[test]
+  fn display_expr() {
+    let ast =
+      AstParser::parse_ast("a *= (1 + 2) * -3 ^ 2 - (4 - 5) || !b.c[0](d, #[e, f,])").unwrap();
+    assert_eq!(
+      ast.to_string(),
+      "a *= (1 + 2) * -3 ^ 2 - (4 - 5) || !b.c[0](d, #[e, f])"
+    );
+  }
+
+  #[test]
+  fn display_stmt() {
+    let ast = AstParser::parse_ast(
+      r#"fn test(a, b) { let c = a; if c { return b; } else { log("no") } }; const d = 1; d"#,
+    )
+    .unwrap();
+    assert_eq!(
+      ast.to_string(),
+      r#"fn test(a, b) { let c = a; if c { return b; } else { log("no") } }; const d = 1; d"#
+    );
+  }
+
+  #[test]
+  fn parse_empty_stmts() {
+    let expr = AstParser::parse_ast(";;; let x = 1 ;;;").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([Empty, Empty, Empty, Let("x", Some(Constant(Int(1)))), Empty, Empty], None)"#
+    );
+  }
+
+  #[test]
+  fn parse_match_expr() {
+    let expr = AstParser::parse_ast(r#"match x { 1 => "one", #[a, _] => a, _ => 0, }"#).unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Match(Constant(Ident([], "x")), [(Literal(Int(1)), Constant(String("\"one\""))), (Tuple([Binding("a"), Wildcard]), Constant(Ident([], "a"))), (Wildcard, Constant(Int(0)))])))"#
+    );
+  }
+
+  #[test]
+  fn parse_try_expr() {
+    let expr = AstParser::parse_ast("try { 1 / x } catch err { err.kind }").unwrap();
+    assert_eq!(
+      &format!("{:?}", expr),
+      r#"Ast([], Some(Try(Block([], Some(Binary(Constant(Int(1)), Divide, Constant(Ident([], "x"))))), "err", Block([], Some(Property(Constant(Ident([], "err")), "kind"))))))"#
     );
   }
+
+  #[test]
+  fn parse_deeply_nested() {
+    let source = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert!(matches!(
+      AstParser::parse_ast(&source),
+      Err(SyntaxError::NestingTooDeep(crate::DEFAULT_MAX_NESTING))
+    ));
+    assert!(AstParser::parse_ast_with_max_nesting(&source, 10).is_err());
+
+    let source = format!("{}1{}", "(".repeat(50), ")".repeat(50));
+    assert!(AstParser::parse_ast(&source).is_ok());
+    // brackets inside strings and comments don't count
+    let source = format!(r#""{}" // {}"#, "(".repeat(300), "[".repeat(300));
+    assert!(AstParser::parse_ast(&source).is_ok());
+    // unless they're in an embedded expression
+    let source = format!(r#""${{{}1{}}}""#, "(".repeat(100_000), ")".repeat(100_000));
+    assert!(matches!(
+      AstParser::parse_ast(&source),
+      Err(SyntaxError::NestingTooDeep(crate::DEFAULT_MAX_NESTING))
+    ));
+    let source = format!(r#""${{{}1{}}}""#, "(".repeat(50), ")".repeat(50));
+    assert!(AstParser::parse_ast(&source).is_ok());
+  }
+
+  #[test]
+  fn parse_long_chains() {
+    for chain in &["1 + ", "c or ", "c and ", "-", "!", "a."] {
+      let source = format!("{}1", chain.repeat(100_000));
+      assert!(matches!(
+        AstParser::parse_ast(&source),
+        Err(SyntaxError::NestingTooDeep(crate::DEFAULT_MAX_NESTING))
+      ));
+    }
+    let source = format!("f{}", "()".repeat(100_000));
+    assert!(matches!(
+      AstParser::parse_ast(&source),
+      Err(SyntaxError::NestingTooDeep(crate::DEFAULT_MAX_NESTING))
+    ));
+    let source = format!("{}1", "if c {} else ".repeat(100_000));
+    assert!(AstParser::parse_ast(&source).is_err());
+
+    let source = format!("{}1", "1 + ".repeat(200));
+    assert!(AstParser::parse_ast(&source).is_ok());
+    // chains end between statements and items, however they're separated
+    let source = "let a = 1 + 2 + 3;\nif a == 6 { a } else { 0 }\n".repeat(1000);
+    assert!(AstParser::parse_ast(&source).is_ok());
+    let source = format!("#[{}]", "1 + 2, ".repeat(1000));
+    assert!(AstParser::parse_ast(&source).is_ok());
+  }
+
+  #[test]
+  fn parse_statement_lines() {
+    let ast = AstParser::parse_ast("let a = 1;\n\nlet b = 2;\n  a + b").unwrap();
+    let lines: Vec<usize> = ast.0.iter().map(|stmt| stmt.line).collect();
+    assert_eq!(lines, vec![1, 3]);
+    assert_eq!(ast.1.unwrap().line, 4);
+  }
+
+  #[test]
+  fn parse_multiple_errors() {
+    let errors = AstParser::parse_ast_all("let a = );\nlet b = ];\nlet c = 3;").unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(&errors[0], SyntaxError::UnexpectedToken(_, token) if token == ")"));
+    assert!(matches!(&errors[1], SyntaxError::UnexpectedToken(_, token) if token == "]"));
+
+    // the single error version keeps the last one
+    assert!(matches!(
+      AstParser::parse_ast("let a = );\nlet b = ];"),
+      Err(SyntaxError::UnexpectedToken(_, token)) if token == "]"
+    ));
+    assert!(AstParser::parse_ast_all("let a = 1;").is_ok());
+  }
 }
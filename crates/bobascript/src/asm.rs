@@ -0,0 +1,355 @@
+use std::{collections::HashMap, rc::Rc, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+  chunk::{Chunk, JumpDirection, OpCode, Upvalue},
+  compiler::unescape,
+  value::{Function, Value},
+};
+
+#[derive(Error, Debug, Clone)]
+pub enum AsmError {
+  #[error("Unknown instruction \"{1}\" on line {0}.")]
+  UnknownInstruction(usize, String),
+  #[error("Missing operand on line {0}.")]
+  MissingOperand(usize),
+  #[error("Invalid operand \"{1}\" on line {0}.")]
+  InvalidOperand(usize, String),
+  #[error("Invalid constant \"{1}\" on line {0}.")]
+  InvalidConstant(usize, String),
+  #[error("The label \"{1}\" jumped to on line {0} is never defined.")]
+  UndefinedLabel(usize, String),
+  #[error("The label \"{1}\" on line {0} has already been defined.")]
+  DuplicateLabel(usize, String),
+  #[error("The function starting on line {0} is never ended.")]
+  UnterminatedFunction(usize),
+  #[error("Found \".end\" on line {0} outside of a function.")]
+  UnexpectedEnd(usize),
+}
+
+/// Assembles a chunk from its textual form, as written by [to_assembly].
+///
+/// Each line holds either one instruction (like `Constant 0` or `Jump L0`), a label (like `L0:`),
+/// or a directive: `.const` adds a constant, `.line` sets the source line of the instructions after it,
+/// and `.function "name" arity` adds a function constant whose chunk runs until the next `.end`.
+/// Anything after a `;` is a comment.
+pub fn assemble(text: &str) -> Result<Chunk, AsmError> {
+  let mut lines = text
+    .lines()
+    .enumerate()
+    .map(|(idx, line)| (idx + 1, strip_comment(line).trim()));
+  match assemble_chunk(&mut lines)? {
+    (chunk, None) => Ok(chunk),
+    (_, Some(line)) => Err(AsmError::UnexpectedEnd(line)),
+  }
+}
+
+/// Writes out a chunk (along with the chunks of any functions within it) in the textual form read by [assemble].
+pub fn to_assembly(chunk: &Chunk) -> String {
+  let mut text = String::new();
+  write_chunk(&mut text, chunk, "");
+  text
+}
+
+/// Assembles lines up until the end of the text or an `.end`, returning the line of the latter if there was one.
+fn assemble_chunk<'a, I>(lines: &mut I) -> Result<(Chunk, Option<usize>), AsmError>
+where
+  I: Iterator<Item = (usize, &'a str)>,
+{
+  let mut chunk = Chunk::default();
+  let mut labels = HashMap::new();
+  // jumps are given their distances once every label is known
+  let mut jumps = Vec::new();
+  let mut source_line = 0;
+
+  while let Some((line, text)) = lines.next() {
+    if text.is_empty() {
+      continue;
+    }
+    if let Some(label) = text.strip_suffix(':') {
+      if labels.insert(label, chunk.code.len()).is_some() {
+        return Err(AsmError::DuplicateLabel(line, label.to_string()));
+      }
+      continue;
+    }
+
+    let (name, operands) = split_word(text);
+    match name {
+      ".end" => return resolve_jumps(chunk, &labels, jumps).map(|chunk| (chunk, Some(line))),
+      ".line" => source_line = parse_operand(line, operands)?,
+      ".const" => {
+        chunk.add_constant(parse_constant(line, operands)?);
+      }
+      ".function" => {
+        let (name, arity) = operands
+          .rsplit_once(char::is_whitespace)
+          .ok_or(AsmError::MissingOperand(line))?;
+        let name = parse_string(line, name.trim())?;
+        let arity = parse_operand(line, arity)?;
+        let function = match assemble_chunk(lines)? {
+          (chunk, Some(_)) => Function { arity, chunk, name },
+          (_, None) => return Err(AsmError::UnterminatedFunction(line)),
+        };
+        chunk.add_constant(Value::Function(Rc::new(function)));
+      }
+      "Jump" | "JumpIfFalse" | "Try" => {
+        if operands.is_empty() {
+          return Err(AsmError::MissingOperand(line));
+        }
+        let ip = chunk.write(OpCode::Jump(JumpDirection::Forwards, 0), source_line);
+        jumps.push((ip, name, operands, line));
+      }
+      _ => {
+        chunk.write(parse_instruction(line, name, operands)?, source_line);
+      }
+    }
+  }
+
+  resolve_jumps(chunk, &labels, jumps).map(|chunk| (chunk, None))
+}
+
+/// Replaces each placeholder jump with one that lands on its label.
+fn resolve_jumps(
+  mut chunk: Chunk,
+  labels: &HashMap<&str, usize>,
+  jumps: Vec<(usize, &str, &str, usize)>,
+) -> Result<Chunk, AsmError> {
+  for (ip, name, label, line) in jumps {
+    let target = *labels
+      .get(label)
+      .ok_or_else(|| AsmError::UndefinedLabel(line, label.to_string()))?;
+    // jumps are taken once the instruction pointer has already moved past them
+    chunk.code[ip].0 = match (target > ip, name) {
+      (true, "JumpIfFalse") => OpCode::JumpIfFalse(target - ip - 1),
+      (true, "Try") => OpCode::Try(target - ip - 1),
+      (true, _) => OpCode::Jump(JumpDirection::Forwards, target - ip - 1),
+      (false, "Jump") => OpCode::Jump(JumpDirection::Backwards, ip + 1 - target),
+      // conditional jumps (and error handlers) can only go forwards
+      (false, _) => return Err(AsmError::InvalidOperand(line, label.to_string())),
+    };
+  }
+  Ok(chunk)
+}
+
+fn parse_instruction(line: usize, name: &str, operands: &str) -> Result<OpCode, AsmError> {
+  let opcode = match name {
+    "Unit" => OpCode::Unit,
+    "None" => OpCode::None,
+    "Some" => OpCode::Some,
+    "True" => OpCode::True,
+    "False" => OpCode::False,
+    "Pop" => OpCode::Pop,
+    "PopResult" => OpCode::PopResult,
+    "Equal" => OpCode::Equal,
+    "GreaterThan" => OpCode::GreaterThan,
+    "LessThan" => OpCode::LessThan,
+    "Add" => OpCode::Add,
+    "Subtract" => OpCode::Subtract,
+    "Multiply" => OpCode::Multiply,
+    "Divide" => OpCode::Divide,
+    "Modulo" => OpCode::Modulo,
+    "Exponent" => OpCode::Exponent,
+    "Not" => OpCode::Not,
+    "Negate" => OpCode::Negate,
+    "Index" => OpCode::Index,
+    "SetIndex" => OpCode::SetIndex,
+    "Append" => OpCode::Append,
+    "Extend" => OpCode::Extend,
+    "MatchSome" => OpCode::MatchSome,
+    "IsUnit" => OpCode::IsUnit,
+    "Unwrap" => OpCode::Unwrap,
+    "RecordLen" => OpCode::RecordLen,
+    "NoMatch" => OpCode::NoMatch,
+    "EndTry" => OpCode::EndTry,
+    "CloseUpvalue" => OpCode::CloseUpvalue,
+    "Return" => OpCode::Return,
+    "ReturnUnit" => OpCode::ReturnUnit,
+    "Tuple" => return parse_operand(line, operands).map(OpCode::Tuple),
+    "Record" => return parse_operand(line, operands).map(OpCode::Record),
+    "Constant" => return parse_operand(line, operands).map(OpCode::Constant),
+    "PopN" => return parse_operand(line, operands).map(OpCode::PopN),
+    "DefineGlobal" => return parse_operand(line, operands).map(OpCode::DefineGlobal),
+    "GetLocal" => return parse_operand(line, operands).map(OpCode::GetLocal),
+    "SetLocal" => return parse_operand(line, operands).map(OpCode::SetLocal),
+    "GetGlobal" => return parse_operand(line, operands).map(OpCode::GetGlobal),
+    "SetGlobal" => return parse_operand(line, operands).map(OpCode::SetGlobal),
+    "GetUpvalue" => return parse_operand(line, operands).map(OpCode::GetUpvalue),
+    "SetUpvalue" => return parse_operand(line, operands).map(OpCode::SetUpvalue),
+    "GetProperty" => return parse_string(line, operands).map(OpCode::GetProperty),
+    "SetProperty" => return parse_string(line, operands).map(OpCode::SetProperty),
+    "HasProperty" => return parse_string(line, operands).map(OpCode::HasProperty),
+    "Log" => return parse_operand(line, operands).map(OpCode::Log),
+    "MatchTuple" => return parse_operand(line, operands).map(OpCode::MatchTuple),
+    "Call" => return parse_operand(line, operands).map(OpCode::Call),
+    "Closure" => {
+      let (idx, captures) = split_word(operands);
+      let idx = parse_operand(line, idx)?;
+      return parse_captures(line, captures).map(|captures| OpCode::Closure(idx, captures));
+    }
+    _ => return Err(AsmError::UnknownInstruction(line, name.to_string())),
+  };
+
+  if operands.is_empty() {
+    Ok(opcode)
+  } else {
+    Err(AsmError::InvalidOperand(line, operands.to_string()))
+  }
+}
+
+/// Parses the variables a closure captures, written as pairs like `local 1 upvalue 0`.
+fn parse_captures(line: usize, text: &str) -> Result<Vec<Upvalue>, AsmError> {
+  let words: Vec<&str> = text.split_whitespace().collect();
+  words
+    .chunks(2)
+    .map(|capture| match capture {
+      ["local", slot] => parse_operand(line, slot).map(Upvalue::Local),
+      ["upvalue", idx] => parse_operand(line, idx).map(Upvalue::Upvalue),
+      _ => Err(AsmError::InvalidOperand(line, capture.join(" "))),
+    })
+    .collect()
+}
+
+fn parse_operand<T: FromStr>(line: usize, text: &str) -> Result<T, AsmError> {
+  if text.is_empty() {
+    return Err(AsmError::MissingOperand(line));
+  }
+  text
+    .parse()
+    .map_err(|_| AsmError::InvalidOperand(line, text.to_string()))
+}
+
+fn parse_constant(line: usize, text: &str) -> Result<Value, AsmError> {
+  match text {
+    "" => Err(AsmError::MissingOperand(line)),
+    "()" => Ok(Value::Unit),
+    "true" => Ok(Value::Boolean(true)),
+    "false" => Ok(Value::Boolean(false)),
+    _ if text.starts_with('"') => parse_string(line, text).map(Value::String),
+    // numbers without a decimal point are integers, as they are in scripts
+    _ => text
+      .parse()
+      .map(Value::Int)
+      .or_else(|_| text.parse().map(Value::Number))
+      .map_err(|_| AsmError::InvalidConstant(line, text.to_string())),
+  }
+}
+
+/// Parses a quoted string, with the same escape sequences as string literals in scripts.
+fn parse_string(line: usize, text: &str) -> Result<String, AsmError> {
+  if text.is_empty() {
+    return Err(AsmError::MissingOperand(line));
+  }
+  let invalid = || AsmError::InvalidOperand(line, text.to_string());
+  let raw = text
+    .strip_prefix('"')
+    .and_then(|text| text.strip_suffix('"'))
+    .ok_or_else(invalid)?;
+  unescape(raw).map_err(|_| invalid())
+}
+
+/// Splits the first word off of a line, returning it along with the rest of the line.
+fn split_word(text: &str) -> (&str, &str) {
+  match text.split_once(char::is_whitespace) {
+    Some((word, rest)) => (word, rest.trim()),
+    None => (text, ""),
+  }
+}
+
+/// Removes the comment (if any) from the end of a line, ignoring semicolons within strings.
+fn strip_comment(line: &str) -> &str {
+  let mut in_string = false;
+  let mut escaped = false;
+  for (idx, c) in line.char_indices() {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' if in_string => escaped = true,
+      '"' => in_string = !in_string,
+      ';' if !in_string => return &line[..idx],
+      _ => {}
+    }
+  }
+  line
+}
+
+fn write_chunk(text: &mut String, chunk: &Chunk, indent: &str) {
+  for constant in &chunk.constants {
+    match constant {
+      Value::Function(function) => {
+        let name = Value::String(function.name.clone()).repr();
+        text.push_str(&format!(
+          "{}.function {} {}\n",
+          indent, name, function.arity
+        ));
+        write_chunk(text, &function.chunk, &format!("{}  ", indent));
+        text.push_str(&format!("{}.end\n", indent));
+      }
+      // floats are written so that they always have a decimal point (or are `inf` or `NaN`)
+      Value::Number(num) => text.push_str(&format!("{}.const {:?}\n", indent, num)),
+      constant => text.push_str(&format!("{}.const {}\n", indent, constant.repr())),
+    }
+  }
+
+  // every instruction that's jumped to is given a label, numbered in order
+  let mut targets: Vec<usize> = chunk
+    .code
+    .iter()
+    .enumerate()
+    .filter_map(|(ip, (opcode, _))| jump_target(ip, opcode))
+    .collect();
+  targets.sort_unstable();
+  targets.dedup();
+  let label = |target: Option<usize>| match target.map(|target| targets.binary_search(&target)) {
+    Some(Ok(idx)) => format!("L{}", idx),
+    _ => "?".to_string(),
+  };
+
+  let mut source_line = None;
+  for (ip, (opcode, line)) in chunk.code.iter().enumerate() {
+    if targets.binary_search(&ip).is_ok() {
+      text.push_str(&format!("{}{}:\n", indent, label(Some(ip))));
+    }
+    if source_line != Some(*line) {
+      text.push_str(&format!("{}  .line {}\n", indent, line));
+      source_line = Some(*line);
+    }
+
+    let instruction = match opcode {
+      OpCode::Jump(..) => format!("Jump {}", label(jump_target(ip, opcode))),
+      OpCode::JumpIfFalse(_) => format!("JumpIfFalse {}", label(jump_target(ip, opcode))),
+      OpCode::Try(_) => format!("Try {}", label(jump_target(ip, opcode))),
+      OpCode::GetProperty(name) => format!("GetProperty {}", Value::String(name.clone()).repr()),
+      OpCode::SetProperty(name) => format!("SetProperty {}", Value::String(name.clone()).repr()),
+      OpCode::HasProperty(name) => format!("HasProperty {}", Value::String(name.clone()).repr()),
+      OpCode::Closure(idx, captures) => {
+        let mut instruction = format!("Closure {}", idx);
+        for capture in captures {
+          match capture {
+            Upvalue::Local(slot) => instruction.push_str(&format!(" local {}", slot)),
+            Upvalue::Upvalue(idx) => instruction.push_str(&format!(" upvalue {}", idx)),
+          }
+        }
+        instruction
+      }
+      // every other instruction is written as its name followed by its operand, if it has one
+      _ => format!("{:?}", opcode).replace('(', " ").replace(')', ""),
+    };
+    text.push_str(&format!("{}  {}\n", indent, instruction));
+  }
+
+  // jumps to the end of the chunk land just past its last instruction
+  if targets.binary_search(&chunk.code.len()).is_ok() {
+    text.push_str(&format!("{}{}:\n", indent, label(Some(chunk.code.len()))));
+  }
+}
+
+/// Finds the instruction a jump lands on, if the given instruction is a jump.
+fn jump_target(ip: usize, opcode: &OpCode) -> Option<usize> {
+  match opcode {
+    OpCode::Jump(JumpDirection::Forwards, distance)
+    | OpCode::JumpIfFalse(distance)
+    | OpCode::Try(distance) => (ip + 1).checked_add(*distance),
+    OpCode::Jump(JumpDirection::Backwards, distance) => (ip + 1).checked_sub(*distance),
+    _ => None,
+  }
+}
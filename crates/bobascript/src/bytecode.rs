@@ -0,0 +1,518 @@
+use std::{
+  convert::{TryFrom, TryInto},
+  rc::Rc,
+};
+
+use thiserror::Error;
+
+use crate::{
+  chunk::{Chunk, JumpDirection, OpCode, Upvalue},
+  value::{Function, RecordKey, Value},
+};
+
+/// Every serialized function starts with these bytes, followed by the format version.
+const MAGIC: &[u8; 4] = b"BOBA";
+/// Bumped whenever the encoding changes, so stale bytecode is rejected instead of misread.
+pub const FORMAT_VERSION: u16 = 1;
+/// How deeply constants (and the functions among them) may be nested inside one another,
+/// so that malicious bytecode can't overflow the stack while it's being read.
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Error, Debug, Clone)]
+pub enum BytecodeError {
+  #[error("The input is not BobaScript bytecode.")]
+  NotBytecode,
+  #[error("Bytecode version {0} is not supported (expected version {FORMAT_VERSION}).")]
+  UnsupportedVersion(u16),
+  #[error("The bytecode ended unexpectedly.")]
+  UnexpectedEnd,
+  #[error("Unknown instruction {0:#04x} at byte {1}.")]
+  UnknownInstruction(u8, usize),
+  #[error("Unknown constant tag {0:#04x} at byte {1}.")]
+  UnknownConstant(u8, usize),
+  #[error("Invalid operand at byte {0}.")]
+  InvalidOperand(usize),
+  #[error("Invalid UTF-8 string at byte {0}.")]
+  InvalidString(usize),
+  #[error("A record constant at byte {0} has a key that can't be used as one.")]
+  InvalidRecordKey(usize),
+  #[error("Found {0} unexpected bytes after the end of the function.")]
+  TrailingBytes(usize),
+  #[error("Constants are nested more than {MAX_DEPTH} deep at byte {0}.")]
+  TooDeep(usize),
+}
+
+impl Function {
+  /// Serializes this function, its chunk, and every function nested in its constants to bytecode.
+  ///
+  /// The output is the magic bytes `BOBA`, a little-endian `u16` format version, and then the function:
+  /// its name, arity, constants, and instructions (each with the line it was compiled from).
+  ///
+  /// # Panics
+  ///
+  /// Panics if a constant is a closure or native function, which only exist at runtime
+  /// and are never put into a chunk by the compiler or assembler.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut writer = Writer(MAGIC.to_vec());
+    writer.u16(FORMAT_VERSION);
+    writer.function(self);
+    writer.0
+  }
+
+  /// Deserializes a function written by [Function::to_bytes].
+  pub fn from_bytes(bytes: &[u8]) -> Result<Function, BytecodeError> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+      return Err(BytecodeError::NotBytecode);
+    }
+    let mut reader = Reader {
+      bytes,
+      offset: MAGIC.len(),
+      depth: 0,
+    };
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+      return Err(BytecodeError::UnsupportedVersion(version));
+    }
+    let function = reader.function()?;
+    match bytes.len() - reader.offset {
+      0 => Ok(function),
+      extra => Err(BytecodeError::TrailingBytes(extra)),
+    }
+  }
+}
+
+mod constant {
+  pub const UNIT: u8 = 0;
+  pub const NONE: u8 = 1;
+  pub const SOME: u8 = 2;
+  pub const TUPLE: u8 = 3;
+  pub const RECORD: u8 = 4;
+  pub const INT: u8 = 5;
+  pub const NUMBER: u8 = 6;
+  pub const TRUE: u8 = 7;
+  pub const FALSE: u8 = 8;
+  pub const STRING: u8 = 9;
+  pub const FUNCTION: u8 = 10;
+}
+
+struct Writer(Vec<u8>);
+impl Writer {
+  fn u8(&mut self, value: u8) {
+    self.0.push(value);
+  }
+
+  fn u16(&mut self, value: u16) {
+    self.0.extend_from_slice(&value.to_le_bytes());
+  }
+
+  /// Sizes, indices, and line numbers are written as unsigned LEB128, since they're almost always small.
+  fn usize(&mut self, mut value: usize) {
+    loop {
+      let byte = (value & 0x7f) as u8;
+      value >>= 7;
+      if value == 0 {
+        self.u8(byte);
+        return;
+      }
+      self.u8(byte | 0x80);
+    }
+  }
+
+  fn string(&mut self, value: &str) {
+    self.usize(value.len());
+    self.0.extend_from_slice(value.as_bytes());
+  }
+
+  fn function(&mut self, function: &Function) {
+    self.string(&function.name);
+    self.u8(function.arity);
+    self.chunk(&function.chunk);
+  }
+
+  fn chunk(&mut self, chunk: &Chunk) {
+    self.usize(chunk.constants.len());
+    for value in &chunk.constants {
+      self.constant(value);
+    }
+    self.usize(chunk.code.len());
+    for (opcode, line) in &chunk.code {
+      self.opcode(opcode);
+      self.usize(*line);
+    }
+  }
+
+  fn constant(&mut self, value: &Value) {
+    match value {
+      Value::Unit => self.u8(constant::UNIT),
+      Value::None => self.u8(constant::NONE),
+      Value::Some(value) => {
+        self.u8(constant::SOME);
+        self.constant(value);
+      }
+      Value::Tuple(tuple) => {
+        self.u8(constant::TUPLE);
+        self.usize(tuple.len());
+        for value in tuple.iter() {
+          self.constant(value);
+        }
+      }
+      Value::Record(record) => {
+        self.u8(constant::RECORD);
+        self.usize(record.len());
+        for (key, value) in record.iter() {
+          self.constant(key.value());
+          self.constant(value);
+        }
+      }
+      Value::Int(value) => {
+        self.u8(constant::INT);
+        self.0.extend_from_slice(&value.to_le_bytes());
+      }
+      Value::Number(value) => {
+        self.u8(constant::NUMBER);
+        self.0.extend_from_slice(&value.to_le_bytes());
+      }
+      Value::Boolean(true) => self.u8(constant::TRUE),
+      Value::Boolean(false) => self.u8(constant::FALSE),
+      Value::String(value) => {
+        self.u8(constant::STRING);
+        self.string(value);
+      }
+      Value::Function(function) => {
+        self.u8(constant::FUNCTION);
+        self.function(function);
+      }
+      Value::NativeFunction(_) | Value::Closure(_) => {
+        panic!("cannot serialize a {} constant", value.type_name())
+      }
+    }
+  }
+
+  fn opcode(&mut self, opcode: &OpCode) {
+    match opcode {
+      OpCode::Unit => self.u8(0),
+      OpCode::None => self.u8(1),
+      OpCode::Some => self.u8(2),
+      OpCode::Tuple(len) => {
+        self.u8(3);
+        self.u8(*len);
+      }
+      OpCode::Record(len) => {
+        self.u8(4);
+        self.u8(*len);
+      }
+      OpCode::Constant(index) => {
+        self.u8(5);
+        self.usize(*index);
+      }
+      OpCode::True => self.u8(6),
+      OpCode::False => self.u8(7),
+      OpCode::Pop => self.u8(8),
+      OpCode::PopResult => self.u8(9),
+      OpCode::PopN(count) => {
+        self.u8(10);
+        self.usize(*count);
+      }
+      OpCode::DefineGlobal(index) => {
+        self.u8(11);
+        self.usize(*index);
+      }
+      OpCode::GetLocal(index) => {
+        self.u8(12);
+        self.usize(*index);
+      }
+      OpCode::SetLocal(index) => {
+        self.u8(13);
+        self.usize(*index);
+      }
+      OpCode::GetGlobal(index) => {
+        self.u8(14);
+        self.usize(*index);
+      }
+      OpCode::SetGlobal(index) => {
+        self.u8(15);
+        self.usize(*index);
+      }
+      OpCode::GetUpvalue(index) => {
+        self.u8(16);
+        self.usize(*index);
+      }
+      OpCode::SetUpvalue(index) => {
+        self.u8(17);
+        self.usize(*index);
+      }
+      OpCode::GetProperty(name) => {
+        self.u8(18);
+        self.string(name);
+      }
+      OpCode::SetProperty(name) => {
+        self.u8(19);
+        self.string(name);
+      }
+      OpCode::Equal => self.u8(20),
+      OpCode::GreaterThan => self.u8(21),
+      OpCode::LessThan => self.u8(22),
+      OpCode::Add => self.u8(23),
+      OpCode::Subtract => self.u8(24),
+      OpCode::Multiply => self.u8(25),
+      OpCode::Divide => self.u8(26),
+      OpCode::Modulo => self.u8(27),
+      OpCode::Exponent => self.u8(28),
+      OpCode::Not => self.u8(29),
+      OpCode::Negate => self.u8(30),
+      OpCode::Log(count) => {
+        self.u8(31);
+        self.u8(*count);
+      }
+      OpCode::Jump(JumpDirection::Forwards, offset) => {
+        self.u8(32);
+        self.usize(*offset);
+      }
+      OpCode::Jump(JumpDirection::Backwards, offset) => {
+        self.u8(33);
+        self.usize(*offset);
+      }
+      OpCode::JumpIfFalse(offset) => {
+        self.u8(34);
+        self.usize(*offset);
+      }
+      OpCode::Index => self.u8(35),
+      OpCode::SetIndex => self.u8(36),
+      OpCode::Append => self.u8(37),
+      OpCode::Extend => self.u8(38),
+      OpCode::MatchTuple(len) => {
+        self.u8(39);
+        self.u8(*len);
+      }
+      OpCode::MatchSome => self.u8(40),
+      OpCode::Unwrap => self.u8(41),
+      OpCode::HasProperty(name) => {
+        self.u8(42);
+        self.string(name);
+      }
+      OpCode::RecordLen => self.u8(43),
+      OpCode::NoMatch => self.u8(44),
+      OpCode::Call(arg_count) => {
+        self.u8(45);
+        self.u8(*arg_count);
+      }
+      OpCode::Closure(index, upvalues) => {
+        self.u8(46);
+        self.usize(*index);
+        self.usize(upvalues.len());
+        for upvalue in upvalues {
+          match upvalue {
+            Upvalue::Local(index) => {
+              self.u8(0);
+              self.usize(*index);
+            }
+            Upvalue::Upvalue(index) => {
+              self.u8(1);
+              self.usize(*index);
+            }
+          }
+        }
+      }
+      OpCode::CloseUpvalue => self.u8(47),
+      OpCode::Return => self.u8(48),
+      OpCode::ReturnUnit => self.u8(49),
+      OpCode::IsUnit => self.u8(50),
+      OpCode::Try(offset) => {
+        self.u8(51);
+        self.usize(*offset);
+      }
+      OpCode::EndTry => self.u8(52),
+    }
+  }
+}
+
+struct Reader<'a> {
+  bytes: &'a [u8],
+  offset: usize,
+  /// How many constants the one being read is nested inside.
+  depth: usize,
+}
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], BytecodeError> {
+    let end = self
+      .offset
+      .checked_add(len)
+      .filter(|end| *end <= self.bytes.len())
+      .ok_or(BytecodeError::UnexpectedEnd)?;
+    let bytes = &self.bytes[self.offset..end];
+    self.offset = end;
+    Ok(bytes)
+  }
+
+  fn u8(&mut self) -> Result<u8, BytecodeError> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16, BytecodeError> {
+    Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+  }
+
+  fn usize(&mut self) -> Result<usize, BytecodeError> {
+    let start = self.offset;
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+      let byte = self.u8()?;
+      let bits = (byte & 0x7f) as usize;
+      // anything that doesn't fit in a usize can't have been written by this platform
+      if shift >= usize::BITS || (bits << shift) >> shift != bits {
+        return Err(BytecodeError::InvalidOperand(start));
+      }
+      value |= bits << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+      shift += 7;
+    }
+  }
+
+  fn string(&mut self) -> Result<String, BytecodeError> {
+    let len = self.usize()?;
+    let start = self.offset;
+    let bytes = self.take(len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| BytecodeError::InvalidString(start))
+  }
+
+  fn function(&mut self) -> Result<Function, BytecodeError> {
+    let name = self.string()?;
+    let arity = self.u8()?;
+    let chunk = self.chunk()?;
+    Ok(Function { arity, chunk, name })
+  }
+
+  fn chunk(&mut self) -> Result<Chunk, BytecodeError> {
+    let mut chunk = Chunk::default();
+    for _ in 0..self.usize()? {
+      let value = self.constant()?;
+      chunk.constants.push(value);
+    }
+    for _ in 0..self.usize()? {
+      let opcode = self.opcode()?;
+      let line = self.usize()?;
+      chunk.code.push((opcode, line));
+    }
+    Ok(chunk)
+  }
+
+  fn constant(&mut self) -> Result<Value, BytecodeError> {
+    let start = self.offset;
+    if self.depth == MAX_DEPTH {
+      return Err(BytecodeError::TooDeep(start));
+    }
+    self.depth += 1;
+    let value = self.constant_value(start);
+    self.depth -= 1;
+    value
+  }
+
+  fn constant_value(&mut self, start: usize) -> Result<Value, BytecodeError> {
+    Ok(match self.u8()? {
+      constant::UNIT => Value::Unit,
+      constant::NONE => Value::None,
+      constant::SOME => Value::Some(Box::new(self.constant()?)),
+      constant::TUPLE => {
+        let len = self.usize()?;
+        Value::tuple_from(
+          (0..len)
+            .map(|_| self.constant())
+            .collect::<Result<Vec<_>, _>>()?,
+        )
+      }
+      constant::RECORD => {
+        let len = self.usize()?;
+        let record = (0..len)
+          .map(|_| {
+            let key = RecordKey::try_from(self.constant()?)
+              .map_err(|_| BytecodeError::InvalidRecordKey(start))?;
+            Ok((key, self.constant()?))
+          })
+          .collect::<Result<_, _>>()?;
+        Value::Record(Rc::new(record))
+      }
+      constant::INT => Value::Int(i64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+      constant::NUMBER => Value::Number(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+      constant::TRUE => Value::Boolean(true),
+      constant::FALSE => Value::Boolean(false),
+      constant::STRING => Value::String(self.string()?),
+      constant::FUNCTION => Value::Function(Rc::new(self.function()?)),
+      tag => return Err(BytecodeError::UnknownConstant(tag, start)),
+    })
+  }
+
+  fn opcode(&mut self) -> Result<OpCode, BytecodeError> {
+    let start = self.offset;
+    Ok(match self.u8()? {
+      0 => OpCode::Unit,
+      1 => OpCode::None,
+      2 => OpCode::Some,
+      3 => OpCode::Tuple(self.u8()?),
+      4 => OpCode::Record(self.u8()?),
+      5 => OpCode::Constant(self.usize()?),
+      6 => OpCode::True,
+      7 => OpCode::False,
+      8 => OpCode::Pop,
+      9 => OpCode::PopResult,
+      10 => OpCode::PopN(self.usize()?),
+      11 => OpCode::DefineGlobal(self.usize()?),
+      12 => OpCode::GetLocal(self.usize()?),
+      13 => OpCode::SetLocal(self.usize()?),
+      14 => OpCode::GetGlobal(self.usize()?),
+      15 => OpCode::SetGlobal(self.usize()?),
+      16 => OpCode::GetUpvalue(self.usize()?),
+      17 => OpCode::SetUpvalue(self.usize()?),
+      18 => OpCode::GetProperty(self.string()?),
+      19 => OpCode::SetProperty(self.string()?),
+      20 => OpCode::Equal,
+      21 => OpCode::GreaterThan,
+      22 => OpCode::LessThan,
+      23 => OpCode::Add,
+      24 => OpCode::Subtract,
+      25 => OpCode::Multiply,
+      26 => OpCode::Divide,
+      27 => OpCode::Modulo,
+      28 => OpCode::Exponent,
+      29 => OpCode::Not,
+      30 => OpCode::Negate,
+      31 => OpCode::Log(self.u8()?),
+      32 => OpCode::Jump(JumpDirection::Forwards, self.usize()?),
+      33 => OpCode::Jump(JumpDirection::Backwards, self.usize()?),
+      34 => OpCode::JumpIfFalse(self.usize()?),
+      35 => OpCode::Index,
+      36 => OpCode::SetIndex,
+      37 => OpCode::Append,
+      38 => OpCode::Extend,
+      39 => OpCode::MatchTuple(self.u8()?),
+      40 => OpCode::MatchSome,
+      41 => OpCode::Unwrap,
+      42 => OpCode::HasProperty(self.string()?),
+      43 => OpCode::RecordLen,
+      44 => OpCode::NoMatch,
+      45 => OpCode::Call(self.u8()?),
+      46 => {
+        let index = self.usize()?;
+        let mut upvalues = Vec::new();
+        for _ in 0..self.usize()? {
+          let start = self.offset;
+          upvalues.push(match self.u8()? {
+            0 => Upvalue::Local(self.usize()?),
+            1 => Upvalue::Upvalue(self.usize()?),
+            _ => return Err(BytecodeError::InvalidOperand(start)),
+          });
+        }
+        OpCode::Closure(index, upvalues)
+      }
+      47 => OpCode::CloseUpvalue,
+      48 => OpCode::Return,
+      49 => OpCode::ReturnUnit,
+      50 => OpCode::IsUnit,
+      51 => OpCode::Try(self.usize()?),
+      52 => OpCode::EndTry,
+      tag => return Err(BytecodeError::UnknownInstruction(tag, start)),
+    })
+  }
+}
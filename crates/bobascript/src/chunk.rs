@@ -2,12 +2,19 @@ use crate::value::Value;
 
 #[derive(Debug, Clone)]
 pub enum OpCode {
+  Unit,
+  /// Pushes `none`.
+  None,
+  /// Replaces the value on top of the stack with `some` of it.
+  Some,
   Tuple(u8),
   Record(u8),
   Constant(usize),
   True,
   False,
   Pop,
+  /// Pops the value of a top-level expression statement, which is kept if results are being collected.
+  PopResult,
   PopN(usize),
   DefineGlobal(usize),
   GetLocal(usize),
@@ -17,6 +24,8 @@ pub enum OpCode {
   GetUpvalue(usize),
   SetUpvalue(usize),
   GetProperty(String),
+  /// Pops a value and the record beneath it, then pushes the value followed by a copy of the record
+  /// with the given field set to it, which is added if the record didn't have it.
   SetProperty(String),
   Equal,
   GreaterThan,
@@ -25,19 +34,120 @@ pub enum OpCode {
   Subtract,
   Multiply,
   Divide,
+  Modulo,
   Exponent,
   Not,
   Negate,
-  Log,
+  /// Replaces the given number of values on top of the stack with the value that's logged,
+  /// which is either the only value or a string of each of them separated by spaces.
+  Log(u8),
   Jump(JumpDirection, usize),
   JumpIfFalse(usize),
   Index,
+  /// Pops a value, an index, and the tuple beneath them, then pushes the value followed by a copy
+  /// of the tuple with the element at that index replaced.
+  SetIndex,
+  /// Pops a value and appends it to the tuple beneath it, replacing that tuple with the result.
+  Append,
+  /// Pops a tuple (or record) and adds its elements (or fields) to the end of the tuple (or record)
+  /// beneath it, replacing that with the result. Fields the record beneath already has are replaced.
+  Extend,
+  /// Replaces the value on top of the stack with whether it's a tuple of the given length.
+  MatchTuple(u8),
+  /// Replaces the value on top of the stack with whether it's a `some`.
+  MatchSome,
+  /// Pushes whether the value on top of the stack is unit, leaving that value where it is.
+  IsUnit,
+  /// Replaces the `some` on top of the stack with the value it holds, raising an error for anything else.
+  Unwrap,
+  /// Replaces the value on top of the stack with whether it's a record with the given field,
+  /// without raising an error like `GetProperty` would.
+  HasProperty(String),
+  /// Replaces the value on top of the stack with its number of fields if it's a record, or unit if not.
+  RecordLen,
+  /// Raises an error, as no arm of a match expression matched.
+  NoMatch,
+  /// Starts handling errors until the matching `EndTry`. If one is raised before then, everything this
+  /// put on the stack is thrown away, a record describing the error is pushed in its place,
+  /// and execution jumps forwards by the given distance.
+  Try(usize),
+  /// Stops handling errors with the innermost `Try`.
+  EndTry,
   Call(u8),
   Closure(usize, Vec<Upvalue>),
   CloseUpvalue,
   Return,
+  /// Returns unit without taking anything off the stack, as a bare `return;` does.
+  ReturnUnit,
 }
 
+impl OpCode {
+  /// The number of values executing this instruction leaves on the stack,
+  /// minus the number of values it takes off.
+  pub fn stack_effect(&self) -> isize {
+    match self {
+      OpCode::Unit
+      | OpCode::None
+      | OpCode::Constant(_)
+      | OpCode::True
+      | OpCode::False
+      | OpCode::GetLocal(_)
+      | OpCode::GetGlobal(_)
+      | OpCode::GetUpvalue(_)
+      | OpCode::IsUnit
+      | OpCode::Closure(_, _) => 1,
+      OpCode::Tuple(len) => 1 - *len as isize,
+      OpCode::Record(len) => 1 - 2 * *len as isize,
+      OpCode::PopN(count) => -(*count as isize),
+      OpCode::Call(args) => -(*args as isize),
+      OpCode::Log(count) => 1 - *count as isize,
+      OpCode::Pop
+      | OpCode::PopResult
+      | OpCode::DefineGlobal(_)
+      | OpCode::Equal
+      | OpCode::GreaterThan
+      | OpCode::LessThan
+      | OpCode::Add
+      | OpCode::Subtract
+      | OpCode::Multiply
+      | OpCode::Divide
+      | OpCode::Modulo
+      | OpCode::Exponent
+      | OpCode::Index
+      | OpCode::SetIndex
+      | OpCode::Append
+      | OpCode::Extend
+      | OpCode::CloseUpvalue
+      | OpCode::Return => -1,
+      OpCode::SetLocal(_)
+      | OpCode::SetGlobal(_)
+      | OpCode::SetUpvalue(_)
+      | OpCode::GetProperty(_)
+      | OpCode::SetProperty(_)
+      | OpCode::Not
+      | OpCode::Negate
+      | OpCode::Jump(_, _)
+      | OpCode::JumpIfFalse(_)
+      | OpCode::MatchTuple(_)
+      | OpCode::Some
+      | OpCode::MatchSome
+      | OpCode::Unwrap
+      | OpCode::HasProperty(_)
+      | OpCode::RecordLen
+      | OpCode::NoMatch
+      | OpCode::Try(_)
+      | OpCode::EndTry
+      | OpCode::ReturnUnit => 0,
+    }
+  }
+}
+
+/// The furthest distance, in either direction, that a single jump may travel.
+pub const MAX_JUMP: usize = u16::MAX as usize;
+
+/// The most variables a single function may capture from the functions surrounding it.
+pub const MAX_UPVALUES: usize = u8::MAX as usize + 1;
+
 #[derive(Debug, Clone, Copy)]
 pub enum JumpDirection {
   Forwards,
@@ -52,8 +162,8 @@ pub enum Upvalue {
 
 #[derive(Debug)]
 pub struct Chunk {
-  // todo: re-add line numbers to code somehow
-  pub code: Vec<OpCode>,
+  /// Each instruction, along with the line of source code it was compiled from.
+  pub code: Vec<(OpCode, usize)>,
   pub constants: Vec<Value>,
 }
 impl Default for Chunk {
@@ -66,8 +176,8 @@ impl Default for Chunk {
 }
 
 impl Chunk {
-  pub fn write(&mut self, opcode: OpCode) -> usize {
-    self.code.push(opcode);
+  pub fn write(&mut self, opcode: OpCode, line: usize) -> usize {
+    self.code.push((opcode, line));
     self.code.len() - 1
   }
 
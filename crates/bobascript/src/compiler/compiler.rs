@@ -1,39 +1,102 @@
-use std::rc::Rc;
+use std::{
+  collections::{HashMap, HashSet},
+  rc::Rc,
+};
 
-use bobascript_parser::ast::{Ast, Expr, Stmt};
+use bobascript_parser::{
+  ast::{Ast, Expr, Located, Stmt},
+  grammar::AstParser,
+  Parser,
+};
 
-use super::{CompileContext, CompileError, CompileResult, FunctionType, Local};
+use super::{
+  CompileContext, CompileError, CompileResult, CompileWarning, FunctionType, Local, Loop,
+};
 use crate::{
-  chunk::{JumpDirection, OpCode, Upvalue},
+  chunk::{JumpDirection, OpCode, Upvalue, MAX_JUMP, MAX_UPVALUES},
   debug::disassemble_chunk,
   value::{Function, Value},
 };
 
+/// The name that binds nothing, discarding whatever value it's given.
+pub(super) const DISCARD_NAME: &str = "_";
+/// What `declare_variable` returns for [DISCARD_NAME], in place of a global's index.
+const DISCARD: usize = usize::MAX;
+
 pub struct Compiler {
   contexts: Vec<CompileContext>,
+  /// Contexts of functions that have finished compiling, kept to be reused by the next ones.
+  spare_contexts: Vec<CompileContext>,
   errors: Vec<CompileError>,
+  warnings: Vec<CompileWarning>,
+  /// The names of all globals declared as functions, along with the names of their parameters.
+  functions: HashMap<String, Vec<String>>,
+  /// The names of all globals declared as pure functions.
+  pure_functions: HashSet<String>,
+  /// The most instructions (or constants) a single function's chunk may hold.
+  max_chunk_size: usize,
+  /// The line of the statement (or expression) currently being compiled.
+  pub(super) line: usize,
+  /// The column that statement (or expression) starts at, which errors are reported at.
+  pub(super) col: usize,
+}
+impl Default for Compiler {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 impl Compiler {
   pub fn new() -> Self {
+    Self::with_max_chunk_size(usize::MAX)
+  }
+
+  pub fn with_max_chunk_size(max_chunk_size: usize) -> Self {
     Self {
       contexts: vec![CompileContext::new(FunctionType::TopLevel)],
+      spare_contexts: vec![],
       errors: vec![],
+      warnings: vec![],
+      functions: HashMap::new(),
+      pure_functions: HashSet::new(),
+      max_chunk_size,
+      line: 1,
+      col: 1,
     }
   }
 
-  pub fn compile(&mut self, ast: &Ast) -> CompileResult<Rc<Function>> {
-    let Ast(stmts, expr) = ast;
-
-    for stmt in stmts {
-      self.statement(stmt);
-    }
-    if let Some(expr) = expr {
-      self.expression(expr);
-    } else {
-      self.emit_opcode(OpCode::Tuple(0));
+  /// Returns the compiler to the state it was in when created (keeping its chunk size limit),
+  /// so that nothing from what it compiled before carries over to what it compiles next.
+  ///
+  /// The space it has already allocated is kept, so compiling many scripts through
+  /// one compiler allocates less than creating a new one for each of them.
+  pub fn reset(&mut self) {
+    // whatever is left from a compilation that didn't finish is reused as well
+    while let Some(context) = self.contexts.pop() {
+      self.spare_contexts.push(context);
     }
+    let context = self.new_context(FunctionType::TopLevel);
+    self.contexts.push(context);
+    self.errors.clear();
+    self.warnings.clear();
+    self.functions.clear();
+    self.pure_functions.clear();
+    self.line = 1;
+    self.col = 1;
+  }
 
-    let function = self.end_compiler();
+  /// Resets the compiler, then parses and compiles the given source code,
+  /// the same as compiling it with a fresh compiler would (but reusing this one's allocations).
+  pub fn compile_source<S>(&mut self, source: S) -> CompileResult<Rc<Function>>
+  where
+    S: Into<String>,
+  {
+    self.reset();
+    let ast = AstParser::parse_ast(&source.into())?;
+    self.compile(&ast)
+  }
+
+  pub fn compile(&mut self, ast: &Ast) -> CompileResult<Rc<Function>> {
+    let function = self.compile_ast(ast);
     if self.errors.is_empty() {
       Ok(function)
     } else {
@@ -43,17 +106,74 @@ impl Compiler {
     }
   }
 
+  /// Compiles the given [Ast] without running anything, returning every error found in it.
+  pub fn check(&mut self, ast: &Ast) -> Vec<CompileError> {
+    self.compile_ast(ast);
+    std::mem::take(&mut self.errors)
+  }
+
+  fn compile_ast(&mut self, ast: &Ast) -> Rc<Function> {
+    let Ast(stmts, expr) = ast;
+
+    for stmt in stmts {
+      self.line = stmt.line;
+      self.col = stmt.col;
+      match &**stmt {
+        Stmt::Expression(expr) => self.expression_stmt(expr, true),
+        stmt => self.statement(stmt),
+      }
+    }
+    if let Some(expr) = expr {
+      self.line = expr.line;
+      self.col = expr.col;
+      self.discarded_expr(expr);
+    } else {
+      self.emit_opcode(OpCode::Unit);
+    }
+
+    self.end_compiler()
+  }
+
   pub(super) fn set_error(&mut self, error: CompileError) {
     self.errors.push(error);
   }
 
-  pub(super) fn with_context<F>(&mut self, fn_type: FunctionType, f: F) -> CompileContext
+  pub(super) fn set_warning(&mut self, warning: CompileWarning) {
+    self.warnings.push(warning);
+  }
+
+  /// Returns all warnings found since the last call, clearing them.
+  pub fn take_warnings(&mut self) -> Vec<CompileWarning> {
+    std::mem::take(&mut self.warnings)
+  }
+
+  /// Compiles a function in a context of its own, returning the function along with the upvalues it captures.
+  pub(super) fn with_context<F>(&mut self, fn_type: FunctionType, f: F) -> (Function, Vec<Upvalue>)
   where
     F: FnOnce(&mut Compiler),
   {
-    self.contexts.push(CompileContext::new(fn_type));
+    let context = self.new_context(fn_type);
+    self.contexts.push(context);
     f(self);
-    self.contexts.pop().unwrap()
+    let mut context = self.contexts.pop().unwrap();
+    for local in context.locals.iter() {
+      self.check_unused(local);
+    }
+    let function = std::mem::take(&mut context.function);
+    let upvalues = std::mem::take(&mut context.upvalues);
+    self.spare_contexts.push(context);
+    (function, upvalues)
+  }
+
+  /// Returns a context for a new function, reusing a spare one if there is one.
+  fn new_context(&mut self, fn_type: FunctionType) -> CompileContext {
+    match self.spare_contexts.pop() {
+      Some(mut context) => {
+        context.reset(fn_type);
+        context
+      }
+      None => CompileContext::new(fn_type),
+    }
   }
 
   pub(super) fn context(&self) -> &CompileContext {
@@ -64,15 +184,33 @@ impl Compiler {
     self.contexts.last_mut().unwrap()
   }
 
-  pub(super) fn block(&mut self, stmts: &Vec<Box<Stmt>>, expr: &Option<Box<Expr>>) {
-    for stmt in stmts {
-      self.statement(&stmt);
-    }
+  pub(super) fn block(
+    &mut self,
+    stmts: &[Located<Stmt>],
+    expr: &Option<Located<Expr>>,
+    value_used: bool,
+  ) {
+    self.statements(stmts);
 
     if let Some(expr) = expr {
-      self.expression(&expr);
+      self.line = expr.line;
+      self.col = expr.col;
+      if value_used {
+        self.expression(expr);
+      } else {
+        self.discarded_expr(expr);
+      }
     } else {
-      self.emit_opcode(OpCode::Tuple(0));
+      self.emit_opcode(OpCode::Unit);
+    }
+  }
+
+  /// Compiles each of the given statements, marking what they emit with the line they're on.
+  pub(super) fn statements(&mut self, stmts: &[Located<Stmt>]) {
+    for stmt in stmts {
+      self.line = stmt.line;
+      self.col = stmt.col;
+      self.statement(&stmt);
     }
   }
 
@@ -83,7 +221,8 @@ impl Compiler {
     args: &[String],
     block: &Expr,
   ) {
-    let context = self.with_context(fn_type, |c| {
+    let (line, col) = (self.line, self.col);
+    let (function, upvalues) = self.with_context(fn_type, |c| {
       if fn_type != FunctionType::TopLevel {
         c.context_mut().function.name = ident.to_string();
       }
@@ -96,13 +235,21 @@ impl Compiler {
           c.context_mut().function.arity += 1;
         }
 
-        // parse the parameter
-        let idx = c.declare_variable(arg);
+        // parse the parameter, whose value is already on the stack when called,
+        // so a discarded parameter still gets a local (which can't be read, as it's named `_`)
+        let idx = if arg == DISCARD_NAME {
+          c.add_local(arg);
+          0
+        } else {
+          c.declare_variable(arg)
+        };
+        c.context_mut().stack_depth += 1;
         c.define_variable(idx);
       }
 
       if let Expr::Block(stmts, expr) = &*block {
-        c.block(stmts, expr);
+        // nothing is expected of a function's body, so an if at its tail doesn't need an else
+        c.block(stmts, expr, false);
         c.emit_opcode(OpCode::Return);
       } else {
         // throw big error dang
@@ -111,118 +258,375 @@ impl Compiler {
 
     if crate::DEBUG && (self.errors.is_empty() || crate::SUPER_DEBUG) {
       disassemble_chunk(
-        &context.function.chunk,
-        if !context.function.name.is_empty() {
-          &context.function.name
+        &function.chunk,
+        if !function.name.is_empty() {
+          &function.name
         } else {
           "[script]"
         },
       );
     }
 
-    let idx = self.make_constant(Value::Function(Rc::new(context.function)));
-    self.emit_opcode(OpCode::Closure(idx, context.upvalues));
+    // the function's body may have moved onto other lines
+    self.line = line;
+    self.col = col;
+    let idx = self.make_constant(Value::Function(Rc::new(function)));
+    self.emit_opcode(OpCode::Closure(idx, upvalues));
   }
 
   pub(super) fn make_constant(&mut self, value: Value) -> usize {
+    if self.context().function.chunk.constants.len() == self.max_chunk_size {
+      self.set_error(CompileError::ProgramTooLarge);
+    }
     self.context_mut().chunk_mut().add_constant(value)
   }
 
   pub(super) fn emit_opcode(&mut self, opcode: OpCode) {
-    // let line_no = self.parser.previous().unwrap().line;
-    // self.current_context_mut().current_chunk_mut().write(opcode, line_no);
-    self.context_mut().chunk_mut().write(opcode);
+    self.emit_opcode_idx(opcode);
   }
 
   /// Emits the given `OpCode` and returns its index in the chunk.
   pub(super) fn emit_opcode_idx(&mut self, opcode: OpCode) -> usize {
-    // let line_no = self.parser.previous().unwrap().line;
-    // self.current_context_mut().current_chunk_mut().write(opcode, line_no)
-    self.context_mut().chunk_mut().write(opcode)
+    if self.context().function.chunk.code.len() == self.max_chunk_size {
+      self.set_error(CompileError::ProgramTooLarge);
+    }
+
+    let line = self.line;
+    let context = self.context_mut();
+    context.stack_depth = (context.stack_depth as isize + opcode.stack_effect()) as usize;
+    context.chunk_mut().write(opcode, line)
+  }
+
+  /// Returns the number of values that will be on the stack at this point.
+  pub(super) fn stack_depth(&self) -> usize {
+    self.context().stack_depth
+  }
+
+  /// Overrides the number of values that will be on the stack at this point,
+  /// for when control flow makes it differ from the instructions before it.
+  pub(super) fn set_stack_depth(&mut self, depth: usize) {
+    self.context_mut().stack_depth = depth;
+  }
+
+  /// Checks (in debug builds) that the compiled code will have the expected number of values
+  /// on the stack at this point, so that unbalanced codegen is caught as it's compiled.
+  pub(super) fn check_stack_depth(&self, expected: usize, after: &str) {
+    // code containing errors won't be run, so its stack can be left however it is
+    if self.errors.is_empty() {
+      debug_assert_eq!(
+        self.stack_depth(),
+        expected,
+        "unbalanced stack after {}",
+        after
+      );
+    }
+  }
+
+  /// Emits the instructions to discard every value above the given stack slot,
+  /// closing any locals that have been captured along the way.
+  ///
+  /// This doesn't remove any locals from the scope, since the code that
+  /// follows may still run with them (e.g. after a `break` in an `if`).
+  pub(super) fn discard_to(&mut self, slot: usize) {
+    for depth in (slot..self.stack_depth()).rev() {
+      let is_captured = self
+        .context()
+        .locals
+        .iter()
+        .any(|local| local.slot == depth && local.is_captured);
+      if is_captured {
+        self.emit_opcode(OpCode::CloseUpvalue);
+      } else {
+        self.emit_opcode(OpCode::Pop);
+      }
+    }
+  }
+
+  pub(super) fn begin_loop(&mut self, label: &Option<String>, slot: usize) {
+    let start = self.context().function.chunk.code.len();
+    let tries = self.context().tries;
+    self.context_mut().loops.push(Loop {
+      label: label.clone(),
+      start,
+      slot,
+      breaks: vec![],
+      tries,
+    });
+  }
+
+  /// Stops tracking the innermost loop, returning the `break` jumps that still need patching.
+  pub(super) fn end_loop(&mut self) -> Vec<usize> {
+    self.context_mut().loops.pop().unwrap().breaks
+  }
+
+  /// Finds the index of the loop a `break` or `continue` with the given label refers to.
+  pub(super) fn resolve_loop(&mut self, label: &Option<String>) -> Option<usize> {
+    let loops = &self.context().loops;
+    let idx = match label {
+      Some(label) => loops
+        .iter()
+        .rposition(|l| l.label.as_deref() == Some(label.as_str())),
+      None => loops.len().checked_sub(1),
+    };
+
+    if idx.is_none() {
+      self.set_error(match label {
+        Some(label) => CompileError::UndefinedLabel(label.clone()),
+        None => CompileError::BreakOutsideLoop,
+      });
+    }
+    idx
+  }
+
+  pub(super) fn loop_start(&self, idx: usize) -> usize {
+    self.context().loops[idx].start
+  }
+
+  pub(super) fn loop_slot(&self, idx: usize) -> usize {
+    self.context().loops[idx].slot
+  }
+
+  pub(super) fn add_loop_break(&mut self, idx: usize, jump: usize) {
+    self.context_mut().loops[idx].breaks.push(jump);
+  }
+
+  /// Ends every `try` block that's been started since the given loop, before jumping out of them.
+  pub(super) fn end_loop_tries(&mut self, idx: usize) {
+    for _ in self.context().loops[idx].tries..self.context().tries {
+      self.emit_opcode(OpCode::EndTry);
+    }
   }
 
   /// This just emits a `Jump` instruction, but backwards
   pub(super) fn emit_loop(&mut self, start: usize) {
-    let offset = self.context_mut().chunk_mut().code.len() - start + 1;
-    self.emit_opcode(OpCode::Jump(JumpDirection::Backwards, offset));
+    let from = self.context().function.chunk.code.len();
+    let (direction, offset) = self.jump_offset(from, start);
+    self.emit_opcode(OpCode::Jump(direction, offset));
   }
 
+  /// Points the jump instruction at `offset` to the next instruction to be emitted.
   pub(super) fn patch_jump(&mut self, offset: usize) {
-    let new_jump = self.context_mut().chunk_mut().code.len() - 1 - offset;
-    let opcode = &self.context_mut().chunk_mut().code[offset];
-    self.context_mut().chunk_mut().code[offset] = match opcode {
-      OpCode::Jump(direction, _) => OpCode::Jump(*direction, new_jump),
-      OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(new_jump),
+    let to = self.context().function.chunk.code.len();
+    let (direction, jump) = self.jump_offset(offset, to);
+    let code = &mut self.context_mut().chunk_mut().code;
+    code[offset].0 = match code[offset].0 {
+      OpCode::Jump(_, _) => OpCode::Jump(direction, jump),
+      OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(jump),
+      OpCode::Try(_) => OpCode::Try(jump),
       _ => unreachable!(),
     };
   }
 
-  pub(super) fn with_scope<F>(&mut self, f: F)
-  where
-    F: FnOnce(&mut Compiler),
-  {
-    self.begin_scope();
-    f(self);
-    self.end_scope();
+  /// Calculates the direction and distance a jump instruction at index `from`
+  /// has to travel so that the instruction at index `to` is executed next.
+  fn jump_offset(&mut self, from: usize, to: usize) -> (JumpDirection, usize) {
+    // by the time a jump is executed, the VM has already moved past it
+    let next = from + 1;
+    let (direction, distance) = if to >= next {
+      (JumpDirection::Forwards, to - next)
+    } else {
+      (JumpDirection::Backwards, next - to)
+    };
+
+    if distance > MAX_JUMP {
+      self.set_error(CompileError::JumpTooLarge);
+      (direction, 0)
+    } else {
+      (direction, distance)
+    }
   }
 
   pub(super) fn begin_scope(&mut self) {
     self.context_mut().scope_depth += 1;
   }
 
-  fn end_scope(&mut self) {
+  pub(super) fn end_scope(&mut self) {
     self.context_mut().scope_depth -= 1;
 
-    // let mut count: usize = 0;
+    // runs of uncaptured locals are popped all at once, while captured ones are closed over one at a time
+    let mut count: usize = 0;
     for i in (0..self.context().locals.len()).rev() {
       if self.context().locals[i].depth > self.context().scope_depth {
         if self.context().locals[i].is_captured {
+          self.emit_pops(count);
+          count = 0;
           self.emit_opcode(OpCode::CloseUpvalue);
         } else {
-          self.emit_opcode(OpCode::Pop);
+          count += 1;
         }
-        self.context_mut().locals.remove(i);
-        // count += 1;
+        let local = self.context_mut().locals.remove(i);
+        self.check_unused(&local);
       } else {
         break;
       }
     }
+    self.emit_pops(count);
+  }
 
-    /* todo: fix this so PopN isn't useless
+  /// Pops the given number of values off the stack, with a single instruction.
+  fn emit_pops(&mut self, count: usize) {
     if count == 1 {
       self.emit_opcode(OpCode::Pop);
     } else if count > 1 {
       self.emit_opcode(OpCode::PopN(count));
     }
-    */
   }
 
   /// Adds a variable to the scope
   pub(super) fn declare_variable(&mut self, name: &str) -> usize {
+    if name == DISCARD_NAME {
+      // nothing is declared, the value is popped by `define_variable` instead
+      return DISCARD;
+    }
     if self.context().scope_depth > 0 {
       let name_exists = self
         .context()
         .locals
         .iter()
         .rev()
-        .filter(|local| local.depth != -1 && local.depth < self.context().scope_depth)
+        .take_while(|local| local.depth == -1 || local.depth >= self.context().scope_depth)
         .any(|local| name == local.name);
 
       if name_exists {
-        self.set_error(CompileError::VariableAlreadyExists(name.to_string()));
-      } else {
-        self.context_mut().locals.push(Local {
+        self.set_error(CompileError::VariableAlreadyExists {
           name: name.to_string(),
-          depth: -1,
-          is_captured: false,
+          line: self.line,
+          col: self.col,
         });
+      } else {
+        self.add_local(name);
       }
       0
     } else {
+      self.functions.remove(name);
+      self.pure_functions.remove(name);
+      self.context_mut().consts.remove(name);
       self.identifier_constant(name.to_string())
     }
   }
 
+  /// Adds an uninitialized local, which takes the next slot on the stack.
+  pub(super) fn add_local(&mut self, name: &str) {
+    let slot = self.stack_depth();
+    self.context_mut().locals.push(Local {
+      name: name.to_string(),
+      depth: -1,
+      slot,
+      is_captured: false,
+      is_function: false,
+      is_pure: false,
+      params: Vec::new(),
+      is_const: false,
+      const_value: None,
+      is_read: false,
+    });
+  }
+
+  /// Marks the most recently declared variable as a function, which makes it immutable.
+  pub(super) fn mark_function(&mut self, name: &str, params: &[String], pure: bool) {
+    if self.context().scope_depth > 0 {
+      let idx = self.context().locals.len() - 1;
+      let local = &mut self.context_mut().locals[idx];
+      local.is_function = true;
+      local.is_pure = pure;
+      local.params = params.to_vec();
+    } else {
+      self.functions.insert(name.to_string(), params.to_vec());
+      if pure {
+        self.pure_functions.insert(name.to_string());
+      }
+    }
+  }
+
+  /// Marks the most recently declared variable as a const, which makes it immutable.
+  ///
+  /// If its value is known at compile time, uses of it can be folded.
+  pub(super) fn mark_const(&mut self, name: &str, value: Option<Value>) {
+    if self.context().scope_depth > 0 {
+      let idx = self.context().locals.len() - 1;
+      let local = &mut self.context_mut().locals[idx];
+      local.is_const = true;
+      local.const_value = value;
+    } else {
+      self.context_mut().consts.insert(name.to_string(), value);
+    }
+  }
+
+  /// Marks the local the given name currently resolves to as read, if there is one.
+  pub(super) fn mark_read(&mut self, name: &str) {
+    for context in self.contexts.iter_mut().rev() {
+      let local = context
+        .locals
+        .iter_mut()
+        .rev()
+        .find(|local| local.depth != -1 && name == local.name);
+      if let Some(local) = local {
+        local.is_read = true;
+        return;
+      }
+    }
+  }
+
+  /// Warns about a local going out of scope without ever being read.
+  fn check_unused(&mut self, local: &Local) {
+    if !local.is_read && !local.name.starts_with('_') {
+      self.set_warning(CompileWarning::UnusedVariable(local.name.clone()));
+    }
+  }
+
+  /// Returns whether the given name currently resolves to a declared function.
+  pub(super) fn is_function(&self, name: &str) -> bool {
+    match self.find_local(name) {
+      Some(local) => local.is_function,
+      None => self.functions.contains_key(name),
+    }
+  }
+
+  /// Returns the names of the parameters of the function the given name currently resolves to,
+  /// if it's one declared with `fn`.
+  pub(super) fn function_params(&self, name: &str) -> Option<&[String]> {
+    match self.find_local(name) {
+      Some(local) if local.is_function => Some(&local.params),
+      Some(_) => None,
+      None => self.functions.get(name).map(Vec::as_slice),
+    }
+  }
+
+  /// Returns whether the given name currently resolves to a function marked `pure`.
+  pub(super) fn is_pure(&self, name: &str) -> bool {
+    match self.find_local(name) {
+      Some(local) => local.is_pure,
+      None => self.pure_functions.contains(name),
+    }
+  }
+
+  /// Returns the local the given name currently resolves to, if there is one.
+  fn find_local(&self, name: &str) -> Option<&Local> {
+    self.contexts.iter().rev().find_map(|context| {
+      context
+        .locals
+        .iter()
+        .rev()
+        .find(|local| local.depth != -1 && name == local.name)
+    })
+  }
+
+  /// Returns whether the given name currently resolves to a const.
+  pub(super) fn is_const(&self, name: &str) -> bool {
+    match self.find_local(name) {
+      Some(local) => local.is_const,
+      None => self.contexts[0].consts.contains_key(name),
+    }
+  }
+
+  /// Returns the value of the const the given name currently resolves to, if it's known.
+  pub(super) fn const_value(&self, name: &str) -> Option<Value> {
+    match self.find_local(name) {
+      Some(local) => local.const_value.clone(),
+      None => self.contexts[0].consts.get(name).cloned().flatten(),
+    }
+  }
+
   pub(super) fn mark_initialized(&mut self) {
     if self.context().scope_depth != 0 {
       let idx = self.context().locals.len() - 1;
@@ -232,7 +636,9 @@ impl Compiler {
 
   /// Initializes a variable in the scope for use
   pub(super) fn define_variable(&mut self, global: usize) {
-    if self.context().scope_depth > 0 {
+    if global == DISCARD {
+      self.emit_opcode(OpCode::Pop);
+    } else if self.context().scope_depth > 0 {
       self.mark_initialized();
     } else {
       self.emit_opcode(OpCode::DefineGlobal(global));
@@ -241,7 +647,8 @@ impl Compiler {
 
   pub(super) fn resolve_variable(&mut self, name: &str) -> (OpCode, OpCode) {
     if let Some(idx) = self.resolve_local(name, 0) {
-      (OpCode::GetLocal(idx), OpCode::SetLocal(idx))
+      let slot = self.context().locals[idx].slot;
+      (OpCode::GetLocal(slot), OpCode::SetLocal(slot))
     } else if let Some(idx) = self.resolve_upvalue(name, 0) {
       (OpCode::GetUpvalue(idx), OpCode::SetUpvalue(idx))
     } else {
@@ -255,7 +662,7 @@ impl Compiler {
   }
 
   fn resolve_local(&mut self, name: &str, context_idx: usize) -> Option<usize> {
-    let context = self.contexts.iter_mut().nth_back(context_idx);
+    let context = self.contexts.iter().nth_back(context_idx);
     let context = match context {
       Some(c) => c,
       // we've run out of compile contexts
@@ -263,11 +670,16 @@ impl Compiler {
     };
 
     match context.resolve_local(name) {
-      Ok(local) => local,
-      Err(err) => {
-        self.set_error(err);
+      Some((local, true)) => Some(local),
+      Some((_, false)) => {
+        self.set_error(CompileError::VariableDoesNotExist {
+          name: name.to_string(),
+          line: self.line,
+          col: self.col,
+        });
         None
       }
+      None => None,
     }
   }
 
@@ -287,6 +699,11 @@ impl Compiler {
       }
     }
 
+    if upvalues.len() == MAX_UPVALUES {
+      self.set_error(CompileError::TooManyUpvalues);
+      return 0;
+    }
+
     upvalues.push(if is_local {
       Upvalue::Local(index)
     } else {
@@ -297,14 +714,15 @@ impl Compiler {
 
   fn resolve_upvalue(&mut self, name: &str, context_idx: usize) -> Option<usize> {
     if let Some(idx) = self.resolve_local(name, context_idx + 1) {
-      self
+      let local = &mut self
         .contexts
         .iter_mut()
         .nth_back(context_idx + 1)
         .unwrap()
-        .locals[idx]
-        .is_captured = true;
-      Some(self.add_upvalue(idx, true, context_idx))
+        .locals[idx];
+      local.is_captured = true;
+      let slot = local.slot;
+      Some(self.add_upvalue(slot, true, context_idx))
     } else if context_idx + 1 >= self.contexts.len() {
       // if we continue here, we'd get stuck in an infinite loop until the stack overflows
       // this is because we are out of contexts to check
@@ -318,19 +736,74 @@ impl Compiler {
 
   fn end_compiler(&mut self) -> Rc<Function> {
     self.emit_opcode(OpCode::Return);
-    let context = self.contexts.pop().unwrap();
+    let mut context = self.contexts.pop().unwrap();
+    let function = std::mem::take(&mut context.function);
+    self.spare_contexts.push(context);
 
     if crate::DEBUG && (self.errors.is_empty() || crate::SUPER_DEBUG) {
       disassemble_chunk(
-        &context.function.chunk,
-        if !context.function.name.is_empty() {
-          &context.function.name
+        &function.chunk,
+        if !function.name.is_empty() {
+          &function.name
         } else {
           "[script]"
         },
       );
     }
 
-    Rc::new(context.function)
+    Rc::new(function)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bobascript_parser::ast::Constant;
+
+  use super::*;
+
+  #[test]
+  fn balanced_statement_passes() {
+    let mut compiler = Compiler::new();
+    let depth = compiler.stack_depth();
+    compiler.statement(&Stmt::Expression(Box::new(Expr::Constant(Constant::Unit))));
+    compiler.check_stack_depth(depth, "a statement");
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "unbalanced stack after a statement")]
+  fn stray_value_trips_assertion() {
+    let mut compiler = Compiler::new();
+    let depth = compiler.stack_depth();
+    // codegen for an expression statement that forgets to pop its value
+    compiler.expression(&Expr::Constant(Constant::Unit));
+    compiler.check_stack_depth(depth, "a statement");
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "unbalanced stack after the branches of an if")]
+  fn uneven_branches_trip_assertion() {
+    let mut compiler = Compiler::new();
+    compiler.expression(&Expr::Constant(Constant::True));
+    compiler.emit_opcode(OpCode::JumpIfFalse(0));
+    compiler.emit_opcode(OpCode::Pop);
+    let branch_depth = compiler.stack_depth();
+    // the other branch leaves behind a value too many
+    compiler.emit_opcode(OpCode::Unit);
+    compiler.emit_opcode(OpCode::Unit);
+    compiler.check_stack_depth(branch_depth, "the branches of an if");
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "unbalanced stack after a function's body")]
+  fn wrong_expected_depth_trips_assertion() {
+    let mut compiler = Compiler::new();
+    compiler.with_context(FunctionType::Function, |c| {
+      // the function itself takes up the first slot, which this forgets to count
+      c.expression(&Expr::Constant(Constant::Unit));
+      c.check_stack_depth(1, "a function's body");
+    });
   }
 }
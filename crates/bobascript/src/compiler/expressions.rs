@@ -1,63 +1,210 @@
-use std::{convert::TryInto, rc::Rc};
+use std::convert::TryInto;
 
-use bobascript_parser::ast::{AssignOp, BinaryOp, Constant, Expr, Stmt, UnaryOp};
+use bobascript_parser::ast::{
+  AssignOp, BinaryOp, Constant, Expr, Field, Located, Pattern, Stmt, UnaryOp,
+};
 
 use crate::{
   chunk::{JumpDirection, OpCode},
-  value::Value,
+  value::{Arithmetic, Value},
 };
 
-use super::{compiler::Compiler, CompileError, FunctionType};
+use super::{
+  compiler::{Compiler, DISCARD_NAME},
+  CompileError, CompileWarning, FunctionType,
+};
+
+/// Returns the parameter name and value of an argument written like `name = value`,
+/// which passes it by keyword rather than by position.
+fn keyword_argument(arg: &Expr) -> Option<(&str, &Expr)> {
+  match arg {
+    Expr::Assign(target, AssignOp::Assign, value) => match &**target {
+      Expr::Constant(Constant::Ident(path, name)) if path.is_empty() => Some((name, value)),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// The name given to anonymous functions, as they're shown when logged.
+const ANONYMOUS_NAME: &str = "anonymous";
+
+/// A step taken from the matched value towards the part of it a pattern is looking at.
+#[derive(Clone, Copy)]
+enum PathStep {
+  /// Into the tuple element at this index.
+  Index(usize),
+  /// Into the value held by a `some`.
+  Unwrap,
+}
+
+/// The part of a tuple or record being assigned to.
+#[derive(Clone, Copy)]
+enum Element<'a> {
+  Index(&'a Expr),
+  Property(&'a str),
+}
 
 impl Compiler {
   pub fn expression(&mut self, expr: &Expr) {
+    // arithmetic on numbers that are already known is worked out ahead of time
+    if let Expr::Binary(..) | Expr::Unary(..) = expr {
+      if let Some(value) = self.fold(expr) {
+        let idx = self.make_constant(value);
+        self.emit_opcode(OpCode::Constant(idx));
+        return;
+      }
+    }
+
     match &*expr {
-      Expr::Log(expr) => self.log_expr(expr),
-      Expr::Block(stmts, expr) => self.block_expr(stmts, expr),
+      Expr::Log(args) => self.log_expr(args),
+      Expr::Block(stmts, expr) => self.block_expr(stmts, expr, true),
       Expr::If(condition, true_branch, false_branch) => {
-        self.if_expr(condition, true_branch, false_branch)
+        self.if_expr(condition, true_branch, false_branch, true)
       }
-      Expr::While(condition, stmts) => self.while_expr(condition, stmts),
+      Expr::While(condition, stmts, label) => self.while_expr(condition, stmts, label),
+      Expr::Collect(condition, block, label) => self.collect_expr(condition, block, label),
+      Expr::Match(expr, arms) => self.match_expr(expr, arms),
+      Expr::Try(body, name, catch) => self.try_expr(body, name, catch),
       Expr::Assign(name, op, expr) => self.assign_expr(name, op, expr),
       Expr::Binary(lhs, op, rhs) => self.binary_expr(lhs, op, rhs),
       Expr::Unary(op, expr) => self.unary_expr(op, expr),
       Expr::Property(expr, prop) => self.property_expr(expr, prop),
       Expr::Index(expr, index) => self.index_expr(expr, index),
       Expr::Call(function, args) => self.call_expr(function, args),
+      Expr::Function(params, body) => {
+        self.function(FunctionType::Function, ANONYMOUS_NAME, params, body)
+      }
+      Expr::Interpolation(parts) => self.interpolation_expr(parts),
+      Expr::Spread(_) => {
+        self.set_error(CompileError::SpreadOutsideLiteral);
+        self.emit_opcode(OpCode::Unit);
+      }
       Expr::Constant(constant) => self.constant_expr(constant),
       Expr::Error => todo!(),
     }
   }
 
-  fn log_expr(&mut self, expr: &Expr) {
-    self.expression(&expr);
-    self.emit_opcode(OpCode::Log);
+  /// Evaluates the given expression at compile time, if it's arithmetic on known numbers.
+  pub(super) fn fold(&mut self, expr: &Expr) -> Option<Value> {
+    match expr {
+      Expr::Constant(Constant::Int(num)) => Some(Value::Int(*num)),
+      Expr::Constant(Constant::Number(num)) => Some(Value::Number(*num)),
+      Expr::Constant(Constant::Ident(_, name)) => {
+        let value = self.const_value(name);
+        if value.is_some() {
+          self.mark_read(name);
+        }
+        value
+      }
+      Expr::Unary(UnaryOp::Negate, expr) => self.fold(expr)?.negate(),
+      Expr::Binary(lhs, op, rhs) => {
+        let (a, b) = (self.fold(lhs)?, self.fold(rhs)?);
+        let op = match op {
+          BinaryOp::Add => Arithmetic::Add,
+          BinaryOp::Subtract => Arithmetic::Subtract,
+          BinaryOp::Multiply => Arithmetic::Multiply,
+          // dividing by zero is left for the VM to deal with
+          BinaryOp::Divide if !b.is_zero() => Arithmetic::Divide,
+          BinaryOp::Modulo if !b.is_zero() => Arithmetic::Modulo,
+          BinaryOp::Exponent => Arithmetic::Exponent,
+          _ => return None,
+        };
+        op.apply(&a, &b)
+      }
+      _ => None,
+    }
+  }
+
+  fn log_expr(&mut self, args: &[Box<Expr>]) {
+    self.check_purity(None);
+    for arg in args {
+      self.expression(arg);
+    }
+    if args.len() > u8::MAX.into() {
+      self.set_error(CompileError::TooManyArguments);
+    }
+    self.emit_opcode(OpCode::Log(args.len().try_into().unwrap_or(u8::MAX)));
   }
 
-  fn block_expr(&mut self, stmts: &Vec<Box<Stmt>>, expr: &Option<Box<Expr>>) {
-    // todo: maybe only compile blocks as functions in some cases instead of all
-    // self.with_scope(|c| {
-    //   c.block(stmts, expr);
-    // });
-    let context = self.with_context(FunctionType::Block, |c| {
-      c.begin_scope();
-      c.block(stmts, expr);
-      c.emit_opcode(OpCode::Return);
+  fn interpolation_expr(&mut self, parts: &[Box<Expr>]) {
+    // the first part is always a string, so adding each part onto it converts that part to a string
+    for (idx, part) in parts.iter().enumerate() {
+      self.expression(part);
+      if idx > 0 {
+        self.emit_opcode(OpCode::Add);
+      }
+    }
+  }
+
+  /// Compiles a block, where `value_used` is whether anything will make use of its value.
+  fn block_expr(
+    &mut self,
+    stmts: &[Located<Stmt>],
+    expr: &Option<Located<Expr>>,
+    value_used: bool,
+  ) {
+    // blocks without any declarations don't need a scope of their own
+    let has_locals = stmts.iter().any(|stmt| {
+      matches!(
+        **stmt,
+        Stmt::Function(..) | Stmt::Const(_, _) | Stmt::Let(_, _)
+      )
     });
+    if !has_locals {
+      self.block(stmts, expr, value_used);
+      return;
+    }
 
-    // todo: disassemble the chunk
+    // otherwise, the block's value is moved into a slot below its locals
+    // so that it's left on top of the stack once they've been popped
+    let slot = self.stack_depth();
+    self.emit_opcode(OpCode::Unit);
+    self.begin_scope();
+    self.block(stmts, expr, value_used);
+    self.emit_opcode(OpCode::SetLocal(slot));
+    self.emit_opcode(OpCode::Pop);
+    self.end_scope();
+    self.check_stack_depth(slot + 1, "a block");
+  }
+
+  /// Compiles an expression whose value is thrown away, so an `if` at its tail doesn't need an else.
+  pub(super) fn discarded_expr(&mut self, expr: &Expr) {
+    match expr {
+      Expr::If(condition, true_branch, false_branch) => {
+        self.if_expr(condition, true_branch, false_branch, false)
+      }
+      Expr::Block(stmts, expr) => self.block_expr(stmts, expr, false),
+      _ => self.expression(expr),
+    }
+  }
 
-    let idx = self.make_constant(Value::Function(Rc::new(context.function)));
-    self.emit_opcode(OpCode::Closure(idx, context.upvalues));
-    self.emit_opcode(OpCode::Call(0));
+  /// Compiles the condition of an `if` or loop, warning if it's an assignment (which was probably meant to be `==`).
+  fn condition(&mut self, condition: &Expr) {
+    if let Expr::Assign(..) = condition {
+      self.set_warning(CompileWarning::AssignmentInCondition);
+    }
+    self.expression(condition);
   }
 
-  fn if_expr(&mut self, condition: &Expr, true_branch: &Expr, false_branch: &Option<Box<Expr>>) {
-    self.expression(&condition);
+  /// Compiles an if expression, where `value_used` is whether anything will make use of its value.
+  pub(super) fn if_expr(
+    &mut self,
+    condition: &Expr,
+    true_branch: &Expr,
+    false_branch: &Option<Box<Expr>>,
+    value_used: bool,
+  ) {
+    self.condition(condition);
     let then_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
     self.emit_opcode(OpCode::Pop);
 
-    self.expression(&true_branch);
+    if value_used {
+      self.expression(true_branch);
+    } else {
+      self.discarded_expr(true_branch);
+    }
+    let branch_depth = self.stack_depth();
 
     let else_jump = self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0));
     self.patch_jump(then_jump);
@@ -65,40 +212,273 @@ impl Compiler {
 
     if let Some(false_branch) = false_branch {
       match &**false_branch {
-        Expr::Block(stmts, expr) => self.block_expr(&stmts, &expr),
+        Expr::Block(stmts, expr) => self.block_expr(&stmts, &expr, value_used),
         Expr::If(condition, true_branch, false_branch) => {
-          self.if_expr(&condition, &true_branch, &false_branch)
+          self.if_expr(&condition, &true_branch, &false_branch, value_used)
         }
         _ => self.set_error(CompileError::UndefinedBehavior(
           r#"An expression other than "if" or "block" was found in the else clause."#.to_string(),
         )),
       }
+    } else {
+      if value_used {
+        self.set_warning(CompileWarning::IfWithoutElseInValuePosition);
+      }
+      self.emit_opcode(OpCode::Unit);
     }
 
+    self.check_stack_depth(branch_depth, "the branches of an if");
     self.patch_jump(else_jump);
   }
 
-  fn while_expr(&mut self, condition: &Expr, stmts: &Vec<Box<Stmt>>) {
+  fn while_expr(&mut self, condition: &Expr, stmts: &[Located<Stmt>], label: &Option<String>) {
+    // since this *is* still an expression, it must return *something*
+    // this is kept in its own slot, so a `break` can replace it
+    let slot = self.stack_depth();
+    self.emit_opcode(OpCode::Unit);
+
     let loop_start = self.context_mut().chunk_mut().code.len();
+    self.begin_loop(label, slot);
 
-    self.expression(&condition);
+    self.condition(condition);
     let exit_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
     self.emit_opcode(OpCode::Pop);
 
-    for stmt in stmts {
-      self.statement(&stmt);
-    }
+    self.begin_scope();
+    self.statements(stmts);
+    self.end_scope();
+    self.check_stack_depth(slot + 1, "the body of a loop");
 
     self.emit_loop(loop_start);
     self.patch_jump(exit_jump);
+    // the condition is still on the stack when the loop exits
+    self.set_stack_depth(slot + 2);
     self.emit_opcode(OpCode::Pop);
 
-    // since this *is* still an expression, it must return *something*
+    for jump in self.end_loop() {
+      self.patch_jump(jump);
+    }
+  }
+
+  fn collect_expr(&mut self, condition: &Expr, block: &Expr, label: &Option<String>) {
+    // the values collected so far are kept in their own slot, which a `break` can also replace
+    let slot = self.stack_depth();
     self.emit_opcode(OpCode::Tuple(0));
+
+    let loop_start = self.context_mut().chunk_mut().code.len();
+    self.begin_loop(label, slot);
+
+    self.condition(condition);
+    let exit_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+    self.emit_opcode(OpCode::Pop);
+
+    self.emit_opcode(OpCode::GetLocal(slot));
+    self.expression(block);
+    self.emit_opcode(OpCode::Append);
+    self.emit_opcode(OpCode::SetLocal(slot));
+    self.emit_opcode(OpCode::Pop);
+    self.check_stack_depth(slot + 1, "the body of a loop");
+
+    self.emit_loop(loop_start);
+    self.patch_jump(exit_jump);
+    // the condition is still on the stack when the loop exits
+    self.set_stack_depth(slot + 2);
+    self.emit_opcode(OpCode::Pop);
+
+    for jump in self.end_loop() {
+      self.patch_jump(jump);
+    }
+  }
+
+  fn match_expr(&mut self, expr: &Expr, arms: &[(Pattern, Box<Expr>)]) {
+    // the value being matched is kept in its own slot, which the matching arm's result replaces
+    let slot = self.stack_depth();
+    self.expression(expr);
+
+    let mut end_jumps = vec![];
+    let mut exhaustive = false;
+    for (pattern, arm) in arms {
+      let next_jump = if pattern.is_irrefutable() {
+        None
+      } else {
+        self.pattern_test(pattern, slot, &mut vec![]);
+        let jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+        self.emit_opcode(OpCode::Pop);
+        Some(jump)
+      };
+
+      self.begin_scope();
+      self.pattern_bindings(pattern, slot, &mut vec![]);
+      self.expression(arm);
+      self.emit_opcode(OpCode::SetLocal(slot));
+      self.emit_opcode(OpCode::Pop);
+      self.end_scope();
+      self.check_stack_depth(slot + 1, "an arm of a match");
+      end_jumps.push(self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0)));
+
+      match next_jump {
+        Some(jump) => {
+          // the failed test's result is still on the stack
+          self.set_stack_depth(slot + 2);
+          self.patch_jump(jump);
+          self.emit_opcode(OpCode::Pop);
+        }
+        None => {
+          // every arm after this one is unreachable
+          exhaustive = true;
+          break;
+        }
+      }
+    }
+
+    if !exhaustive {
+      self.emit_opcode(OpCode::NoMatch);
+    }
+    for jump in end_jumps {
+      self.patch_jump(jump);
+    }
+    self.set_stack_depth(slot + 1);
+  }
+
+  fn try_expr(&mut self, body: &Expr, name: &str, catch: &Expr) {
+    let slot = self.stack_depth();
+    let catch_jump = self.emit_opcode_idx(OpCode::Try(0));
+    self.context_mut().tries += 1;
+    self.expression(body);
+    self.context_mut().tries -= 1;
+    self.emit_opcode(OpCode::EndTry);
+    let end_jump = self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0));
+
+    // when an error is caught, the record describing it is put in the slot the body's value would be in,
+    // which the catch block's value then replaces, like the value matched by a match expression
+    self.set_stack_depth(slot + 1);
+    self.patch_jump(catch_jump);
+    self.begin_scope();
+    let local = self.declare_variable(name);
+    self.emit_opcode(OpCode::GetLocal(slot));
+    self.define_variable(local);
+    self.expression(catch);
+    self.emit_opcode(OpCode::SetLocal(slot));
+    self.emit_opcode(OpCode::Pop);
+    self.end_scope();
+    self.check_stack_depth(slot + 1, "a catch block");
+
+    self.patch_jump(end_jump);
+  }
+
+  /// Pushes the value found by following each step of `path` from the value in `slot`.
+  fn pattern_value(&mut self, slot: usize, path: &[PathStep]) {
+    self.emit_opcode(OpCode::GetLocal(slot));
+    for step in path {
+      match step {
+        PathStep::Index(idx) => {
+          let idx = self.make_constant(Value::Int(*idx as i64));
+          self.emit_opcode(OpCode::Constant(idx));
+          self.emit_opcode(OpCode::Index);
+        }
+        PathStep::Unwrap => self.emit_opcode(OpCode::Unwrap),
+      }
+    }
+  }
+
+  /// Pushes whether the value at `path` matches the given (refutable) pattern.
+  fn pattern_test(&mut self, pattern: &Pattern, slot: usize, path: &mut Vec<PathStep>) {
+    match pattern {
+      Pattern::Wildcard | Pattern::Binding(_) => self.emit_opcode(OpCode::True),
+      Pattern::Literal(constant) => {
+        self.pattern_value(slot, path);
+        self.constant_expr(constant);
+        self.emit_opcode(OpCode::Equal);
+      }
+      Pattern::Tuple(patterns) => {
+        self.pattern_value(slot, path);
+        self.emit_opcode(OpCode::MatchTuple(patterns.len() as u8));
+
+        // each item is only checked if everything before it matched, like with `and`
+        let mut end_jumps = vec![];
+        for (idx, pattern) in patterns.iter().enumerate() {
+          if pattern.is_irrefutable() {
+            continue;
+          }
+          end_jumps.push(self.emit_opcode_idx(OpCode::JumpIfFalse(0)));
+          self.emit_opcode(OpCode::Pop);
+          path.push(PathStep::Index(idx));
+          self.pattern_test(pattern, slot, path);
+          path.pop();
+        }
+        for jump in end_jumps {
+          self.patch_jump(jump);
+        }
+      }
+      Pattern::Some(pattern) => {
+        self.pattern_value(slot, path);
+        self.emit_opcode(OpCode::MatchSome);
+        if !pattern.is_irrefutable() {
+          let end_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+          self.emit_opcode(OpCode::Pop);
+          path.push(PathStep::Unwrap);
+          self.pattern_test(pattern, slot, path);
+          path.pop();
+          self.patch_jump(end_jump);
+        }
+      }
+    }
+  }
+
+  /// Declares a local for each binding in the given pattern, holding the value it matched.
+  fn pattern_bindings(&mut self, pattern: &Pattern, slot: usize, path: &mut Vec<PathStep>) {
+    match pattern {
+      Pattern::Binding(name) => {
+        let local = self.declare_variable(name);
+        self.pattern_value(slot, path);
+        self.define_variable(local);
+      }
+      Pattern::Tuple(patterns) => {
+        for (idx, pattern) in patterns.iter().enumerate() {
+          path.push(PathStep::Index(idx));
+          self.pattern_bindings(pattern, slot, path);
+          path.pop();
+        }
+      }
+      Pattern::Some(pattern) => {
+        path.push(PathStep::Unwrap);
+        self.pattern_bindings(pattern, slot, path);
+        path.pop();
+      }
+      Pattern::Wildcard | Pattern::Literal(_) => {}
+    }
   }
 
   fn assign_expr(&mut self, name: &Expr, op: &AssignOp, expr: &Expr) {
+    match name {
+      Expr::Index(tuple, index) => {
+        return self.set_element(name, tuple, Element::Index(index), op, expr)
+      }
+      Expr::Property(record, prop) => {
+        return self.set_element(name, record, Element::Property(prop), op, expr)
+      }
+      _ => {}
+    }
     if let Expr::Constant(Constant::Ident(_, name)) = &*name {
+      if self.is_function(name) {
+        self.set_error(CompileError::AssignToFunction {
+          name: name.to_string(),
+          line: self.line,
+          col: self.col,
+        });
+      }
+      if self.is_const(name) {
+        self.set_error(CompileError::AssignToConst {
+          name: name.to_string(),
+          line: self.line,
+          col: self.col,
+        });
+      }
+
+      if !matches!(op, AssignOp::Assign) {
+        self.mark_read(name);
+      }
+
       let (get_op, set_op) = self.resolve_variable(name);
       match op {
         AssignOp::Assign => {
@@ -143,7 +523,9 @@ impl Compiler {
           self.patch_jump(else_jump);
           self.emit_opcode(OpCode::Pop);
 
+          // the variable only needs to be set if the right-hand side was evaluated
           self.expression(&expr);
+          self.emit_opcode(set_op);
           self.patch_jump(end_jump);
         }
         AssignOp::AndAssign => {
@@ -152,17 +534,139 @@ impl Compiler {
 
           self.emit_opcode(OpCode::Pop);
           self.expression(&expr);
+          self.emit_opcode(set_op);
 
           self.patch_jump(end_jump);
         }
       }
     } else {
-      self.set_error(CompileError::InvalidAssignmentTarget);
+      self.set_error(CompileError::InvalidAssignmentTarget {
+        target: name.to_string(),
+        line: self.line,
+        col: self.col,
+      });
     }
   }
 
+  /// Compiles assignments like `tuple[index] = expr` and `record.field += expr`, which only work on
+  /// values stored directly in variables. Tuples and records are immutable, so this stores an
+  /// updated copy back into the variable, and any other variables holding the same value keep
+  /// seeing the original.
+  fn set_element(
+    &mut self,
+    target: &Expr,
+    container: &Expr,
+    element: Element,
+    op: &AssignOp,
+    expr: &Expr,
+  ) {
+    let name = match container {
+      Expr::Constant(Constant::Ident(_, name)) => name,
+      _ => {
+        return self.set_error(CompileError::InvalidAssignmentTarget {
+          target: target.to_string(),
+          line: self.line,
+          col: self.col,
+        })
+      }
+    };
+    if self.is_function(name) {
+      self.set_error(CompileError::AssignToFunction {
+        name: name.to_string(),
+        line: self.line,
+        col: self.col,
+      });
+    }
+    if self.is_const(name) {
+      self.set_error(CompileError::AssignToConst {
+        name: name.to_string(),
+        line: self.line,
+        col: self.col,
+      });
+    }
+    self.mark_read(name);
+
+    let (get_op, set_op) = self.resolve_variable(name);
+    let slot = self.stack_depth();
+    self.emit_opcode(get_op);
+    if let Element::Index(index) = element {
+      self.expression(index);
+    }
+
+    if !matches!(op, AssignOp::Assign) {
+      // the current value is read using the container and index already on the stack,
+      // so that the index is only evaluated once
+      self.emit_opcode(OpCode::GetLocal(slot));
+      match element {
+        Element::Index(_) => {
+          self.emit_opcode(OpCode::GetLocal(slot + 1));
+          self.emit_opcode(OpCode::Index);
+        }
+        Element::Property(prop) => self.emit_opcode(OpCode::GetProperty(prop.to_string())),
+      }
+    }
+    match op {
+      AssignOp::Assign => self.expression(expr),
+      // unlike with variables, the element is stored back even when short-circuiting,
+      // since the container and index are already on the stack waiting for it
+      AssignOp::OrAssign => {
+        let else_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+        let end_jump = self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0));
+        self.patch_jump(else_jump);
+        self.emit_opcode(OpCode::Pop);
+        self.expression(expr);
+        self.patch_jump(end_jump);
+      }
+      AssignOp::AndAssign => {
+        let end_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+        self.emit_opcode(OpCode::Pop);
+        self.expression(expr);
+        self.patch_jump(end_jump);
+      }
+      AssignOp::AddAssign
+      | AssignOp::SubtractAssign
+      | AssignOp::MultiplyAssign
+      | AssignOp::DivideAssign
+      | AssignOp::ExponentAssign => {
+        self.expression(expr);
+        self.emit_opcode(match op {
+          AssignOp::AddAssign => OpCode::Add,
+          AssignOp::SubtractAssign => OpCode::Subtract,
+          AssignOp::MultiplyAssign => OpCode::Multiply,
+          AssignOp::DivideAssign => OpCode::Divide,
+          _ => OpCode::Exponent,
+        });
+      }
+    }
+
+    match element {
+      Element::Index(_) => self.emit_opcode(OpCode::SetIndex),
+      Element::Property(prop) => self.emit_opcode(OpCode::SetProperty(prop.to_string())),
+    }
+    // the updated value is popped once it's stored, leaving the assigned value
+    self.emit_opcode(set_op);
+    self.emit_opcode(OpCode::Pop);
+  }
+
   fn binary_expr(&mut self, lhs: &Expr, op: &BinaryOp, rhs: &Expr) {
     match op {
+      BinaryOp::Coalesce => {
+        self.expression(lhs);
+        self.emit_opcode(OpCode::IsUnit);
+        let keep_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
+
+        // the left side is unit, so it's replaced by the right side
+        self.emit_opcode(OpCode::PopN(2));
+        self.expression(rhs);
+        let end_jump = self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0));
+        let depth = self.stack_depth();
+
+        // otherwise, only the check is popped, which leaves the left side
+        self.patch_jump(keep_jump);
+        self.set_stack_depth(depth + 1);
+        self.emit_opcode(OpCode::Pop);
+        self.patch_jump(end_jump);
+      }
       BinaryOp::Or => {
         self.expression(lhs);
         let else_jump = self.emit_opcode_idx(OpCode::JumpIfFalse(0));
@@ -236,6 +740,11 @@ impl Compiler {
         self.expression(&rhs);
         self.emit_opcode(OpCode::Divide);
       }
+      BinaryOp::Modulo => {
+        self.expression(&lhs);
+        self.expression(&rhs);
+        self.emit_opcode(OpCode::Modulo);
+      }
       BinaryOp::Exponent => {
         self.expression(&lhs);
         self.expression(&rhs);
@@ -265,10 +774,27 @@ impl Compiler {
     self.emit_opcode(OpCode::Index);
   }
 
-  fn call_expr(&mut self, function: &Expr, args: &Vec<Box<Expr>>) {
+  /// Errors if the function being compiled is marked `pure`, unless it's calling a pure function.
+  fn check_purity(&mut self, callee: Option<&Expr>) {
+    if self.context().fn_type != FunctionType::PureFunction {
+      return;
+    }
+    let calls_pure = match callee {
+      Some(Expr::Constant(Constant::Ident(path, name))) => path.is_empty() && self.is_pure(name),
+      _ => false,
+    };
+    if !calls_pure {
+      let name = self.context().function.name.clone();
+      self.set_error(CompileError::ImpureFunction(name));
+    }
+  }
+
+  fn call_expr(&mut self, function: &Expr, args: &[Box<Expr>]) {
+    self.check_purity(Some(function));
     self.expression(function);
-    for arg in args {
-      self.expression(&arg);
+    let args = self.order_arguments(function, args);
+    for arg in &args {
+      self.expression(arg);
       if args.len() >= u8::MAX.into() {
         self.set_error(CompileError::TooManyArguments);
       }
@@ -276,43 +802,220 @@ impl Compiler {
     self.emit_opcode(OpCode::Call(args.len().try_into().unwrap()));
   }
 
+  /// Returns the arguments of a call in the order they're passed in, with any keyword arguments
+  /// (written like `name = value`) moved to the position of the parameter they name.
+  ///
+  /// As in Python, keyword arguments come after all positional ones, and every parameter
+  /// must be given exactly one argument.
+  fn order_arguments<'a>(&mut self, function: &Expr, args: &'a [Box<Expr>]) -> Vec<&'a Expr> {
+    if args.iter().all(|arg| keyword_argument(arg).is_none()) {
+      return args.iter().map(|arg| &**arg).collect();
+    }
+
+    // the parameters' names can only be known if the callee is a function declared with `fn`
+    let (callee, params) = match function {
+      Expr::Constant(Constant::Ident(path, name)) if path.is_empty() => (
+        name.to_string(),
+        self.function_params(name).map(<[String]>::to_vec),
+      ),
+      function => (function.to_string(), None),
+    };
+    let params = match params {
+      Some(params) => params,
+      None => {
+        self.set_error(CompileError::UnknownSignature(callee));
+        return args.iter().map(|arg| &**arg).collect();
+      }
+    };
+
+    let mut ordered: Vec<Option<&Expr>> = vec![None; params.len()];
+    let mut extra = vec![];
+    let mut seen_keyword = false;
+    for (idx, arg) in args.iter().enumerate() {
+      match keyword_argument(arg) {
+        Some((name, value)) => {
+          seen_keyword = true;
+          match params.iter().position(|param| param == name) {
+            Some(pos) if ordered[pos].is_some() => {
+              self.set_error(CompileError::DuplicateArgument(name.to_string()))
+            }
+            Some(pos) => ordered[pos] = Some(value),
+            None => self.set_error(CompileError::UnknownKeywordArgument(
+              callee.clone(),
+              name.to_string(),
+            )),
+          }
+        }
+        None if seen_keyword => self.set_error(CompileError::PositionalAfterKeyword),
+        None if idx < ordered.len() => ordered[idx] = Some(arg),
+        // left for the VM to report, like any other call with too many arguments
+        None => extra.push(&**arg),
+      }
+    }
+
+    for (param, arg) in params.iter().zip(&ordered) {
+      if arg.is_none() {
+        self.set_error(CompileError::MissingArgument(param.to_string()));
+      }
+    }
+    ordered.into_iter().flatten().chain(extra).collect()
+  }
+
   fn constant_expr(&mut self, constant: &Constant) {
     match constant {
+      Constant::Unit => self.emit_opcode(OpCode::Unit),
       Constant::True => self.emit_opcode(OpCode::True),
       Constant::False => self.emit_opcode(OpCode::False),
+      Constant::None => self.emit_opcode(OpCode::None),
+      Constant::Some(expr) => {
+        self.expression(expr);
+        self.emit_opcode(OpCode::Some);
+      }
+      Constant::Ident(_, ident) if ident == DISCARD_NAME => {
+        self.set_error(CompileError::ReadDiscard);
+        self.emit_opcode(OpCode::Unit);
+      }
       Constant::Ident(_, ident) => {
+        self.mark_read(ident);
         let (get_op, _) = self.resolve_variable(ident);
         self.emit_opcode(get_op);
       }
+      Constant::Int(num) => {
+        let num_idx = self.make_constant(Value::Int(*num));
+        self.emit_opcode(OpCode::Constant(num_idx));
+      }
       Constant::Number(num) => {
         let num_idx = self.make_constant(Value::Number(*num));
         self.emit_opcode(OpCode::Constant(num_idx));
       }
       Constant::String(str) => {
-        // strip the leading and trailing quotation mark off the string:
-        let string = str[1..(str.len() - 1)].to_string();
+        let string = self.string_literal(str);
         let string_idx = self.make_constant(Value::String(string));
         self.emit_opcode(OpCode::Constant(string_idx))
       }
       Constant::Tuple(tuple) => {
+        // items between spreads are gathered into tuples of their own, which are then joined up
+        let mut items: u8 = 0;
+        let mut started = false;
         for expr in tuple {
-          self.expression(&expr);
+          if let Expr::Spread(spread) = &**expr {
+            self.join_items(OpCode::Tuple(items), &mut started);
+            items = 0;
+            self.expression(spread);
+            self.emit_opcode(OpCode::Extend);
+          } else {
+            self.expression(&expr);
+            items += 1;
+            // an opcode can only gather so many items at once, so longer runs are joined up in chunks
+            if items == u8::MAX {
+              self.join_items(OpCode::Tuple(items), &mut started);
+              items = 0;
+            }
+          }
+        }
+        if items > 0 || !started {
+          self.join_items(OpCode::Tuple(items), &mut started);
         }
-        self.emit_opcode(OpCode::Tuple(tuple.len().try_into().unwrap()));
       }
       Constant::Record(record) => {
-        for (prop, expr) in record {
-          self.expression(&expr);
-          let prop = if prop.starts_with('"') {
-            prop[1..(prop.len() - 1)].to_string()
-          } else {
-            prop.clone()
-          };
-          let idx = self.make_constant(Value::String(prop));
-          self.emit_opcode(OpCode::Constant(idx));
+        // the same goes for fields between spreads
+        let mut fields: u8 = 0;
+        let mut started = false;
+        for field in record {
+          match field {
+            Field::Named(prop, expr) => {
+              self.expression(&expr);
+              let prop = if prop.starts_with('"') {
+                self.string_literal(prop)
+              } else {
+                prop.clone()
+              };
+              let idx = self.make_constant(Value::String(prop));
+              self.emit_opcode(OpCode::Constant(idx));
+              fields += 1;
+            }
+            Field::Keyed(key, expr) => {
+              // like with named fields, the value goes beneath its key
+              self.expression(expr);
+              self.expression(key);
+              fields += 1;
+            }
+            Field::Spread(spread) => {
+              self.join_items(OpCode::Record(fields), &mut started);
+              fields = 0;
+              self.expression(spread);
+              self.emit_opcode(OpCode::Extend);
+            }
+          }
+          if fields == u8::MAX {
+            self.join_items(OpCode::Record(fields), &mut started);
+            fields = 0;
+          }
+        }
+        if fields > 0 || !started {
+          self.join_items(OpCode::Record(fields), &mut started);
         }
-        self.emit_opcode(OpCode::Record(record.len().try_into().unwrap()));
       }
     }
   }
+
+  /// Gathers the items on top of the stack into a tuple or record with the given opcode, then adds
+  /// that onto the one being built beneath it, if it's already been `started`.
+  fn join_items(&mut self, opcode: OpCode, started: &mut bool) {
+    self.emit_opcode(opcode);
+    if *started {
+      self.emit_opcode(OpCode::Extend);
+    }
+    *started = true;
+  }
+
+  /// Converts a string literal, quotation marks and all, into the string it represents.
+  fn string_literal(&mut self, literal: &str) -> String {
+    // strip the leading and trailing quotation mark off the string:
+    match unescape(&literal[1..(literal.len() - 1)]) {
+      Ok(string) => string,
+      Err(error) => {
+        self.set_error(error);
+        String::new()
+      }
+    }
+  }
+}
+
+/// Replaces the escape sequences within a string literal with the characters they stand for.
+pub(crate) fn unescape(raw: &str) -> Result<String, CompileError> {
+  let mut string = String::with_capacity(raw.len());
+  let mut chars = raw.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      string.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('n') => string.push('\n'),
+      Some('t') => string.push('\t'),
+      Some('r') => string.push('\r'),
+      Some('\\') => string.push('\\'),
+      Some('"') => string.push('"'),
+      Some('$') => string.push('$'),
+      Some('u') => {
+        let rest = chars.as_str();
+        let digits = rest
+          .strip_prefix('{')
+          .and_then(|rest| rest.split_once('}'))
+          .map_or("", |(digits, _)| digits);
+        let c = Some(digits)
+          .filter(|digits| digits.len() <= 6 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+          .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+          .and_then(char::from_u32)
+          .ok_or_else(|| CompileError::InvalidEscape(format!("\\u{{{}}}", digits)))?;
+        string.push(c);
+        // skip past the braces and the digits within them
+        chars = rest[digits.len() + 2..].chars();
+      }
+      Some(c) => return Err(CompileError::InvalidEscape(format!("\\{}", c))),
+      None => return Err(CompileError::InvalidEscape("\\".to_string())),
+    }
+  }
+  Ok(string)
 }
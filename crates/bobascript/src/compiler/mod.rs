@@ -1,12 +1,13 @@
-use std::rc::Rc;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use bobascript_parser::{grammar::AstParser, Parser, SyntaxError};
+use bobascript_parser::{ast::Ast, grammar::AstParser, Parser, SyntaxError};
 use thiserror::Error;
 
-use self::compiler::Compiler;
+pub use self::compiler::Compiler;
+pub(crate) use self::expressions::unescape;
 use crate::{
   chunk::{Chunk, Upvalue},
-  value::Function,
+  value::{Function, Value},
 };
 
 mod compiler;
@@ -25,55 +26,187 @@ pub enum CompileError {
   UnexpectedCharacter(usize, char),
   #[error("Unterminated string on line {0}.")]
   UnterminatedString(usize),
+  #[error("Invalid escape sequence \"{0}\" in string.")]
+  InvalidEscape(String),
   #[error("Expected {0}.")]
   Expected(&'static str),
-  #[error("Invalid assignment target.")]
-  InvalidAssignmentTarget,
-  #[error("A variable with the name \"{0}\" already exists in this scope.")]
-  VariableAlreadyExists(String),
-  #[error("A variable with the name \"{0}\" does not exist in scope.")]
-  VariableDoesNotExist(String),
+  #[error("Invalid assignment target \"{target}\" on line {line}, col {col}.")]
+  InvalidAssignmentTarget {
+    target: String,
+    line: usize,
+    col: usize,
+  },
+  #[error(
+    "A variable with the name \"{name}\" already exists in this scope on line {line}, col {col}."
+  )]
+  VariableAlreadyExists {
+    name: String,
+    line: usize,
+    col: usize,
+  },
+  #[error(
+    "A variable with the name \"{name}\" does not exist in scope on line {line}, col {col}."
+  )]
+  VariableDoesNotExist {
+    name: String,
+    line: usize,
+    col: usize,
+  },
   #[error("Functions and function calls can only have a maximum of 255 arguments. Why do you need that many?")]
   TooManyArguments,
   #[error("Cannot return from top-level code.")]
   TopLevelReturn,
+  #[error("Too much code to jump over.")]
+  JumpTooLarge,
+  #[error("Cannot assign to \"{name}\" on line {line}, col {col} because it is a function.")]
+  AssignToFunction {
+    name: String,
+    line: usize,
+    col: usize,
+  },
+  #[error("Cannot assign to \"{name}\" on line {line}, col {col} because it is a constant.")]
+  AssignToConst {
+    name: String,
+    line: usize,
+    col: usize,
+  },
+  #[error("Cannot use \"break\" or \"continue\" outside of a loop.")]
+  BreakOutsideLoop,
+  #[error("A loop with the label '{0} does not exist in scope.")]
+  UndefinedLabel(String),
+  #[error("The program is too large to compile.")]
+  ProgramTooLarge,
+  #[error("Functions can only capture a maximum of 256 variables.")]
+  TooManyUpvalues,
+  #[error("\"_\" discards whatever is bound to it, so it can't be used as a value.")]
+  ReadDiscard,
+  #[error("The pure function \"{0}\" can only call other pure functions, and cannot log.")]
+  ImpureFunction(String),
+  #[error("Keyword arguments can only be passed to functions declared with \"fn\", which \"{0}\" isn't known to be.")]
+  UnknownSignature(String),
+  #[error("The function \"{0}\" has no parameter named \"{1}\".")]
+  UnknownKeywordArgument(String, String),
+  #[error("The parameter \"{0}\" was given more than one argument.")]
+  DuplicateArgument(String),
+  #[error("No argument was given for the parameter \"{0}\".")]
+  MissingArgument(String),
+  #[error("Positional arguments cannot come after keyword arguments.")]
+  PositionalAfterKeyword,
+  #[error("\"...\" can only be used within tuple and record literals.")]
+  SpreadOutsideLiteral,
+}
+
+#[derive(Debug, Error, Clone)]
+pub enum CompileWarning {
+  #[error("Unused variable \"{0}\". Prefix it with an underscore if this is intentional.")]
+  UnusedVariable(String),
+  #[error("The value of an \"if\" without an \"else\" is used, but will be unit whenever its condition is false.")]
+  IfWithoutElseInValuePosition,
+  #[error("A condition assigns to a variable, where you may have meant to compare with \"==\". Wrap the assignment in a block if this is intentional.")]
+  AssignmentInCondition,
 }
 
 pub struct Local {
   name: String,
   // todo: change this so we don't use -1 for uninitialized locals
   depth: i32,
+  /// The local's position in its function's stack window.
+  slot: usize,
   is_captured: bool,
+  is_function: bool,
+  /// Whether the local is a function marked `pure`.
+  is_pure: bool,
+  /// The names of the function's parameters, if the local is one.
+  params: Vec<String>,
+  is_const: bool,
+  /// The local's value, if it's a const whose value is known at compile time.
+  const_value: Option<Value>,
+  is_read: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FunctionType {
   /// The root (or top) level script.
   TopLevel,
-  /// A block expression.
-  Block,
   /// A function within the script.
   Function,
+  /// A function within the script marked `pure`, which can't log or call impure functions.
+  PureFunction,
 }
+/// A loop currently being compiled, which `break` and `continue` can target.
+pub struct Loop {
+  label: Option<String>,
+  /// The index of the loop's first instruction.
+  start: usize,
+  /// The stack slot the loop's resulting value is kept in.
+  slot: usize,
+  /// The indices of all `break` jumps that need to be patched to the end of the loop.
+  breaks: Vec<usize>,
+  /// The number of `try` blocks the loop is inside of, so that any it contains are
+  /// ended when a `break` or `continue` leaves them.
+  tries: usize,
+}
+
 pub struct CompileContext {
   function: Function,
   fn_type: FunctionType,
   locals: Vec<Local>,
   upvalues: Vec<Upvalue>,
   scope_depth: i32,
+  /// The number of values the compiled code will have on the stack at this point,
+  /// including the function itself in slot 0.
+  stack_depth: usize,
+  loops: Vec<Loop>,
+  /// The number of `try` blocks being compiled within this function.
+  tries: usize,
+  /// The globals declared as consts, along with their values if known at compile time.
+  consts: HashMap<String, Option<Value>>,
 }
 impl CompileContext {
   pub fn new(fn_type: FunctionType) -> Self {
-    Self {
+    let mut context = Self {
       function: Function::default(),
       fn_type,
-      locals: vec![Local {
-        name: "".to_string(),
-        depth: 0,
-        is_captured: false,
-      }],
+      locals: Vec::new(),
       upvalues: Vec::new(),
       scope_depth: 0,
+      stack_depth: 1,
+      loops: Vec::new(),
+      tries: 0,
+      consts: HashMap::new(),
+    };
+    context.locals.push(Self::function_local());
+    context
+  }
+
+  /// Returns the context to the state it was in when created for a function of the given type,
+  /// keeping the space its locals, upvalues, loops, and consts have already taken up.
+  fn reset(&mut self, fn_type: FunctionType) {
+    self.function = Function::default();
+    self.fn_type = fn_type;
+    self.locals.clear();
+    self.locals.push(Self::function_local());
+    self.upvalues.clear();
+    self.scope_depth = 0;
+    self.stack_depth = 1;
+    self.loops.clear();
+    self.tries = 0;
+    self.consts.clear();
+  }
+
+  /// The local in slot 0, which holds the function itself.
+  fn function_local() -> Local {
+    Local {
+      name: "".to_string(),
+      depth: 0,
+      slot: 0,
+      is_captured: false,
+      is_function: false,
+      is_pure: false,
+      params: Vec::new(),
+      is_const: false,
+      const_value: None,
+      is_read: true,
     }
   }
 
@@ -81,29 +214,82 @@ impl CompileContext {
     &mut self.function.chunk
   }
 
-  fn resolve_local(&self, name: &str) -> CompileResult<Option<usize>> {
+  /// Returns the index of the innermost local with the given name,
+  /// along with whether it has been initialized yet.
+  fn resolve_local(&self, name: &str) -> Option<(usize, bool)> {
     for i in (0..self.locals.len()).rev() {
       if name == self.locals[i].name {
-        return if self.locals[i].depth == -1 {
-          Err(CompileError::VariableDoesNotExist(
-            self.locals[i].name.clone(),
-          ))
-        } else {
-          // println!("yeah we got a {} at index {}", name, i);
-          Ok(Some(i))
-        };
+        return Some((i, self.locals[i].depth != -1));
       }
     }
-    Ok(None)
+    None
   }
 }
 
 /// Compiles the given source code and returns its resulting function.
 pub fn compile<S>(source: S) -> CompileResult<Rc<Function>>
+where
+  S: Into<String>,
+{
+  compile_with_warnings(source).map(|(function, _)| function)
+}
+
+thread_local! {
+  static POOLED_COMPILER: RefCell<Compiler> = RefCell::new(Compiler::new());
+}
+
+/// Compiles the given source code like [compile], but through a compiler kept for the current thread,
+/// so that compiling many scripts reuses the space allocated for the ones before them.
+pub fn compile_pooled<S>(source: S) -> CompileResult<Rc<Function>>
+where
+  S: Into<String>,
+{
+  POOLED_COMPILER.with(|compiler| compiler.borrow_mut().compile_source(source))
+}
+
+/// Compiles the given source code and returns its resulting function,
+/// along with any warnings found while compiling it.
+pub fn compile_with_warnings<S>(source: S) -> CompileResult<(Rc<Function>, Vec<CompileWarning>)>
 where
   S: Into<String>,
 {
   let ast = AstParser::parse_ast(&source.into())?;
   let mut compiler = Compiler::new();
+  let function = compiler.compile(&ast)?;
+  Ok((function, compiler.take_warnings()))
+}
+
+/// Compiles the given source code without running it, returning every error and warning found.
+pub fn check<S>(source: S) -> (Vec<CompileError>, Vec<CompileWarning>)
+where
+  S: Into<String>,
+{
+  let ast = match AstParser::parse_ast_all(&source.into()) {
+    Ok(ast) => ast,
+    Err(errors) => return (errors.into_iter().map(Into::into).collect(), vec![]),
+  };
+  let mut compiler = Compiler::new();
+  let errors = compiler.check(&ast);
+  (errors, compiler.take_warnings())
+}
+
+/// Compiles the given source code like [compile], but fails with [CompileError::ProgramTooLarge]
+/// if any function would need more than `max_chunk_size` instructions or constants.
+///
+/// This is useful for guarding against enormous scripts from untrusted sources.
+pub fn compile_with_limit<S>(source: S, max_chunk_size: usize) -> CompileResult<Rc<Function>>
+where
+  S: Into<String>,
+{
+  let ast = AstParser::parse_ast(&source.into())?;
+  let mut compiler = Compiler::with_max_chunk_size(max_chunk_size);
   compiler.compile(&ast)
 }
+
+/// Compiles an already parsed (or hand-built) [Ast] and returns its resulting function.
+///
+/// See [bobascript_parser::ast::mk] for helpers to build an [Ast] without any source code.
+pub fn compile_ast(ast: &Ast) -> CompileResult<Rc<Function>> {
+  let mut compiler = Compiler::new();
+  compiler.compile(ast)
+}
@@ -1,30 +1,55 @@
 use bobascript_parser::ast::{Expr, Stmt};
 
-use crate::chunk::OpCode;
+use crate::chunk::{JumpDirection, OpCode};
 
-use super::{compiler::Compiler, CompileError, FunctionType};
+use super::{
+  compiler::{Compiler, DISCARD_NAME},
+  CompileError, FunctionType,
+};
 
 impl Compiler {
   pub fn statement(&mut self, stmt: &Stmt) {
+    let depth = self.stack_depth();
+    let locals = self.context().locals.len();
+
     match &*stmt {
-      Stmt::Function(ident, args, block) => self.function_stmt(ident, args, block),
-      Stmt::Const(_, _) => self.const_stmt(),
+      Stmt::Function(ident, args, block, pure) => self.function_stmt(ident, args, block, *pure),
+      Stmt::Const(ident, expr) => self.const_stmt(ident, expr),
       Stmt::Let(ident, expr) => self.let_stmt(ident, expr),
       Stmt::Return(expr) => self.return_stmt(expr),
-      Stmt::Break(_) => self.break_stmt(),
-      Stmt::Expression(expr) => self.expression_stmt(expr),
+      Stmt::Break(label, expr) => self.break_stmt(label, expr),
+      Stmt::Continue(label) => self.continue_stmt(label),
+      Stmt::Empty => {}
+      Stmt::Expression(expr) => self.expression_stmt(expr, false),
     }
+
+    // a statement leaves nothing behind on the stack, other than any local it declares
+    self.check_stack_depth(depth + self.context().locals.len() - locals, "a statement");
   }
 
-  fn function_stmt(&mut self, ident: &str, args: &[String], block: &Expr) {
+  fn function_stmt(&mut self, ident: &str, args: &[String], block: &Expr, pure: bool) {
     let global_idx = self.declare_variable(ident);
-    self.mark_initialized();
-    self.function(FunctionType::Function, ident, args, block);
+    if ident != DISCARD_NAME {
+      self.mark_initialized();
+      self.mark_function(ident, args, pure);
+    }
+    let fn_type = if pure {
+      FunctionType::PureFunction
+    } else {
+      FunctionType::Function
+    };
+    self.function(fn_type, ident, args, block);
     self.define_variable(global_idx);
   }
 
-  fn const_stmt(&mut self) {
-    todo!("add const statement");
+  fn const_stmt(&mut self, ident: &str, expr: &Expr) {
+    let value = self.fold(expr);
+    let global = self.declare_variable(ident);
+    self.expression(expr);
+    if ident != DISCARD_NAME {
+      self.mark_const(ident, value);
+    }
+    self.define_variable(global);
   }
 
   fn let_stmt(&mut self, ident: &str, expr: &Option<Box<Expr>>) {
@@ -33,7 +58,7 @@ impl Compiler {
     if let Some(expr) = expr {
       self.expression(&expr);
     } else {
-      self.emit_opcode(OpCode::Tuple(0));
+      self.emit_opcode(OpCode::Unit);
     }
 
     self.define_variable(global);
@@ -46,16 +71,58 @@ impl Compiler {
 
     if let Some(expr) = expr {
       self.expression(&expr);
+      self.emit_opcode(OpCode::Return);
+    } else {
+      self.emit_opcode(OpCode::ReturnUnit);
     }
-    self.emit_opcode(OpCode::Return);
   }
 
-  fn break_stmt(&mut self) {
-    todo!("add break statement");
+  fn break_stmt(&mut self, label: &Option<String>, expr: &Option<Box<Expr>>) {
+    let idx = match self.resolve_loop(label) {
+      Some(idx) => idx,
+      None => return,
+    };
+    let depth = self.stack_depth();
+    let slot = self.loop_slot(idx);
+
+    if let Some(expr) = expr {
+      self.expression(&expr);
+      self.emit_opcode(OpCode::SetLocal(slot));
+    }
+
+    // leave only the loop's value behind, then jump past the end of the loop
+    self.discard_to(slot + 1);
+    self.end_loop_tries(idx);
+    let jump = self.emit_opcode_idx(OpCode::Jump(JumpDirection::Forwards, 0));
+    self.add_loop_break(idx, jump);
+
+    // nothing after this runs, but keep compiling as if it does
+    self.set_stack_depth(depth);
   }
 
-  fn expression_stmt(&mut self, expr: &Expr) {
-    self.expression(&expr);
-    self.emit_opcode(OpCode::Pop);
+  fn continue_stmt(&mut self, label: &Option<String>) {
+    let idx = match self.resolve_loop(label) {
+      Some(idx) => idx,
+      None => return,
+    };
+    let depth = self.stack_depth();
+    let slot = self.loop_slot(idx);
+
+    self.discard_to(slot + 1);
+    self.end_loop_tries(idx);
+    self.emit_loop(self.loop_start(idx));
+
+    self.set_stack_depth(depth);
+  }
+
+  /// Compiles an expression statement, where `top_level` is whether it's directly within the script,
+  /// in which case its value can be collected before it's thrown away.
+  pub(super) fn expression_stmt(&mut self, expr: &Expr, top_level: bool) {
+    self.discarded_expr(expr);
+    self.emit_opcode(if top_level {
+      OpCode::PopResult
+    } else {
+      OpCode::Pop
+    });
   }
 }
@@ -3,7 +3,7 @@ use crate::chunk::{Chunk, OpCode};
 pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
   println!("== {} ==", name);
 
-  for (i, opcode) in chunk.code.iter().enumerate() {
+  for (i, (opcode, _)) in chunk.code.iter().enumerate() {
     disassemble_instruction(chunk, opcode, i);
   }
 }
@@ -13,6 +13,6 @@ pub fn disassemble_instruction(chunk: &Chunk, opcode: &OpCode, offset: usize) {
     OpCode::Constant(idx) => format!("Constant {:0>#4} {}", idx, chunk.constants[*idx]),
     _ => format!("{:?}", opcode),
   };
-  // println!("{:0>#4} #{:0>#4} {}", offset, line, instruction);
-  println!("{:0>#4} {}", offset, instruction);
+  let line = chunk.code[offset].1;
+  println!("{:0>#4} #{:0>#4} {}", offset, line, instruction);
 }
@@ -1,11 +1,18 @@
 use compiler::CompileError;
 use thiserror::Error;
+use verify::VerifyError;
 use vm::RuntimeError;
 
+pub use bobascript_parser::ast;
+
+pub mod asm;
+pub mod bytecode;
 pub mod chunk;
 pub mod compiler;
 pub mod debug;
+pub mod stdlib;
 pub mod value;
+pub mod verify;
 pub mod vm;
 
 #[cfg(not(feature = "debug"))]
@@ -26,6 +33,8 @@ pub enum InterpretError {
   Unknown,
   #[error("An error occurred during compilation:\n{0}")]
   CompileError(#[from] CompileError),
-  #[error("An error occurred during execution:\n{0}")]
-  RuntimeError(#[from] RuntimeError),
+  #[error("An error occurred during execution on line {line}:\n{error}")]
+  RuntimeError { error: RuntimeError, line: usize },
+  #[error("The compiled bytecode failed verification:\n{0}")]
+  VerifyError(#[from] VerifyError),
 }
@@ -0,0 +1,481 @@
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, convert::TryInto, rc::Rc};
+
+use crate::{
+  value::{Arithmetic, NativeFunction, NativeProperty, Value},
+  vm::{RuntimeError, VM},
+};
+
+/// Native properties, grouped by the name of the type of value they're on.
+pub type PropertyTable = HashMap<&'static str, HashMap<String, NativeProperty>>;
+
+/// A set of native functions that can be shared between any number of VMs.
+///
+/// Natives are stored behind `Rc`s, so handing the same registry to many VMs
+/// (see `VM::with_shared_stdlib`) only clones pointers instead of the natives themselves.
+/// This also means a native that keeps state of its own shares it between all of those VMs,
+/// so the standard library's natives keep any state they need (like when `clock` started) on the VM.
+#[derive(Debug, Default)]
+pub struct StdlibRegistry {
+  natives: HashMap<String, Rc<RefCell<NativeFunction>>>,
+  properties: PropertyTable,
+}
+impl StdlibRegistry {
+  /// Creates a registry of every native in the standard library: those in the prelude
+  /// that every VM starts out with, along with those VMs otherwise opt in to with
+  /// `VM::register_std`, `VM::register_math`, and `VM::register_string_lib`.
+  pub fn with_std() -> Self {
+    let mut registry = Self::default();
+    let natives = prelude_natives()
+      .into_iter()
+      .chain(std_natives())
+      .chain(math_natives())
+      .chain(string_natives());
+    for (name, function) in natives {
+      registry.define_native(name.to_string(), Rc::new(RefCell::new(function)));
+    }
+    registry
+  }
+
+  pub fn define_native(&mut self, name: String, function: Rc<RefCell<NativeFunction>>) {
+    self.natives.insert(name, function);
+  }
+
+  pub fn natives(&self) -> impl Iterator<Item = (&String, &Rc<RefCell<NativeFunction>>)> {
+    self.natives.iter()
+  }
+
+  /// Defines a property on every value whose [Value::type_name] is `type_name`.
+  pub fn define_property(
+    &mut self,
+    type_name: &'static str,
+    name: String,
+    property: NativeProperty,
+  ) {
+    insert_property(&mut self.properties, type_name, name, property);
+  }
+
+  pub fn properties(&self) -> impl Iterator<Item = (&'static str, &String, &NativeProperty)> {
+    self.properties.iter().flat_map(|(type_name, properties)| {
+      properties
+        .iter()
+        .map(move |(name, property)| (*type_name, name, property))
+    })
+  }
+}
+
+fn insert_property(
+  properties: &mut PropertyTable,
+  type_name: &'static str,
+  name: String,
+  property: NativeProperty,
+) {
+  properties
+    .entry(type_name)
+    .or_default()
+    .insert(name, property);
+}
+
+/// Defines the native properties every VM starts out with.
+pub(crate) fn define_prelude_properties(properties: &mut PropertyTable) {
+  let prelude = vec![
+    (
+      "string",
+      "length",
+      NativeProperty {
+        getter: string_length,
+      },
+    ),
+    (
+      "tuple",
+      "length",
+      NativeProperty {
+        getter: tuple_length,
+      },
+    ),
+  ];
+  for (type_name, name, property) in prelude {
+    insert_property(properties, type_name, name.to_string(), property);
+  }
+}
+
+/// The natives every VM starts out with.
+pub(crate) fn prelude_natives() -> Vec<(&'static str, NativeFunction)> {
+  vec![
+    ("len", NativeFunction::new(len)),
+    ("byte_len", NativeFunction::new(byte_len)),
+    ("str", NativeFunction::reentrant(str)),
+    ("repr", NativeFunction::new(repr)),
+    ("to_string", NativeFunction::reentrant(to_string)),
+    ("parse_number", NativeFunction::new(parse_number)),
+    ("is_some", NativeFunction::new(is_some)),
+    ("unwrap_or", NativeFunction::new(unwrap_or)),
+  ]
+}
+
+/// The natives of the standard library, which VMs only have if they opt in.
+pub(crate) fn std_natives() -> Vec<(&'static str, NativeFunction)> {
+  vec![
+    ("type", NativeFunction::new(type_of)),
+    ("clock", NativeFunction::reentrant(clock)),
+    ("map", NativeFunction::reentrant(map)),
+    ("filter", NativeFunction::reentrant(filter)),
+    ("reduce", NativeFunction::reentrant(reduce)),
+  ]
+}
+
+/// The natives of the math library (like `sqrt` and `min`), which VMs only have if they opt in.
+pub(crate) fn math_natives() -> Vec<(&'static str, NativeFunction)> {
+  vec![
+    ("sqrt", NativeFunction::new(sqrt)),
+    ("abs", NativeFunction::new(abs)),
+    ("floor", NativeFunction::new(floor)),
+    ("ceil", NativeFunction::new(ceil)),
+    ("round", NativeFunction::new(round)),
+    ("pow", NativeFunction::new(pow)),
+    ("min", NativeFunction::new(min)),
+    ("max", NativeFunction::new(max)),
+  ]
+}
+
+/// The natives of the string library (like `upper` and `split`), which VMs only have if they opt in.
+pub(crate) fn string_natives() -> Vec<(&'static str, NativeFunction)> {
+  vec![
+    ("upper", NativeFunction::new(upper)),
+    ("lower", NativeFunction::new(lower)),
+    ("trim", NativeFunction::new(trim)),
+    ("contains", NativeFunction::new(contains)),
+    ("split", NativeFunction::new(split)),
+  ]
+}
+
+/// Defines each of the given natives as a global.
+pub(crate) fn define_natives(
+  globals: &mut HashMap<String, Value>,
+  natives: Vec<(&'static str, NativeFunction)>,
+) {
+  for (name, function) in natives {
+    globals.insert(
+      name.to_string(),
+      Value::NativeFunction(Rc::new(RefCell::new(function))),
+    );
+  }
+}
+
+/// Checks that exactly `count` values were passed.
+fn param_count(params: &[Value], count: u8) -> Result<(), RuntimeError> {
+  if params.len() != count as usize {
+    return Err(RuntimeError::IncorrectParameterCount(
+      count,
+      params.len().try_into().unwrap_or(u8::MAX),
+    ));
+  }
+  Ok(())
+}
+
+/// Checks that exactly one value was passed, and returns it.
+fn value_param(params: &[Value]) -> Result<&Value, RuntimeError> {
+  param_count(params, 1)?;
+  Ok(&params[0])
+}
+
+/// Checks that a value is a string, and returns it.
+fn string(value: &Value) -> Result<&str, RuntimeError> {
+  match value {
+    Value::String(string) => Ok(string),
+    value => Err(RuntimeError::TypeError {
+      expected: "string",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// Checks that exactly one string was passed, and returns it.
+fn string_param(params: &[Value]) -> Result<&str, RuntimeError> {
+  string(value_param(params)?)
+}
+
+/// Checks that exactly two strings were passed, and returns them.
+fn string_params(params: &[Value]) -> Result<(&str, &str), RuntimeError> {
+  param_count(params, 2)?;
+  Ok((string(&params[0])?, string(&params[1])?))
+}
+
+/// Checks that a value is a number of either kind, and returns it.
+fn number(value: &Value) -> Result<&Value, RuntimeError> {
+  match value {
+    Value::Int(_) | Value::Number(_) => Ok(value),
+    value => Err(RuntimeError::TypeError {
+      expected: "number",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// Checks that exactly one number was passed, and returns it.
+fn number_param(params: &[Value]) -> Result<&Value, RuntimeError> {
+  number(value_param(params)?)
+}
+
+/// Returns the number of elements in a tuple, entries in a record,
+/// or characters (Unicode scalar values) in a string.
+fn len(params: &[Value]) -> Result<Value, RuntimeError> {
+  let len = match value_param(params)? {
+    Value::Tuple(tuple) => tuple.len(),
+    Value::Record(record) => record.len(),
+    Value::String(string) => string.chars().count(),
+    _ => return Err(RuntimeError::OperationNotSupported),
+  };
+  Ok(Value::Int(len as i64))
+}
+
+/// Returns the number of bytes a string takes up when encoded as UTF-8.
+fn byte_len(params: &[Value]) -> Result<Value, RuntimeError> {
+  let string = string_param(params)?;
+  Ok(Value::Int(string.len() as i64))
+}
+
+/// Returns the plain form of a value, as it's logged (so strings aren't quoted),
+/// with numbers written in the format of the VM it's called from.
+fn str(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  let value = value_param(params)?;
+  Ok(Value::String(value.to_string_with(vm.number_format())))
+}
+
+/// Returns the form of a value as it would be written in a script, with strings quoted and escaped.
+fn repr(params: &[Value]) -> Result<Value, RuntimeError> {
+  let value = value_param(params)?;
+  Ok(Value::String(value.repr()))
+}
+
+/// Returns the display form of any value, the same as `str`.
+fn to_string(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  str(vm, params)
+}
+
+/// Parses a string as a number in the same way as a literal, so whole numbers are integers,
+/// ignoring any whitespace around it.
+fn parse_number(params: &[Value]) -> Result<Value, RuntimeError> {
+  let string = string_param(params)?;
+  let trimmed = string.trim();
+  if let Ok(int) = trimmed.parse() {
+    return Ok(Value::Int(int));
+  }
+  match trimmed.parse::<f64>() {
+    Ok(num) if num.is_finite() => Ok(Value::Number(num)),
+    _ => Err(RuntimeError::ParseError(string.to_string())),
+  }
+}
+
+/// Returns whether an optional value is present.
+fn is_some(params: &[Value]) -> Result<Value, RuntimeError> {
+  match value_param(params)? {
+    Value::Some(_) => Ok(Value::Boolean(true)),
+    Value::None => Ok(Value::Boolean(false)),
+    value => Err(RuntimeError::TypeError {
+      expected: "option",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// Returns the value held by an optional value, or the given default if it's `none`.
+fn unwrap_or(params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 2)?;
+  match &params[0] {
+    Value::Some(value) => Ok((**value).clone()),
+    Value::None => Ok(params[1].clone()),
+    value => Err(RuntimeError::TypeError {
+      expected: "option",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// Returns the name of a value's type, such as `"number"` or `"function"`.
+fn type_of(params: &[Value]) -> Result<Value, RuntimeError> {
+  let value = value_param(params)?;
+  Ok(Value::String(value.type_name().to_string()))
+}
+
+/// Returns the number of seconds since the VM it's called from was created, which never goes down.
+fn clock(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 0)?;
+  Ok(Value::Number(vm.created().elapsed().as_secs_f64()))
+}
+
+/// Checks that a value is a tuple, and returns a copy of its elements.
+fn tuple(value: &Value) -> Result<Vec<Value>, RuntimeError> {
+  value.clone().try_into()
+}
+
+/// Calls a function on each element of a tuple, returning a tuple of the results.
+fn map(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 2)?;
+  let function = &params[1];
+  let mapped = tuple(&params[0])?
+    .into_iter()
+    .map(|element| vm.invoke(function.clone(), vec![element]))
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(Value::tuple_from(mapped))
+}
+
+/// Returns a tuple of only the elements of a tuple that a function returns true for.
+fn filter(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 2)?;
+  let function = &params[1];
+  let mut kept = Vec::new();
+  for element in tuple(&params[0])? {
+    let keep: bool = vm
+      .invoke(function.clone(), vec![element.clone()])?
+      .try_into()?;
+    if keep {
+      kept.push(element);
+    }
+  }
+  Ok(Value::tuple_from(kept))
+}
+
+/// Folds the elements of a tuple into one value, starting from `init`
+/// and calling a function with what's been accumulated so far and each element in turn.
+fn reduce(vm: &mut VM, params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 3)?;
+  let function = &params[1];
+  tuple(&params[0])?
+    .into_iter()
+    .try_fold(params[2].clone(), |accumulated, element| {
+      vm.invoke(function.clone(), vec![accumulated, element])
+    })
+}
+
+/// The number of characters in a string, the same as `len`.
+fn string_length(value: &Value) -> Result<Value, RuntimeError> {
+  match value {
+    Value::String(string) => Ok(Value::Int(string.chars().count() as i64)),
+    value => Err(RuntimeError::TypeError {
+      expected: "string",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// The number of elements in a tuple.
+fn tuple_length(value: &Value) -> Result<Value, RuntimeError> {
+  match value {
+    Value::Tuple(tuple) => Ok(Value::Int(tuple.len() as i64)),
+    value => Err(RuntimeError::TypeError {
+      expected: "tuple",
+      found: value.clone(),
+    }),
+  }
+}
+
+/// Returns the square root of a number, which is always a float.
+fn sqrt(params: &[Value]) -> Result<Value, RuntimeError> {
+  let num = number_param(params)?.as_f64().unwrap();
+  Ok(Value::Number(num.sqrt()))
+}
+
+/// Returns the absolute value of a number, keeping integers as integers unless they'd overflow.
+fn abs(params: &[Value]) -> Result<Value, RuntimeError> {
+  Ok(match number_param(params)? {
+    Value::Int(num) => num
+      .checked_abs()
+      .map_or(Value::Number((*num as f64).abs()), Value::Int),
+    value => Value::Number(value.as_f64().unwrap().abs()),
+  })
+}
+
+/// Rounds a float with the given function, while integers are already whole and are returned as they are.
+fn round_with(params: &[Value], round: fn(f64) -> f64) -> Result<Value, RuntimeError> {
+  Ok(match number_param(params)? {
+    Value::Int(num) => Value::Int(*num),
+    value => Value::Number(round(value.as_f64().unwrap())),
+  })
+}
+
+fn floor(params: &[Value]) -> Result<Value, RuntimeError> {
+  round_with(params, f64::floor)
+}
+
+fn ceil(params: &[Value]) -> Result<Value, RuntimeError> {
+  round_with(params, f64::ceil)
+}
+
+/// Rounds half-way cases away from zero.
+fn round(params: &[Value]) -> Result<Value, RuntimeError> {
+  round_with(params, f64::round)
+}
+
+/// Raises the first number to the power of the second, in the same way as `^`.
+fn pow(params: &[Value]) -> Result<Value, RuntimeError> {
+  param_count(params, 2)?;
+  let (base, exponent) = (number(&params[0])?, number(&params[1])?);
+  Ok(Arithmetic::Exponent.apply(base, exponent).unwrap())
+}
+
+/// Returns whichever of the numbers passed (at least one) compares as `ordering` to all the others,
+/// picking the first of any that are equal.
+fn extreme(params: &[Value], ordering: Ordering) -> Result<Value, RuntimeError> {
+  if params.is_empty() {
+    return Err(RuntimeError::IncorrectParameterCount(1, 0));
+  }
+  let mut best = number(&params[0])?;
+  for param in &params[1..] {
+    let param = number(param)?;
+    let compared = match (param, best) {
+      (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+      (a, b) => a.as_f64().unwrap().partial_cmp(&b.as_f64().unwrap()),
+    };
+    if compared == Some(ordering) {
+      best = param;
+    }
+  }
+  Ok(best.clone())
+}
+
+/// Returns the smallest of any number of numbers.
+fn min(params: &[Value]) -> Result<Value, RuntimeError> {
+  extreme(params, Ordering::Less)
+}
+
+/// Returns the largest of any number of numbers.
+fn max(params: &[Value]) -> Result<Value, RuntimeError> {
+  extreme(params, Ordering::Greater)
+}
+
+fn upper(params: &[Value]) -> Result<Value, RuntimeError> {
+  Ok(Value::String(string_param(params)?.to_uppercase()))
+}
+
+fn lower(params: &[Value]) -> Result<Value, RuntimeError> {
+  Ok(Value::String(string_param(params)?.to_lowercase()))
+}
+
+/// Removes whitespace from both ends of a string.
+fn trim(params: &[Value]) -> Result<Value, RuntimeError> {
+  Ok(Value::String(string_param(params)?.trim().to_string()))
+}
+
+/// Returns whether the first string contains the second (which every string does if it's empty).
+fn contains(params: &[Value]) -> Result<Value, RuntimeError> {
+  let (string, substring) = string_params(params)?;
+  Ok(Value::Boolean(string.contains(substring)))
+}
+
+/// Splits a string into a tuple of the parts between each separator,
+/// or into its characters if the separator is empty.
+fn split(params: &[Value]) -> Result<Value, RuntimeError> {
+  let (string, separator) = string_params(params)?;
+  let parts = if separator.is_empty() {
+    string
+      .chars()
+      .map(|c| Value::String(c.to_string()))
+      .collect()
+  } else {
+    string
+      .split(separator)
+      .map(|part| Value::String(part.to_string()))
+      .collect::<Vec<_>>()
+  };
+  Ok(Value::tuple_from(parts))
+}
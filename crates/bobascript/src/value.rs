@@ -1,6 +1,19 @@
-use std::{cell::RefCell, collections::HashMap, convert::TryInto, fmt, rc::Rc};
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  convert::{TryFrom, TryInto},
+  fmt,
+  hash::{Hash, Hasher},
+  mem::size_of,
+  rc::Rc,
+};
 
-use crate::{chunk::Chunk, vm::RuntimeError};
+use indexmap::IndexMap;
+
+use crate::{
+  chunk::Chunk,
+  vm::{RuntimeError, VM},
+};
 
 #[derive(Debug)]
 pub struct Function {
@@ -18,8 +31,42 @@ impl Default for Function {
   }
 }
 
+/// The signature of a function implemented by the host.
+pub type NativeFn = dyn FnMut(&[Value]) -> Result<Value, RuntimeError>;
+
+/// The signature of a function implemented by the host that calls back into the VM,
+/// like `map`, which calls the function it's passed.
+pub type ReentrantFn = fn(&mut VM, &[Value]) -> Result<Value, RuntimeError>;
+
+/// A function implemented by the host, which may capture and mutate state of its own.
 pub struct NativeFunction {
-  pub function: fn(&[Value]) -> Result<Value, RuntimeError>,
+  pub function: Box<NativeFn>,
+  /// Set for natives that call back into the VM, which are run in place of `function`.
+  pub(crate) reentrant: Option<ReentrantFn>,
+}
+impl NativeFunction {
+  pub fn new<F>(function: F) -> Self
+  where
+    F: FnMut(&[Value]) -> Result<Value, RuntimeError> + 'static,
+  {
+    Self {
+      function: Box::new(function),
+      reentrant: None,
+    }
+  }
+
+  /// Creates a native that's given the VM it's called from, so that it can call other functions.
+  pub fn reentrant(function: ReentrantFn) -> Self {
+    Self {
+      function: Box::new(|_| Err(RuntimeError::InvalidCallSignature)),
+      reentrant: Some(function),
+    }
+  }
+}
+impl From<fn(&[Value]) -> Result<Value, RuntimeError>> for NativeFunction {
+  fn from(function: fn(&[Value]) -> Result<Value, RuntimeError>) -> Self {
+    Self::new(function)
+  }
 }
 impl fmt::Debug for NativeFunction {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -27,6 +74,17 @@ impl fmt::Debug for NativeFunction {
   }
 }
 
+/// A read-only property that every value of some type has, computed by the host.
+#[derive(Clone, Copy)]
+pub struct NativeProperty {
+  pub getter: fn(&Value) -> Result<Value, RuntimeError>,
+}
+impl fmt::Debug for NativeProperty {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "<native property>")
+  }
+}
+
 #[derive(Clone)]
 pub struct Closure {
   pub function: Rc<Function>,
@@ -46,8 +104,19 @@ pub enum Upvalue {
 
 #[derive(Debug, Clone)]
 pub enum Value {
-  Tuple(Box<[Value]>),
-  Record(HashMap<String, Value>),
+  /// The value of anything that doesn't produce a value, written as `()`.
+  Unit,
+  /// `none`, an optional value that's missing, which is distinct from unit.
+  None,
+  /// `some(x)`, an optional value that's present.
+  Some(Box<Value>),
+  /// Tuples and records are immutable, so they're shared behind an `Rc` to keep cloning cheap.
+  Tuple(Rc<[Value]>),
+  /// Fields are kept in the order they were inserted in, and are usually keyed by strings,
+  /// though any value that [Value::is_hashable] may be used as a key.
+  Record(Rc<IndexMap<RecordKey, Value>>),
+  /// An integer, which arithmetic keeps as one until it's mixed with a float (or overflows).
+  Int(i64),
   Number(f64),
   Boolean(bool),
   String(String),
@@ -58,28 +127,276 @@ pub enum Value {
 
 impl Value {
   pub fn get_unit() -> Self {
-    Self::Tuple(vec![].into_boxed_slice())
+    Self::Unit
+  }
+
+  /// Builds a tuple out of the values from an iterator.
+  pub fn tuple_from<I>(iter: I) -> Self
+  where
+    I: IntoIterator<Item = Value>,
+  {
+    Self::Tuple(iter.into_iter().collect())
+  }
+
+  /// Builds a record out of the fields from an iterator, in the order they're given.
+  pub fn record_from<I>(iter: I) -> Self
+  where
+    I: IntoIterator<Item = (String, Value)>,
+  {
+    Self::Record(Rc::new(
+      iter
+        .into_iter()
+        .map(|(name, value)| (RecordKey::from(name), value))
+        .collect(),
+    ))
+  }
+
+  /// Whether this value can be used as a record key.
+  ///
+  /// Functions never equal anything and NaN never equals itself, so neither could ever be looked up again,
+  /// while records aren't allowed since their fields can be in any order.
+  pub fn is_hashable(&self) -> bool {
+    match self {
+      Self::Some(value) => value.is_hashable(),
+      Self::Tuple(tuple) => tuple.iter().all(Value::is_hashable),
+      Self::Number(num) => !num.is_nan(),
+      Self::Record(_) | Self::Function(_) | Self::NativeFunction(_) | Self::Closure(_) => false,
+      Self::Unit | Self::None | Self::Int(_) | Self::Boolean(_) | Self::String(_) => true,
+    }
+  }
+
+  /// The name of this value's type, as used in errors and by native properties.
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Self::Unit => "unit",
+      Self::None | Self::Some(_) => "option",
+      Self::Tuple(_) => "tuple",
+      Self::Record(_) => "record",
+      Self::Int(_) | Self::Number(_) => "number",
+      Self::Boolean(_) => "boolean",
+      Self::String(_) => "string",
+      Self::Function(_) | Self::NativeFunction(_) | Self::Closure(_) => "function",
+    }
+  }
+
+  /// An estimate of how many bytes this value takes up, including the contents of
+  /// any strings, tuples, and records it holds.
+  ///
+  /// Values shared between several places are counted once for each of them.
+  pub fn heap_size(&self) -> usize {
+    size_of::<Value>()
+      + match self {
+        Self::String(string) => string.len(),
+        Self::Some(value) => value.heap_size(),
+        Self::Tuple(tuple) => tuple.iter().map(Value::heap_size).sum(),
+        Self::Record(record) => record
+          .iter()
+          .map(|(key, value)| key.value().heap_size() + value.heap_size())
+          .sum(),
+        _ => 0,
+      }
+  }
+
+  /// Returns the plain form of this value (as `str` does), with numbers written in the given format.
+  pub fn to_string_with(&self, format: &NumberFormat) -> String {
+    match self {
+      Self::Unit => "()".to_string(),
+      Self::None => "none".to_string(),
+      Self::Some(value) => format!("some({})", value.display_with(format)),
+      Self::Tuple(tuple) => format!(
+        "#[{}]",
+        tuple
+          .iter()
+          .map(|v| v.display_with(format))
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      Self::Record(record) => format!(
+        "#{{{}}}",
+        record
+          .iter()
+          .map(|(k, v)| { format!("{}: {}", k.value().key_with(format), v.display_with(format)) })
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      Self::Int(num) => format.format_int(*num),
+      Self::Number(num) => format.format_float(*num),
+      Self::Boolean(bool) => format!("{}", bool),
+      Self::String(str) => str.clone(),
+      Self::Function(function) if !function.name.is_empty() => format!("<fn {}>", function.name),
+      Self::NativeFunction(native_fn) => format!("{:?}", native_fn),
+      Self::Closure(closure) if !closure.function.name.is_empty() => {
+        format!("<fn {}>", closure.function.name)
+      }
+      // only the top-level script is left without a name
+      Self::Function(_) | Self::Closure(_) => "<script>".to_string(),
+    }
+  }
+
+  /// Returns this value as it's displayed, with strings quoted and numbers written in the given format.
+  pub fn display_with(&self, format: &NumberFormat) -> String {
+    match self {
+      Self::String(string) => format!("\"{}\"", string),
+      value => value.to_string_with(format),
+    }
+  }
+
+  /// Returns this value as it's written as a record key: strings are quoted, and anything else is bracketed.
+  fn key_with(&self, format: &NumberFormat) -> String {
+    match self {
+      Self::String(_) => self.display_with(format),
+      key => format!("[{}]", key.display_with(format)),
+    }
+  }
+
+  /// Returns the form of this value as it would be written in a script,
+  /// with strings (including those nested in tuples and records) quoted and escaped.
+  pub fn repr(&self) -> String {
+    match self {
+      Self::String(string) => escape(string),
+      Self::Some(value) => format!("some({})", value.repr()),
+      Self::Tuple(tuple) => format!(
+        "#[{}]",
+        tuple
+          .iter()
+          .map(|v| v.repr())
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      Self::Record(record) => format!(
+        "#{{{}}}",
+        record
+          .iter()
+          .map(|(k, v)| match k.value() {
+            Self::String(k) => format!("{}: {}", escape(k), v.repr()),
+            k => format!("[{}]: {}", k.repr(), v.repr()),
+          })
+          .collect::<Vec<String>>()
+          .join(", ")
+      ),
+      _ => self.clone().try_into().unwrap(),
+    }
+  }
+
+  /// Returns this value as a float, if it's a number of either kind.
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Self::Int(num) => Some(*num as f64),
+      Self::Number(num) => Some(*num),
+      _ => None,
+    }
+  }
+
+  /// Whether this value is a number (of either kind) equal to zero.
+  pub fn is_zero(&self) -> bool {
+    self.as_f64() == Some(0.0)
+  }
+
+  /// Negates this value, if it's a number.
+  pub fn negate(&self) -> Option<Value> {
+    match self {
+      Self::Int(num) => Some(
+        num
+          .checked_neg()
+          .map_or(Self::Number(-(*num as f64)), Self::Int),
+      ),
+      Self::Number(num) => Some(Self::Number(-num)),
+      _ => None,
+    }
   }
 
   pub fn equal(&self, b: &Self) -> bool {
-    match (self, b) {
-      (Self::Number(a), Self::Number(b)) => (*a - *b).abs() < f64::EPSILON,
-      (Self::Boolean(a), Self::Boolean(b)) => *a == *b,
-      (Self::String(a), Self::String(b)) => *a == *b,
-      (Self::Tuple(a), Self::Tuple(b)) => {
-        if a.len() == b.len() {
-          for i in 0..a.len() {
-            if !a[i].equal(&b[i]) {
-              return false;
+    // nested tuples and records are compared using a worklist instead of recursion,
+    // so that comparing deeply nested values can't overflow the stack
+    let mut pending = vec![(self, b)];
+    while let Some((a, b)) = pending.pop() {
+      let equal = match (a, b) {
+        (Self::Unit, Self::Unit) | (Self::None, Self::None) => true,
+        (Self::Some(a), Self::Some(b)) => {
+          pending.push((a, b));
+          true
+        }
+        // IEEE comparison, so that 0 == -0 but NaN never equals anything (even itself)
+        (Self::Number(a), Self::Number(b)) => *a == *b,
+        (Self::Int(a), Self::Int(b)) => *a == *b,
+        (Self::Int(a), Self::Number(b)) | (Self::Number(b), Self::Int(a)) => *a as f64 == *b,
+        (Self::Boolean(a), Self::Boolean(b)) => *a == *b,
+        (Self::String(a), Self::String(b)) => *a == *b,
+        (Self::Tuple(a), Self::Tuple(b)) if a.len() == b.len() => {
+          pending.extend(a.iter().zip(b.iter()));
+          true
+        }
+        (Self::Record(a), Self::Record(b)) => {
+          a.len() == b.len()
+            && a.iter().all(|(key, a)| match b.get(key) {
+              Some(b) => {
+                pending.push((a, b));
+                true
+              }
+              None => false,
+            })
+        }
+        _ => false,
+      };
+
+      if !equal {
+        return false;
+      }
+    }
+    true
+  }
+
+  /// Describes where this value first differs from the `expected` one, if it does,
+  /// such as `value[2]: expected 3, got 4` or `value.x: missing from record`.
+  ///
+  /// Values are different exactly when [Value::equal] says they are.
+  pub fn diff(&self, expected: &Self) -> Option<String> {
+    // this is worked through in the same way as `equal`, keeping track of where each value is
+    let mut pending = vec![("value".to_string(), expected, self)];
+    while let Some((path, expected, actual)) = pending.pop() {
+      match (expected, actual) {
+        (Self::Tuple(expected), Self::Tuple(actual)) => {
+          if expected.len() != actual.len() {
+            return Some(format!(
+              "{}: expected a tuple of length {}, got one of length {}",
+              path,
+              expected.len(),
+              actual.len()
+            ));
+          }
+          // pushed in reverse, so that earlier elements are compared first
+          for (idx, (expected, actual)) in expected.iter().zip(actual.iter()).enumerate().rev() {
+            pending.push((format!("{}[{}]", path, idx), expected, actual));
+          }
+        }
+        (Self::Record(expected), Self::Record(actual)) => {
+          if let Some(key) = expected.keys().find(|key| !actual.contains_key(*key)) {
+            return Some(format!("{}: missing from record", field_path(&path, key)));
+          }
+          if let Some(key) = actual.keys().find(|key| !expected.contains_key(*key)) {
+            return Some(format!(
+              "{}: not expected in record",
+              field_path(&path, key)
+            ));
+          }
+          for (key, expected) in expected.iter().rev() {
+            if let Some(actual) = actual.get(key) {
+              pending.push((field_path(&path, key), expected, actual));
             }
           }
-          true
-        } else {
-          false
         }
+        (expected, actual) if !expected.equal(actual) => {
+          return Some(format!(
+            "{}: expected {}, got {}",
+            path,
+            expected.repr(),
+            actual.repr()
+          ));
+        }
+        _ => {}
       }
-      _ => false,
     }
+    None
   }
 }
 
@@ -87,13 +404,24 @@ impl TryInto<f64> for Value {
   type Error = RuntimeError;
 
   fn try_into(self) -> Result<f64, Self::Error> {
-    if let Value::Number(num) = self {
-      Ok(num)
-    } else {
-      Err(RuntimeError::TypeError {
-        expected: "number",
+    self.as_f64().ok_or(RuntimeError::TypeError {
+      expected: "number",
+      found: self,
+    })
+  }
+}
+impl TryInto<i64> for Value {
+  type Error = RuntimeError;
+
+  fn try_into(self) -> Result<i64, Self::Error> {
+    match self {
+      Value::Int(num) => Ok(num),
+      // floats are accepted as long as they're whole and in range
+      Value::Number(num) if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => Ok(num as i64),
+      _ => Err(RuntimeError::TypeError {
+        expected: "integer",
         found: self,
-      })
+      }),
     }
   }
 }
@@ -115,33 +443,7 @@ impl TryInto<String> for Value {
   type Error = RuntimeError;
 
   fn try_into(self) -> Result<String, Self::Error> {
-    match self {
-      Self::Tuple(tuple) => Ok(format!(
-        "#[{}]",
-        tuple
-          .iter()
-          .map(|v| format!("{}", v))
-          .collect::<Vec<String>>()
-          .join(", ")
-      )),
-      Self::Record(record) => Ok(format!(
-        "#{{{}}}",
-        record
-          .iter()
-          .map(|(k, v)| format!("\"{}\": {}", k, v))
-          .collect::<Vec<String>>()
-          .join(", ")
-      )),
-      Self::Number(num) => Ok(format!("{}", num)),
-      Self::Boolean(bool) => Ok(format!("{}", bool)),
-      Self::String(str) => Ok(str),
-      Self::Function(function) if function.name.is_empty() => Ok(format!("<fn {}>", function.name)),
-      Self::NativeFunction(native_fn) => Ok(format!("{:?}", native_fn)),
-      Self::Closure(closure) if closure.function.name.is_empty() => {
-        Ok(format!("<fn {}>", closure.function.name))
-      }
-      Self::Function(_) | Self::Closure(_) => Ok("<script>".to_string()),
-    }
+    Ok(self.to_string_with(&NumberFormat::default()))
   }
 }
 impl TryInto<Rc<Function>> for Value {
@@ -172,7 +474,286 @@ impl TryInto<Rc<RefCell<NativeFunction>>> for Value {
     }
   }
 }
+impl TryInto<HashMap<String, Value>> for Value {
+  type Error = RuntimeError;
 
+  fn try_into(self) -> Result<HashMap<String, Value>, Self::Error> {
+    if let Value::Record(record) = self {
+      record
+        .iter()
+        .map(|(key, value)| match key.value() {
+          Value::String(key) => Ok((key.clone(), value.clone())),
+          key => Err(RuntimeError::TypeError {
+            expected: "string",
+            found: key.clone(),
+          }),
+        })
+        .collect()
+    } else {
+      Err(RuntimeError::TypeError {
+        expected: "record",
+        found: self,
+      })
+    }
+  }
+}
+impl TryInto<Vec<Value>> for Value {
+  type Error = RuntimeError;
+
+  fn try_into(self) -> Result<Vec<Value>, Self::Error> {
+    if let Value::Tuple(tuple) = self {
+      Ok(tuple.to_vec())
+    } else {
+      Err(RuntimeError::TypeError {
+        expected: "tuple",
+        found: self,
+      })
+    }
+  }
+}
+
+impl From<HashMap<String, Value>> for Value {
+  fn from(map: HashMap<String, Value>) -> Self {
+    Value::record_from(map)
+  }
+}
+impl From<Vec<Value>> for Value {
+  fn from(vec: Vec<Value>) -> Self {
+    Value::Tuple(vec.into())
+  }
+}
+
+// follows the language's `==`, so this is only a partial equivalence: NaN never equals itself
+impl PartialEq for Value {
+  fn eq(&self, other: &Self) -> bool {
+    self.equal(other)
+  }
+}
+
+/// A value used as a record key, which can only be made from a value that [Value::is_hashable].
+///
+/// Keys are equal when their values are exactly the same, so unlike `==`, an int only equals
+/// a number if the number is whole and converts to that exact int (even above 2^53).
+#[derive(Debug, Clone)]
+pub struct RecordKey(Value);
+impl RecordKey {
+  pub fn value(&self) -> &Value {
+    &self.0
+  }
+
+  pub fn into_value(self) -> Value {
+    self.0
+  }
+
+  fn key_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+      (Value::Unit, Value::Unit) | (Value::None, Value::None) => true,
+      (Value::Some(a), Value::Some(b)) => Self::key_eq(a, b),
+      (Value::Tuple(a), Value::Tuple(b)) => {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| Self::key_eq(a, b))
+      }
+      (Value::Int(a), Value::Int(b)) => a == b,
+      (Value::Number(a), Value::Number(b)) => a == b,
+      (Value::Int(a), Value::Number(b)) | (Value::Number(b), Value::Int(a)) => {
+        whole_int(*b) == Some(*a)
+      }
+      (Value::Boolean(a), Value::Boolean(b)) => a == b,
+      (Value::String(a), Value::String(b)) => a == b,
+      _ => false,
+    }
+  }
+
+  fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+      Value::Unit => 0u8.hash(state),
+      Value::None => 1u8.hash(state),
+      Value::Some(value) => {
+        2u8.hash(state);
+        Self::hash_value(value, state);
+      }
+      Value::Tuple(tuple) => {
+        3u8.hash(state);
+        tuple.len().hash(state);
+        for value in tuple.iter() {
+          Self::hash_value(value, state);
+        }
+      }
+      Value::Int(num) => {
+        4u8.hash(state);
+        num.hash(state);
+      }
+      // whole numbers hash the same as the int they equal, and `-0.0` the same as `0`
+      Value::Number(num) => match whole_int(*num) {
+        Some(num) => {
+          4u8.hash(state);
+          num.hash(state);
+        }
+        None => {
+          5u8.hash(state);
+          num.to_bits().hash(state);
+        }
+      },
+      Value::Boolean(bool) => {
+        6u8.hash(state);
+        bool.hash(state);
+      }
+      Value::String(str) => {
+        7u8.hash(state);
+        str.hash(state);
+      }
+      // none of these can be made into a key
+      Value::Record(_) | Value::Function(_) | Value::NativeFunction(_) | Value::Closure(_) => {}
+    }
+  }
+}
+impl TryFrom<Value> for RecordKey {
+  type Error = RuntimeError;
+
+  fn try_from(value: Value) -> Result<Self, Self::Error> {
+    if value.is_hashable() {
+      Ok(Self(value))
+    } else {
+      Err(RuntimeError::UnhashableKey(value))
+    }
+  }
+}
+impl From<String> for RecordKey {
+  fn from(name: String) -> Self {
+    Self(Value::String(name))
+  }
+}
+impl From<&str> for RecordKey {
+  fn from(name: &str) -> Self {
+    Self(Value::String(name.to_string()))
+  }
+}
+impl PartialEq for RecordKey {
+  fn eq(&self, other: &Self) -> bool {
+    Self::key_eq(&self.0, &other.0)
+  }
+}
+// keys never hold NaN, and ints and numbers are compared exactly, so this is a full equivalence
+impl Eq for RecordKey {}
+/// Hashing is deterministic, as it only depends on the value.
+impl Hash for RecordKey {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Self::hash_value(&self.0, state);
+  }
+}
+
+/// Returns the int a float is exactly equal to, if it's whole and in range of one.
+fn whole_int(num: f64) -> Option<i64> {
+  // 2^63 is exactly representable, unlike `i64::MAX`
+  const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+  if num.fract() == 0.0 && (-LIMIT..LIMIT).contains(&num) {
+    Some(num as i64)
+  } else {
+    None
+  }
+}
+
+/// Tuples serialize as sequences and records as maps (in field order), and optional values as
+/// options, while functions can't be serialized at all.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::{Error, SerializeMap, SerializeSeq};
+
+    match self {
+      Self::Unit => serializer.serialize_unit(),
+      Self::None => serializer.serialize_none(),
+      Self::Some(value) => serializer.serialize_some(&**value),
+      Self::Tuple(tuple) => {
+        let mut seq = serializer.serialize_seq(Some(tuple.len()))?;
+        for value in tuple.iter() {
+          seq.serialize_element(value)?;
+        }
+        seq.end()
+      }
+      Self::Record(record) => {
+        let mut map = serializer.serialize_map(Some(record.len()))?;
+        for (key, value) in record.iter() {
+          map.serialize_entry(key.value(), value)?;
+        }
+        map.end()
+      }
+      Self::Int(num) => serializer.serialize_i64(*num),
+      Self::Number(num) => serializer.serialize_f64(*num),
+      Self::Boolean(bool) => serializer.serialize_bool(*bool),
+      Self::String(str) => serializer.serialize_str(str),
+      Self::Function(_) | Self::NativeFunction(_) | Self::Closure(_) => {
+        Err(S::Error::custom("functions cannot be serialized"))
+      }
+    }
+  }
+}
+
+/// Sequences deserialize as tuples and maps as records, while integers that don't fit in an `Int`
+/// become (possibly rounded) numbers.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::{MapAccess, SeqAccess, Visitor};
+
+    struct ValueVisitor;
+    impl<'de> Visitor<'de> for ValueVisitor {
+      type Value = Value;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a BobaScript value")
+      }
+
+      fn visit_unit<E: serde::de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+      }
+      fn visit_none<E: serde::de::Error>(self) -> Result<Value, E> {
+        Ok(Value::None)
+      }
+      fn visit_some<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let value = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Value::Some(Box::new(value)))
+      }
+      fn visit_bool<E: serde::de::Error>(self, bool: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(bool))
+      }
+      fn visit_i64<E: serde::de::Error>(self, num: i64) -> Result<Value, E> {
+        Ok(Value::Int(num))
+      }
+      fn visit_u64<E: serde::de::Error>(self, num: u64) -> Result<Value, E> {
+        Ok(i64::try_from(num).map_or(Value::Number(num as f64), Value::Int))
+      }
+      fn visit_f64<E: serde::de::Error>(self, num: f64) -> Result<Value, E> {
+        Ok(Value::Number(num))
+      }
+      fn visit_str<E: serde::de::Error>(self, str: &str) -> Result<Value, E> {
+        Ok(Value::String(str.to_string()))
+      }
+      fn visit_string<E: serde::de::Error>(self, str: String) -> Result<Value, E> {
+        Ok(Value::String(str))
+      }
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut tuple = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+          tuple.push(value);
+        }
+        Ok(tuple.into())
+      }
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut record = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<Value, Value>()? {
+          let key = RecordKey::try_from(key)
+            .map_err(|error| serde::de::Error::custom(error.to_string()))?;
+          record.insert(key, value);
+        }
+        Ok(Value::Record(Rc::new(record)))
+      }
+    }
+
+    deserializer.deserialize_any(ValueVisitor)
+  }
+}
+
+// this always writes numbers in the default format, since it has no VM to take one from (see `VM::display`)
 impl fmt::Display for Value {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     let value: String = Value::try_into(self.clone()).unwrap();
@@ -183,3 +764,146 @@ impl fmt::Display for Value {
     }
   }
 }
+
+/// How numbers are written when values are converted into strings, such as by `log` and `str`.
+///
+/// The default writes numbers plainly, with a `.` as the decimal separator and no grouping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+  /// Written between the whole and fractional parts of a float.
+  pub decimal_separator: char,
+  /// If set, written between every group of three digits in the whole part of a number.
+  pub thousands_separator: Option<char>,
+  /// If set, the number of digits floats are written with after their decimal separator.
+  pub precision: Option<usize>,
+}
+impl Default for NumberFormat {
+  fn default() -> Self {
+    Self {
+      decimal_separator: '.',
+      thousands_separator: None,
+      precision: None,
+    }
+  }
+}
+impl NumberFormat {
+  pub fn format_int(&self, num: i64) -> String {
+    self.group(&num.to_string())
+  }
+
+  pub fn format_float(&self, num: f64) -> String {
+    let plain = match self.precision {
+      Some(precision) => format!("{:.*}", precision, num),
+      None => format!("{}", num),
+    };
+    // infinities and NaN have no digits to separate
+    if !num.is_finite() {
+      return plain;
+    }
+    match plain.split_once('.') {
+      Some((whole, fraction)) => format!(
+        "{}{}{}",
+        self.group(whole),
+        self.decimal_separator,
+        fraction
+      ),
+      None => self.group(&plain),
+    }
+  }
+
+  /// Inserts the thousands separator (if there is one) into a whole number, which may be negative.
+  fn group(&self, whole: &str) -> String {
+    let separator = match self.thousands_separator {
+      Some(separator) => separator,
+      None => return whole.to_string(),
+    };
+    let (sign, digits) = match whole.strip_prefix('-') {
+      Some(digits) => ("-", digits),
+      None => ("", whole),
+    };
+    let mut grouped = String::from(sign);
+    for (idx, digit) in digits.chars().enumerate() {
+      if idx > 0 && (digits.len() - idx) % 3 == 0 {
+        grouped.push(separator);
+      }
+      grouped.push(digit);
+    }
+    grouped
+  }
+}
+
+/// A binary arithmetic operation, which works on integers when both operands are integers
+/// (falling back to floats if the result would overflow) and on floats otherwise.
+#[derive(Debug, Clone, Copy)]
+pub enum Arithmetic {
+  Add,
+  Subtract,
+  Multiply,
+  /// Integer division truncates towards zero, as in Rust.
+  Divide,
+  Modulo,
+  Exponent,
+}
+impl Arithmetic {
+  /// Applies this operation, or returns `None` if either operand isn't a number.
+  ///
+  /// Dividing by zero isn't checked for here, as it's up to the caller what to do about it.
+  pub fn apply(self, a: &Value, b: &Value) -> Option<Value> {
+    if let (Value::Int(a), Value::Int(b)) = (a, b) {
+      if let Some(result) = self.apply_int(*a, *b) {
+        return Some(Value::Int(result));
+      }
+    }
+    Some(Value::Number(self.apply_float(a.as_f64()?, b.as_f64()?)))
+  }
+
+  fn apply_int(self, a: i64, b: i64) -> Option<i64> {
+    match self {
+      Arithmetic::Add => a.checked_add(b),
+      Arithmetic::Subtract => a.checked_sub(b),
+      Arithmetic::Multiply => a.checked_mul(b),
+      Arithmetic::Divide => a.checked_div(b),
+      Arithmetic::Modulo => a.checked_rem(b),
+      // negative exponents give fractions, so they're left to floats
+      Arithmetic::Exponent => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+    }
+  }
+
+  fn apply_float(self, a: f64, b: f64) -> f64 {
+    match self {
+      Arithmetic::Add => a + b,
+      Arithmetic::Subtract => a - b,
+      Arithmetic::Multiply => a * b,
+      Arithmetic::Divide => a / b,
+      Arithmetic::Modulo => a % b,
+      Arithmetic::Exponent => a.powf(b),
+    }
+  }
+}
+
+/// Quotes a string, escaping any characters that can't appear as-is within a string literal.
+fn escape(string: &str) -> String {
+  let mut escaped = String::with_capacity(string.len() + 2);
+  escaped.push('"');
+  for c in string.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\t' => escaped.push_str("\\t"),
+      '\r' => escaped.push_str("\\r"),
+      c if c.is_control() => escaped.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped.push('"');
+  escaped
+}
+
+/// Describes where a record's field is, as `path.name` for string keys or `path[key]` for any other key.
+fn field_path(path: &str, key: &RecordKey) -> String {
+  match key.value() {
+    Value::String(name) => format!("{}.{}", path, name),
+    key => format!("{}[{}]", path, key.repr()),
+  }
+}
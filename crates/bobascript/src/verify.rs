@@ -0,0 +1,178 @@
+use thiserror::Error;
+
+use crate::{
+  chunk::{Chunk, JumpDirection, OpCode, Upvalue},
+  value::Value,
+};
+
+#[derive(Error, Debug, Clone)]
+pub enum VerifyError {
+  #[error("The jump at instruction {0} leads outside of its chunk.")]
+  JumpOutOfBounds(usize),
+  #[error("Instruction {0} refers to a constant that doesn't exist or has the wrong type.")]
+  InvalidConstant(usize),
+  #[error("Instruction {0} refers to a local slot that isn't on the stack.")]
+  InvalidLocal(usize),
+  #[error("Instruction {0} refers to an upvalue that hasn't been captured.")]
+  InvalidUpvalue(usize),
+  #[error("Instruction {0} takes more values off the stack than there are.")]
+  StackUnderflow(usize),
+  #[error("Instruction {0} can be reached with differing numbers of values on the stack.")]
+  InconsistentStack(usize),
+  #[error("The end of the chunk can be reached without returning.")]
+  MissingReturn,
+}
+
+/// Statically checks that a script's chunk, along with the chunks of every function within it,
+/// only jumps within itself, only uses constants, locals, and upvalues that exist,
+/// and never takes more values off the stack than it has put on, along every path through it.
+pub fn verify(chunk: &Chunk) -> Result<(), VerifyError> {
+  verify_chunk(chunk, 0, 0)
+}
+
+/// Verifies the chunk of a function taking `arity` parameters and capturing `upvalues` variables.
+fn verify_chunk(chunk: &Chunk, arity: u8, upvalues: usize) -> Result<(), VerifyError> {
+  // the number of values on the stack before each instruction, once it's been reached
+  let mut depths: Vec<Option<usize>> = vec![None; chunk.code.len()];
+  // the function itself and its parameters are already on the stack when it starts
+  let mut pending = vec![(0, 1 + arity as usize)];
+
+  while let Some((ip, depth)) = pending.pop() {
+    let (opcode, _) = chunk.code.get(ip).ok_or(VerifyError::MissingReturn)?;
+    match depths[ip] {
+      Some(seen) if seen == depth => continue,
+      Some(_) => return Err(VerifyError::InconsistentStack(ip)),
+      None => depths[ip] = Some(depth),
+    }
+
+    // slot 0 holds the function itself, which is only ever removed by returning
+    if depth < inputs(opcode) + 1 {
+      return Err(VerifyError::StackUnderflow(ip));
+    }
+    check_operands(chunk, opcode, ip, depth, upvalues)?;
+    let next_depth = (depth as isize + opcode.stack_effect()) as usize;
+
+    match opcode {
+      OpCode::Return | OpCode::ReturnUnit | OpCode::NoMatch => {}
+      OpCode::Jump(direction, distance) => {
+        pending.push((jump_target(chunk, ip, *direction, *distance)?, next_depth))
+      }
+      OpCode::JumpIfFalse(distance) => {
+        let target = jump_target(chunk, ip, JumpDirection::Forwards, *distance)?;
+        pending.push((target, next_depth));
+        pending.push((ip + 1, next_depth));
+      }
+      OpCode::Try(distance) => {
+        // errors are caught with a record describing them in place of anything pushed since
+        let target = jump_target(chunk, ip, JumpDirection::Forwards, *distance)?;
+        pending.push((target, depth + 1));
+        pending.push((ip + 1, next_depth));
+      }
+      _ => pending.push((ip + 1, next_depth)),
+    }
+  }
+
+  Ok(())
+}
+
+/// The number of values the given instruction needs on the stack in order to run.
+fn inputs(opcode: &OpCode) -> usize {
+  match opcode {
+    OpCode::Tuple(len) => *len as usize,
+    OpCode::Record(len) => 2 * *len as usize,
+    OpCode::PopN(count) => *count,
+    OpCode::Call(args) => *args as usize + 1,
+    OpCode::Log(count) => *count as usize,
+    OpCode::Equal
+    | OpCode::GreaterThan
+    | OpCode::LessThan
+    | OpCode::Add
+    | OpCode::Subtract
+    | OpCode::Multiply
+    | OpCode::Divide
+    | OpCode::Modulo
+    | OpCode::Exponent
+    | OpCode::Index
+    | OpCode::Append
+    | OpCode::Extend
+    | OpCode::SetProperty(_) => 2,
+    OpCode::SetIndex => 3,
+    OpCode::Pop
+    | OpCode::PopResult
+    | OpCode::DefineGlobal(_)
+    | OpCode::SetLocal(_)
+    | OpCode::SetGlobal(_)
+    | OpCode::SetUpvalue(_)
+    | OpCode::GetProperty(_)
+    | OpCode::Not
+    | OpCode::Negate
+    | OpCode::JumpIfFalse(_)
+    | OpCode::MatchTuple(_)
+    | OpCode::Some
+    | OpCode::MatchSome
+    | OpCode::IsUnit
+    | OpCode::Unwrap
+    | OpCode::HasProperty(_)
+    | OpCode::RecordLen
+    | OpCode::CloseUpvalue
+    | OpCode::Return => 1,
+    _ => 0,
+  }
+}
+
+/// Checks that the constants, locals, and upvalues an instruction refers to exist.
+fn check_operands(
+  chunk: &Chunk,
+  opcode: &OpCode,
+  ip: usize,
+  depth: usize,
+  upvalues: usize,
+) -> Result<(), VerifyError> {
+  match opcode {
+    OpCode::Constant(idx) if *idx >= chunk.constants.len() => Err(VerifyError::InvalidConstant(ip)),
+    OpCode::DefineGlobal(idx) | OpCode::GetGlobal(idx) | OpCode::SetGlobal(idx) => {
+      match chunk.constants.get(*idx) {
+        Some(Value::String(_)) => Ok(()),
+        _ => Err(VerifyError::InvalidConstant(ip)),
+      }
+    }
+    OpCode::GetLocal(slot) | OpCode::SetLocal(slot) if *slot >= depth => {
+      Err(VerifyError::InvalidLocal(ip))
+    }
+    OpCode::GetUpvalue(idx) | OpCode::SetUpvalue(idx) if *idx >= upvalues => {
+      Err(VerifyError::InvalidUpvalue(ip))
+    }
+    OpCode::Closure(idx, captures) => {
+      let function = match chunk.constants.get(*idx) {
+        Some(Value::Function(function)) => function,
+        _ => return Err(VerifyError::InvalidConstant(ip)),
+      };
+      for capture in captures {
+        match capture {
+          Upvalue::Local(slot) if *slot >= depth => return Err(VerifyError::InvalidLocal(ip)),
+          Upvalue::Upvalue(idx) if *idx >= upvalues => return Err(VerifyError::InvalidUpvalue(ip)),
+          _ => {}
+        }
+      }
+      verify_chunk(&function.chunk, function.arity, captures.len())
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Finds the instruction a jump lands on, making sure it's within the chunk.
+fn jump_target(
+  chunk: &Chunk,
+  ip: usize,
+  direction: JumpDirection,
+  distance: usize,
+) -> Result<usize, VerifyError> {
+  // jumps are taken once the instruction pointer has already moved past them
+  let target = match direction {
+    JumpDirection::Forwards => (ip + 1).checked_add(distance),
+    JumpDirection::Backwards => (ip + 1).checked_sub(distance),
+  };
+  target
+    .filter(|target| *target < chunk.code.len())
+    .ok_or(VerifyError::JumpOutOfBounds(ip))
+}
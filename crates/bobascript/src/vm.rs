@@ -1,12 +1,26 @@
-use std::{cell::RefCell, collections::HashMap, convert::TryInto, iter::repeat, rc::Rc};
+use std::{
+  cell::RefCell,
+  cmp::Ordering,
+  collections::{HashMap, VecDeque},
+  convert::{TryFrom, TryInto},
+  rc::Rc,
+  time::Instant,
+};
+
+use indexmap::IndexMap;
 
 use thiserror::Error;
 
 use crate::{
   chunk::{JumpDirection, OpCode},
   debug::disassemble_instruction,
-  value::{Closure, Function, NativeFunction, Upvalue, Value},
-  InterpretResult,
+  stdlib::{self, PropertyTable, StdlibRegistry},
+  value::{
+    Arithmetic, Closure, Function, NativeFunction, NativeProperty, NumberFormat, RecordKey,
+    Upvalue, Value,
+  },
+  verify::verify,
+  InterpretError, InterpretResult,
 };
 
 macro_rules! binary_op {
@@ -39,41 +53,422 @@ pub enum RuntimeError {
   StackOverflow,
   #[error(r#"Could not index value "{0}" by value "{1}"."#)]
   InvalidIndex(String, String),
-  #[error("Only records have properties.")]
-  NoProperties,
-  #[error(r#"Properties on records are immutable and cannot be changed directly. You may want to use "with" syntax here."#)]
-  ImmutableSet,
+  #[error("Undefined property \"{0}\".")]
+  UndefinedProperty(String),
+  #[error("Index {index} is out of bounds for a value of length {len}.")]
+  IndexOutOfBounds { index: isize, len: usize },
+  #[error("The bytecode being run is corrupt.")]
+  CorruptBytecode,
+  #[error("No arm of the match expression matched its value.")]
+  NonExhaustiveMatch,
+  #[error("Cannot divide by zero.")]
+  DivideByZero,
+  #[error("The script has run out of memory.")]
+  OutOfMemory,
+  #[error("The value {0} cannot be used as a record key.")]
+  UnhashableKey(Value),
+  #[error("The script has run too many instructions.")]
+  InstructionLimitExceeded,
+  #[error(r#"Could not parse "{0}" as a number."#)]
+  ParseError(String),
 }
+impl RuntimeError {
+  /// A stable name for this kind of error, which scripts can branch on.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      RuntimeError::Unknown => "Unknown",
+      RuntimeError::TypeError { .. } => "TypeError",
+      RuntimeError::OperationNotSupported => "OperationNotSupported",
+      RuntimeError::UndefinedVariable(_) => "UndefinedVariable",
+      RuntimeError::InvalidCallSignature => "InvalidCallSignature",
+      RuntimeError::IncorrectParameterCount(_, _) => "IncorrectParameterCount",
+      RuntimeError::StackOverflow => "StackOverflow",
+      RuntimeError::InvalidIndex(_, _) => "InvalidIndex",
+      RuntimeError::UndefinedProperty(_) => "UndefinedProperty",
+      RuntimeError::IndexOutOfBounds { .. } => "IndexOutOfBounds",
+      RuntimeError::CorruptBytecode => "CorruptBytecode",
+      RuntimeError::NonExhaustiveMatch => "NonExhaustiveMatch",
+      RuntimeError::DivideByZero => "DivisionByZero",
+      RuntimeError::OutOfMemory => "OutOfMemory",
+      RuntimeError::UnhashableKey(_) => "UnhashableKey",
+      RuntimeError::InstructionLimitExceeded => "InstructionLimitExceeded",
+      RuntimeError::ParseError(_) => "ParseError",
+    }
+  }
 
-struct CallFrame {
+  /// Whether a `try` block can catch this error, which it can't for the limits set by the host.
+  pub fn is_catchable(&self) -> bool {
+    !matches!(
+      self,
+      RuntimeError::OutOfMemory | RuntimeError::InstructionLimitExceeded
+    )
+  }
+
+  /// Describes this error to scripts as a record of its `kind`, `message`, and the `line` it occurred on.
+  pub fn to_value(&self, line: usize) -> Value {
+    Value::record_from(vec![
+      ("kind".to_string(), Value::String(self.kind().to_string())),
+      ("message".to_string(), Value::String(self.to_string())),
+      ("line".to_string(), Value::Int(line as i64)),
+    ])
+  }
+}
+
+/// Turns an index into one within a tuple or string of the given length,
+/// where negative indices count back from the end (so `-1` is the last element).
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+  let index = if index < 0 {
+    index.checked_add(len as i64)?
+  } else {
+    index
+  };
+  if index >= 0 && index < len as i64 {
+    Some(index as usize)
+  } else {
+    None
+  }
+}
+
+pub struct CallFrame {
   closure: Closure,
   ip: usize,
   slots_start: usize,
 }
 
+/// Where to go when an error is raised within a `try` block.
+struct Handler {
+  /// The number of frames there were when the `try` block started, its own being the last.
+  frame_count: usize,
+  /// The size of the stack when the `try` block started, which it's unwound back to.
+  stack_len: usize,
+  /// The instruction its catch block starts at.
+  catch_ip: usize,
+}
+impl CallFrame {
+  /// The function being run in this frame.
+  pub fn function(&self) -> &Function {
+    &self.closure.function
+  }
+
+  /// The index of the next instruction to be run in this frame.
+  pub fn ip(&self) -> usize {
+    self.ip
+  }
+}
+
+/// A read-only view of the VM's state at the moment a runtime error was raised.
+pub struct VmState<'a> {
+  frames: &'a [CallFrame],
+  stack: &'a [Value],
+  globals: &'a HashMap<String, Value>,
+}
+impl<'a> VmState<'a> {
+  /// The call frames, from the top-level script to the innermost function.
+  pub fn frames(&self) -> &'a [CallFrame] {
+    self.frames
+  }
+
+  pub fn stack(&self) -> &'a [Value] {
+    self.stack
+  }
+
+  pub fn globals(&self) -> &'a HashMap<String, Value> {
+    self.globals
+  }
+}
+
+/// A function to call, along with the arguments to call it with.
+type ScheduledCall = (Value, Vec<Value>);
+
+/// A handle for scheduling calls to script (or native) functions from places
+/// that can't call into the VM themselves, such as log handlers and natives.
+///
+/// The VM is never re-entered while a handler is running. Instead, scheduled calls
+/// are queued up and run, in order, right after the instruction that ran the handler
+/// finishes, and before the script continues. Calls scheduled by a scheduled call
+/// are run the same way, once it has finished. Their results are discarded, and any
+/// error they raise stops the script as if it was raised by the script itself.
+#[derive(Clone, Default)]
+pub struct CallbackQueue(Rc<RefCell<VecDeque<ScheduledCall>>>);
+impl CallbackQueue {
+  pub fn schedule(&self, function: Value, args: Vec<Value>) {
+    self.0.borrow_mut().push_back((function, args));
+  }
+
+  fn next(&self) -> Option<ScheduledCall> {
+    self.0.borrow_mut().pop_front()
+  }
+}
+
+/// A hook called with any runtime error raised, along with the VM's state at that moment.
+pub type ErrorHook = Box<dyn FnMut(&RuntimeError, &VmState)>;
+
 pub struct VM {
   log_handler: Option<Box<dyn FnMut(Value)>>,
+  error_hook: Option<ErrorHook>,
+  callbacks: CallbackQueue,
   frames: Vec<CallFrame>,
   stack: Vec<Value>,
+  /// The `try` blocks that are currently running, innermost last.
+  handlers: Vec<Handler>,
   globals: HashMap<String, Value>,
+  properties: PropertyTable,
   upvalues: Vec<Rc<RefCell<Upvalue>>>,
+  /// The values of each top-level expression statement, while they're being collected.
+  results: Option<Vec<Value>>,
+  /// Whether bytecode is verified before it's interpreted.
+  verify: bool,
+  /// The most memory, in bytes, that the stack and globals may take up, if limited.
+  max_heap: Option<usize>,
+  /// The heap estimate as of the last time it was worked out, plus everything allocated since,
+  /// or `None` if it needs working out again (see `reserve`).
+  heap_used: Option<usize>,
+  /// The most call frames (including the script's own) that may be active at once.
+  max_call_depth: usize,
+  /// The most instructions each call to `interpret` or `call_function` may run, if limited.
+  instruction_limit: Option<u64>,
+  /// How many more instructions may be run before the limit is reached.
+  fuel: Option<u64>,
+  /// How numbers are written when they're logged.
+  number_format: NumberFormat,
+  /// When the VM was created, which `clock` counts from.
+  created: Instant,
 }
 impl Default for VM {
   fn default() -> Self {
+    Self::new()
+  }
+}
+impl VM {
+  /// Creates a VM whose globals are only the prelude's natives (like `len`),
+  /// with no log handler (so logs are printed to stdout), no error hook, and verification off.
+  pub fn new() -> Self {
+    let mut vm = Self::without_natives();
+    vm.register_builtins();
+    vm
+  }
+
+  /// Creates a VM like [VM::new], but without any natives defined.
+  fn without_natives() -> Self {
+    let mut properties = PropertyTable::new();
+    stdlib::define_prelude_properties(&mut properties);
+
     Self {
       log_handler: None,
+      error_hook: None,
+      callbacks: CallbackQueue::default(),
       frames: Vec::with_capacity(64),
       stack: Vec::with_capacity(256),
+      handlers: Vec::new(),
       globals: HashMap::new(),
+      properties,
       upvalues: Vec::new(),
+      results: None,
+      verify: false,
+      max_heap: None,
+      heap_used: None,
+      max_call_depth: 64,
+      instruction_limit: None,
+      fuel: None,
+      number_format: NumberFormat::default(),
+      created: Instant::now(),
     }
   }
-}
-impl VM {
+
+  /// Defines the natives every VM starts out with (like `len`),
+  /// replacing any globals that have since been given the same names.
+  pub fn register_builtins(&mut self) {
+    stdlib::define_natives(&mut self.globals, stdlib::prelude_natives());
+  }
+
+  /// Defines the natives of the standard library (like `type`, `clock`, which counts the seconds
+  /// since the VM was created, and `map`, `filter`, and `reduce` over tuples), which aren't defined by default.
+  pub fn register_std(&mut self) {
+    stdlib::define_natives(&mut self.globals, stdlib::std_natives());
+  }
+
+  /// Defines the natives of the math library (`sqrt`, `abs`, `floor`, `ceil`, `round`, `pow`, `min`, and `max`),
+  /// which aren't defined by default.
+  pub fn register_math(&mut self) {
+    stdlib::define_natives(&mut self.globals, stdlib::math_natives());
+  }
+
+  /// Defines the natives of the string library (`upper`, `lower`, `trim`, `contains`, and `split`),
+  /// which aren't defined by default.
+  pub fn register_string_lib(&mut self) {
+    stdlib::define_natives(&mut self.globals, stdlib::string_natives());
+  }
+
+  /// Creates a new VM whose natives are those of the given registry (see [StdlibRegistry::with_std]),
+  /// in place of the prelude's. They're shared with every other VM created from the same registry,
+  /// rather than each VM creating its own.
+  pub fn with_shared_stdlib(registry: Rc<StdlibRegistry>) -> Self {
+    let mut vm = Self::without_natives();
+    for (name, function) in registry.natives() {
+      vm.globals
+        .insert(name.clone(), Value::NativeFunction(Rc::clone(function)));
+    }
+    for (type_name, name, property) in registry.properties() {
+      vm.define_property(type_name, name.clone(), *property);
+    }
+    vm
+  }
+
   pub fn add_log_handler(&mut self, handler: Box<dyn FnMut(Value)>) {
     self.log_handler = Some(handler);
   }
 
+  /// Sets a hook that is called whenever a runtime error is raised,
+  /// before the VM's stack and frames are cleared.
+  pub fn add_error_hook(&mut self, hook: ErrorHook) {
+    self.error_hook = Some(hook);
+  }
+
+  /// Sets whether each function's bytecode is verified before it's interpreted.
+  pub fn set_verify(&mut self, verify: bool) {
+    self.verify = verify;
+  }
+
+  /// Limits the estimated memory taken up by the stack and globals to the given number of bytes.
+  ///
+  /// Creating a string, tuple, or record that would go over this limit raises an `OutOfMemory` error.
+  pub fn set_max_heap(&mut self, max_heap: usize) {
+    self.max_heap = Some(max_heap);
+    self.heap_used = None;
+  }
+
+  /// Limits how deeply functions may call each other (64 by default), counting the script itself as one call.
+  ///
+  /// Calling a function while already this deep raises a `StackOverflow` error.
+  pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+    self.max_call_depth = max_call_depth;
+  }
+
+  /// Limits how many instructions each call to `interpret` or `call_function` may run,
+  /// or lifts the limit with `None` (the default).
+  ///
+  /// Running any more raises an `InstructionLimitExceeded` error, so that scripts can't run forever.
+  pub fn set_instruction_limit(&mut self, limit: Option<u64>) {
+    self.instruction_limit = limit;
+  }
+
+  /// Sets how numbers are written when they're logged or converted to strings with `str` or `to_string`.
+  ///
+  /// This has no effect on arithmetic or on how numbers are parsed.
+  pub fn set_number_format(&mut self, format: NumberFormat) {
+    self.number_format = format;
+  }
+
+  pub fn number_format(&self) -> &NumberFormat {
+    &self.number_format
+  }
+
+  /// Returns when this VM was created.
+  pub fn created(&self) -> Instant {
+    self.created
+  }
+
+  /// Returns a value as it's logged, with numbers written in this VM's format
+  /// (unlike its `Display` form, which always uses the default format).
+  pub fn display(&self, value: &Value) -> String {
+    value.display_with(&self.number_format)
+  }
+
+  /// Estimates how many bytes the values on the stack and in globals take up.
+  pub fn heap_estimate(&self) -> usize {
+    let stack: usize = self.stack.iter().map(Value::heap_size).sum();
+    let globals: usize = self
+      .globals
+      .iter()
+      .map(|(name, value)| name.len() + value.heap_size())
+      .sum();
+    stack + globals
+  }
+
+  /// Returns a handle that can be used to schedule calls from within handlers and natives.
+  pub fn callback_queue(&self) -> CallbackQueue {
+    self.callbacks.clone()
+  }
+
+  /// Calls the given function with the given arguments, running it to completion.
+  pub fn call_function(&mut self, function: Value, args: Vec<Value>) -> InterpretResult<Value> {
+    self.fuel = self.instruction_limit;
+    self.heap_used = None;
+    let (frame_count, stack_len) = (self.frames.len(), self.stack.len());
+    self.invoke(function, args).map_err(|error| {
+      let error = self.locate(error);
+      self.unwind(frame_count, stack_len);
+      error
+    })
+  }
+
+  /// Abandons every frame and value above the given depths, such as after a call fails part way through,
+  /// closing any upvalues that still point into the part of the stack that's thrown away.
+  fn unwind(&mut self, frame_count: usize, stack_len: usize) {
+    self.close_upvalues(stack_len);
+    self.frames.truncate(frame_count);
+    self.stack.truncate(stack_len);
+    self
+      .handlers
+      .retain(|handler| handler.frame_count <= frame_count && handler.stack_len <= stack_len);
+  }
+
+  pub(crate) fn invoke(
+    &mut self,
+    function: Value,
+    args: Vec<Value>,
+  ) -> Result<Value, RuntimeError> {
+    let arg_count: u8 = args
+      .len()
+      .try_into()
+      .map_err(|_| RuntimeError::IncorrectParameterCount(u8::MAX, u8::MAX))?;
+    let depth = self.frames.len();
+
+    self.push(function.clone());
+    for arg in args {
+      self.push(arg);
+    }
+    self.call_value(function, arg_count)?;
+
+    if self.frames.len() > depth {
+      self.run_until(depth)
+    } else {
+      // natives have already left their result on the stack
+      Ok(self.pop().unwrap())
+    }
+  }
+
+  /// Runs every call that has been scheduled through the [CallbackQueue].
+  fn run_callbacks(&mut self) -> Result<(), RuntimeError> {
+    while let Some((function, args)) = self.callbacks.next() {
+      self.invoke(function, args)?;
+    }
+    Ok(())
+  }
+
+  /// Defines a read-only property on every value whose [Value::type_name] is `type_name`,
+  /// which is used whenever a record doesn't have a field of the same name.
+  pub fn define_property(
+    &mut self,
+    type_name: &'static str,
+    name: String,
+    property: NativeProperty,
+  ) {
+    self
+      .properties
+      .entry(type_name)
+      .or_default()
+      .insert(name, property);
+  }
+
+  fn native_property(&self, value: &Value, name: &str) -> Option<NativeProperty> {
+    self
+      .properties
+      .get(value.type_name())
+      .and_then(|properties| properties.get(name))
+      .copied()
+  }
+
   pub fn define_native(&mut self, name: String, function: Rc<RefCell<NativeFunction>>) {
     self.push(Value::String(name));
     self.push(Value::NativeFunction(function));
@@ -85,21 +480,103 @@ impl VM {
   }
 
   pub fn interpret(&mut self, function: Rc<Function>) -> InterpretResult<Value> {
-    self.push(Value::Function(function.clone()));
+    if self.verify {
+      verify(&function.chunk)?;
+    }
+
+    self.fuel = self.instruction_limit;
+    // anything left over from a call that failed is dead by now
+    self.unwind(0, 0);
+    // and the host may have changed globals since the last run
+    self.heap_used = None;
+
+    // the script's frame is set up directly, rather than through `call`, as it takes no arguments
+    // and can't overflow the (empty) stack of frames; like any callee, it sits in its frame's slot 0
     let closure = Closure {
       function,
       upvalues: Vec::new(),
     };
-    self.pop();
-    self.push(Value::Closure(closure.clone()));
-    self.call(closure, 0)?;
+    self.frames.push(CallFrame {
+      closure: closure.clone(),
+      ip: 0,
+      slots_start: self.stack.len(),
+    });
+    self.push(Value::Closure(closure));
+    let result = self.run_until(0).map_err(|error| self.locate(error));
+    debug_assert!(
+      result.is_err() || self.stack.is_empty(),
+      "the script's frame should be popped off the stack once it returns"
+    );
+
+    if let (Err(InterpretError::RuntimeError { error, .. }), Some(hook)) =
+      (&result, &mut self.error_hook)
+    {
+      hook(
+        error,
+        &VmState {
+          frames: &self.frames,
+          stack: &self.stack,
+          globals: &self.globals,
+        },
+      );
+    }
 
-    let result = self.run();
-    self.stack.clear();
-    self.frames.clear();
+    self.unwind(0, 0);
+    while self.callbacks.next().is_some() {}
     result
   }
 
+  /// Interprets the given function, returning the value of each of its top-level
+  /// expression statements (rather than its final value) in the order they ran.
+  pub fn interpret_collecting(&mut self, function: Rc<Function>) -> InterpretResult<Vec<Value>> {
+    self.results = Some(Vec::new());
+    let result = self.interpret(function);
+    let results = self.results.take().unwrap_or_default();
+    result.map(|_| results)
+  }
+
+  /// Interprets the given function, returning its result along with a snapshot of every global
+  /// (including the prelude's natives) afterwards, for scripts that are run to define values.
+  pub fn interpret_with_globals(
+    &mut self,
+    function: Rc<Function>,
+  ) -> InterpretResult<(Value, HashMap<String, Value>)> {
+    let result = self.interpret(function)?;
+    Ok((result, self.globals.clone()))
+  }
+
+  /// Returns the value of the global with the given name, if one has been defined.
+  pub fn get_global(&self, name: &str) -> Option<Value> {
+    self.globals.get(name).cloned()
+  }
+
+  /// Sets the value of the global with the given name, creating it if it doesn't exist yet.
+  pub fn set_global(&mut self, name: &str, value: Value) {
+    self.globals.insert(name.to_string(), value);
+  }
+
+  /// Attaches the line of the instruction that was being run to the given error.
+  fn locate(&self, error: RuntimeError) -> InterpretError {
+    let line = self.current_line();
+    InterpretError::RuntimeError { error, line }
+  }
+
+  /// The line of the instruction that was last run.
+  fn current_line(&self) -> usize {
+    self
+      .frames
+      .last()
+      .and_then(|frame| {
+        frame
+          .closure
+          .function
+          .chunk
+          .code
+          .get(frame.ip.saturating_sub(1))
+      })
+      .map_or(0, |(_, line)| *line)
+  }
+
   fn frame(&self) -> &CallFrame {
     &self.frames[self.frames.len() - 1]
   }
@@ -113,10 +590,45 @@ impl VM {
     self.stack.push(value)
   }
 
+  /// Checks that there's room for a new value of the given size under the heap limit.
+  ///
+  /// Walking the stack and globals for every allocation would make building up a value quadratic,
+  /// so they're only walked once a running total of what's been allocated would go over the limit,
+  /// which finds out how much is actually still in use (as the total doesn't notice values being dropped).
+  /// Copies of values that are already around, like those in a global being read, are only noticed then.
+  fn reserve(&mut self, size: usize) -> Result<(), RuntimeError> {
+    let max_heap = match self.max_heap {
+      Some(max_heap) => max_heap,
+      None => return Ok(()),
+    };
+    let used = match self.heap_used {
+      Some(used) if used.saturating_add(size) <= max_heap => used,
+      _ => self.heap_estimate(),
+    };
+    if used.saturating_add(size) > max_heap {
+      self.heap_used = Some(used);
+      return Err(RuntimeError::OutOfMemory);
+    }
+    self.heap_used = Some(used + size);
+    Ok(())
+  }
+
+  /// Pushes a newly created string, tuple, or record, as long as there's room for it.
+  fn allocate(&mut self, value: Value) -> Result<(), RuntimeError> {
+    self.reserve(value.heap_size())?;
+    self.push(value);
+    Ok(())
+  }
+
   fn peek(&self, n: usize) -> Option<&Value> {
     self.stack.iter().nth_back(n)
   }
 
+  /// Returns whether the right-hand side of the division about to happen is zero.
+  fn divisor_is_zero(&self) -> bool {
+    self.peek(0).is_some_and(Value::is_zero)
+  }
+
   fn pop(&mut self) -> Option<Value> {
     self.stack.pop()
   }
@@ -127,14 +639,46 @@ impl VM {
     }
   }
 
-  fn pop_as<T>(&mut self) -> InterpretResult<T>
+  fn pop_as<T>(&mut self) -> Result<T, RuntimeError>
   where
     Value: TryInto<T, Error = RuntimeError>,
   {
-    Ok(self.pop().ok_or(RuntimeError::Unknown)?.try_into()?)
+    self.pop().ok_or(RuntimeError::Unknown)?.try_into()
   }
 
-  fn peek_and_pop_as<T>(&mut self) -> InterpretResult<T>
+  /// Pops two numbers and pushes the result of the given arithmetic on them.
+  fn arithmetic(&mut self, op: Arithmetic) -> Result<(), RuntimeError> {
+    let b = self.pop().ok_or(RuntimeError::Unknown)?;
+    let a = self.pop().ok_or(RuntimeError::Unknown)?;
+    match op.apply(&a, &b) {
+      Some(value) => {
+        self.push(value);
+        Ok(())
+      }
+      None => Err(RuntimeError::TypeError {
+        expected: "number",
+        found: if a.as_f64().is_none() { a } else { b },
+      }),
+    }
+  }
+
+  /// Pops two numbers (or two strings, which are compared lexicographically) and compares them,
+  /// where `None` means they can't be ordered (e.g. when one is NaN).
+  fn pop_and_compare(&mut self) -> Result<Option<Ordering>, RuntimeError> {
+    if let (Some(Value::String(_)), Some(Value::String(_))) = (self.peek(1), self.peek(0)) {
+      let b = self.pop_as::<String>()?;
+      let a = self.pop_as::<String>()?;
+      Ok(Some(a.cmp(&b)))
+    } else if let (Some(Value::Int(a)), Some(Value::Int(b))) = (self.peek(1), self.peek(0)) {
+      let ordering = a.cmp(b);
+      self.pop_n(2);
+      Ok(Some(ordering))
+    } else {
+      binary_op!(self, f64, |a: f64, b: f64| a.partial_cmp(&b))
+    }
+  }
+
+  fn peek_and_pop_as<T>(&mut self) -> Result<T, RuntimeError>
   where
     Value: TryInto<T, Error = RuntimeError>,
   {
@@ -147,7 +691,7 @@ impl VM {
     Ok(value)
   }
 
-  fn call_value(&mut self, callee: Value, arg_count: u8) -> InterpretResult<()> {
+  fn call_value(&mut self, callee: Value, arg_count: u8) -> Result<(), RuntimeError> {
     match callee {
       Value::Closure(closure) => {
         self.call(closure, arg_count)?;
@@ -155,16 +699,69 @@ impl VM {
       }
       Value::NativeFunction(native_fn) => {
         let arg_start = self.stack.len() - (arg_count as usize);
-        let args = &self.stack[arg_start..];
-        let value = (native_fn.borrow().function)(args)?;
+        // copied out first, since the native may call this same function again
+        let reentrant = native_fn.borrow().reentrant;
+        let value = match reentrant {
+          Some(function) => {
+            let args = self.stack[arg_start..].to_vec();
+            function(self, &args)?
+          }
+          None => (native_fn.borrow_mut().function)(&self.stack[arg_start..])?,
+        };
         self.pop_n(arg_count as usize + 1);
+        // natives aren't held to the heap limit, but what they return still counts towards it
+        if let Some(used) = self.heap_used {
+          self.heap_used = Some(used.saturating_add(value.heap_size()));
+        }
         self.push(value);
         Ok(())
       }
-      _ => Err(RuntimeError::InvalidCallSignature.into()),
+      _ => Err(RuntimeError::InvalidCallSignature),
     }
   }
 
+  /// Calls a closure in place of the current function, reusing its frame,
+  /// which can be done whenever the current function would return the result straight away.
+  fn tail_call(&mut self, closure: Closure, arg_count: u8) -> Result<(), RuntimeError> {
+    if arg_count != closure.function.arity {
+      return Err(RuntimeError::IncorrectParameterCount(
+        closure.function.arity,
+        arg_count,
+      ));
+    }
+
+    // the current function is done with its locals, so any that were captured are closed over
+    let slots_start = self.frame().slots_start;
+    self.close_upvalues(slots_start);
+    // then the callee and its arguments are moved down into the start of the frame's window
+    let callee_start = self.stack.len() - 1 - (arg_count as usize);
+    self.stack.drain(slots_start..callee_start);
+
+    let frame = self.frame_mut();
+    frame.closure = closure;
+    frame.ip = 0;
+    Ok(())
+  }
+
+  /// Whether the current function returns as soon as it resumes, possibly after jumping forwards to a return.
+  fn returns_next(&self) -> bool {
+    let frame = self.frame();
+    let code = &frame.closure.function.chunk.code;
+    let mut ip = frame.ip;
+    loop {
+      match code.get(ip) {
+        Some((OpCode::Return, _)) => return true,
+        Some((OpCode::Jump(JumpDirection::Forwards, offset), _)) => ip += 1 + offset,
+        _ => return false,
+      }
+    }
+  }
+
+  /// Whether the current frame is within a `try` block.
+  fn in_try(&self) -> bool {
+    matches!(self.handlers.last(), Some(handler) if handler.frame_count == self.frames.len())
+  }
+
   fn find_upvalue(&self, idx: usize) -> Option<&Rc<RefCell<Upvalue>>> {
     self.upvalues.iter().find(|&up| match *up.borrow() {
       Upvalue::Open(local) => idx == local,
@@ -195,18 +792,21 @@ impl VM {
       };
 
       if let Some(idx) = replace {
-        let value = &self.stack[idx];
-        upvalue.replace(Upvalue::Closed(value.clone()));
+        let value = self.stack.get(idx).cloned().unwrap_or(Value::Unit);
+        upvalue.replace(Upvalue::Closed(value));
       }
     }
   }
 
-  fn call(&mut self, closure: Closure, arg_count: u8) -> InterpretResult<()> {
+  fn call(&mut self, closure: Closure, arg_count: u8) -> Result<(), RuntimeError> {
     if arg_count != closure.function.arity {
-      return Err(RuntimeError::IncorrectParameterCount(closure.function.arity, arg_count).into());
+      return Err(RuntimeError::IncorrectParameterCount(
+        closure.function.arity,
+        arg_count,
+      ));
     }
-    if self.frames.len() == 64 {
-      return Err(RuntimeError::StackOverflow.into());
+    if self.frames.len() >= self.max_call_depth {
+      return Err(RuntimeError::StackOverflow);
     }
 
     self.frames.push(CallFrame {
@@ -217,15 +817,44 @@ impl VM {
     Ok(())
   }
 
-  fn run(&mut self) -> InterpretResult<Value> {
+  /// Runs until returning from the frame that brings the number of frames back down to `depth`,
+  /// catching any errors raised within `try` blocks that were started since then.
+  fn run_until(&mut self, depth: usize) -> Result<Value, RuntimeError> {
+    loop {
+      let error = match self.run_uncaught(depth) {
+        Err(error) if error.is_catchable() => error,
+        result => return result,
+      };
+      match self.handlers.last() {
+        Some(handler) if handler.frame_count > depth => {
+          let line = self.current_line();
+          let handler = self.handlers.pop().unwrap();
+          self.unwind(handler.frame_count, handler.stack_len);
+          self.frame_mut().ip = handler.catch_ip;
+          self.push(error.to_value(line));
+        }
+        _ => return Err(error),
+      }
+    }
+  }
+
+  /// Runs like [VM::run_until], stopping at the first error raised.
+  fn run_uncaught(&mut self, depth: usize) -> Result<Value, RuntimeError> {
     loop {
       let instruction = {
         let frame = self.frame();
-        let instruction = frame.closure.function.chunk.code[frame.ip].clone();
+        let (instruction, _) = frame.closure.function.chunk.code[frame.ip].clone();
         self.frame_mut().ip += 1;
         instruction
       };
 
+      if let Some(fuel) = &mut self.fuel {
+        if *fuel == 0 {
+          break Err(RuntimeError::InstructionLimitExceeded);
+        }
+        *fuel -= 1;
+      }
+
       if crate::DEBUG {
         print!("\t");
         for value in self.stack.iter() {
@@ -235,30 +864,35 @@ impl VM {
         disassemble_instruction(
           &self.frame().closure.function.chunk,
           &instruction,
-          self.frame().ip,
+          self.frame().ip - 1,
         );
       }
 
       match instruction {
+        OpCode::Unit => self.push(Value::Unit),
+        OpCode::None => self.push(Value::None),
+        OpCode::Some => {
+          let value = self.pop().unwrap();
+          self.push(Value::Some(Box::new(value)));
+        }
         OpCode::Tuple(length) => {
           let mut tuple = Vec::new();
           for _ in 0..length {
             tuple.push(self.pop().unwrap());
           }
           tuple.reverse();
-          self.push(Value::Tuple(tuple.into_boxed_slice()));
+          self.allocate(Value::Tuple(tuple.into()))?;
         }
         OpCode::Record(length) => {
-          let mut record = HashMap::new();
+          let mut fields = Vec::new();
           for _ in 0..length {
-            let name = self.peek(0).unwrap().clone();
-            if let Value::String(name) = name {
-              self.pop();
-              let value = self.pop().unwrap();
-              record.insert(name, value);
-            }
+            let key = RecordKey::try_from(self.pop().unwrap())?;
+            let value = self.pop().unwrap();
+            fields.push((key, value));
           }
-          self.push(Value::Record(record));
+          // fields are popped off the stack in reverse
+          let record: IndexMap<RecordKey, Value> = fields.into_iter().rev().collect();
+          self.allocate(Value::Record(Rc::new(record)))?;
         }
         OpCode::Constant(idx) => {
           let constant = self.frame().closure.function.chunk.constants[idx].clone();
@@ -269,6 +903,12 @@ impl VM {
         OpCode::Pop => {
           self.pop();
         }
+        OpCode::PopResult => {
+          let value = self.pop().unwrap();
+          if let Some(results) = &mut self.results {
+            results.push(value);
+          }
+        }
         OpCode::PopN(count) => {
           self.pop_n(count);
         }
@@ -287,14 +927,19 @@ impl VM {
           self.stack[slot_offset + idx] = self.peek(0).unwrap().clone();
         }
         OpCode::GetGlobal(idx) => {
-          let global = self.frame().closure.function.chunk.constants[idx].clone();
-          let name: String = global.try_into()?;
-
-          let value = self
-            .globals
-            .get(&name)
-            .ok_or(RuntimeError::UndefinedVariable(name))?
-            .clone();
+          let value = match &self.frame().closure.function.chunk.constants[idx] {
+            Value::String(name) => self
+              .globals
+              .get(name)
+              .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?
+              .clone(),
+            global => {
+              break Err(RuntimeError::TypeError {
+                expected: "string",
+                found: global.clone(),
+              })
+            }
+          };
 
           self.push(value);
         }
@@ -309,7 +954,10 @@ impl VM {
             .ok_or(RuntimeError::UndefinedVariable(name))? = new_value;
         }
         OpCode::GetUpvalue(idx) => {
-          let upvalue = &self.frame().closure.upvalues[idx];
+          let upvalue = match self.frame().closure.upvalues.get(idx) {
+            Some(upvalue) => upvalue,
+            None => break Err(RuntimeError::CorruptBytecode),
+          };
           let value = match &*upvalue.borrow() {
             Upvalue::Open(idx) => self.stack[*idx].clone(),
             Upvalue::Closed(value) => value.clone(),
@@ -318,33 +966,55 @@ impl VM {
         }
         OpCode::SetUpvalue(idx) => {
           let new_value = self.peek(0).unwrap().clone();
-          let upvalue = &self.frame().closure.upvalues[idx].clone();
+          let upvalue = match self.frame().closure.upvalues.get(idx) {
+            Some(upvalue) => upvalue.clone(),
+            None => break Err(RuntimeError::CorruptBytecode),
+          };
           match &mut *upvalue.borrow_mut() {
             Upvalue::Open(idx) => self.stack[*idx] = new_value,
             Upvalue::Closed(value) => *value = new_value,
           };
         }
         OpCode::GetProperty(name) => {
-          let value = self.peek(0).unwrap().clone();
-          match value {
-            Value::Record(record) => {
-              if record.contains_key(&name) {
-                self.pop(); // drop the instance
-                let property = record.get(&name).unwrap().clone();
-                self.push(property); // push the property
+          let value = self.pop().unwrap(); // drop the instance
+          let field = match &value {
+            Value::Record(record) => record.get(&RecordKey::from(name.as_str())).cloned(),
+            _ => None,
+          };
+          let property = match field {
+            Some(field) => field,
+            // fall back to any native property defined for the value's type
+            None => match self.native_property(&value, &name) {
+              Some(property) => (property.getter)(&value)?,
+              None if matches!(value, Value::Record(_)) => {
+                break Err(RuntimeError::UndefinedProperty(name))
               }
-            }
-            _ => break Err(RuntimeError::NoProperties.into()),
-          }
+              None => {
+                break Err(RuntimeError::TypeError {
+                  expected: "record",
+                  found: value,
+                })
+              }
+            },
+          };
+          self.push(property); // push the property
         }
-        OpCode::SetProperty(_name) => {
-          let value = self.peek(0).unwrap().clone();
-          match value {
-            Value::Record(_) => {
-              break Err(RuntimeError::ImmutableSet.into());
+        OpCode::SetProperty(name) => {
+          let value = self.pop().unwrap();
+          let record = match self.pop().unwrap() {
+            Value::Record(record) => record,
+            found => {
+              break Err(RuntimeError::TypeError {
+                expected: "record",
+                found,
+              })
             }
-            _ => break Err(RuntimeError::NoProperties.into()),
-          }
+          };
+          // records are immutable, so the field is set (or added to the end) in a copy
+          let mut fields = (*record).clone();
+          fields.insert(RecordKey::from(name), value.clone());
+          self.push(value);
+          self.allocate(Value::Record(Rc::new(fields)))?;
         }
         OpCode::Equal => {
           let b = self.pop().ok_or(RuntimeError::Unknown)?;
@@ -353,76 +1023,104 @@ impl VM {
           self.push(Value::Boolean(value));
         }
         OpCode::GreaterThan => {
-          let value = binary_op!(self, f64, |a, b| a > b)?;
-          self.push(Value::Boolean(value));
+          let ordering = self.pop_and_compare()?;
+          self.push(Value::Boolean(ordering == Some(Ordering::Greater)));
         }
         OpCode::LessThan => {
-          let value = binary_op!(self, f64, |a, b| a < b)?;
-          self.push(Value::Boolean(value));
+          let ordering = self.pop_and_compare()?;
+          self.push(Value::Boolean(ordering == Some(Ordering::Less)));
         }
         OpCode::Add => {
           let b = self.peek(0).ok_or(RuntimeError::Unknown)?;
           let a = self.peek(1).ok_or(RuntimeError::Unknown)?;
 
           match (a, b) {
-            (Value::Number(_), Value::Number(_)) => {
-              let value = binary_op!(self, f64, |a, b| a + b)?;
-              self.push(Value::Number(value));
-            }
             (Value::String(_), Value::String(_))
-            | (Value::Number(_), Value::String(_))
-            | (Value::String(_), Value::Number(_)) => {
+            | (Value::String(_), Value::Int(_) | Value::Number(_))
+            | (Value::Int(_) | Value::Number(_), Value::String(_)) => {
               let b = self.pop_as::<String>()?;
               let a = self.pop_as::<String>()?;
+              self.reserve(a.len() + b.len())?;
               self.push(Value::String(format!("{}{}", a, b)));
             }
-            _ => break Err(RuntimeError::OperationNotSupported.into()),
+            (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+              self.arithmetic(Arithmetic::Add)?
+            }
+            (Value::Tuple(_), Value::Tuple(_)) => {
+              let b: Vec<Value> = self.pop_as()?;
+              let a: Vec<Value> = self.pop_as()?;
+              self.allocate(Value::Tuple(a.into_iter().chain(b).collect()))?;
+            }
+            _ => break Err(RuntimeError::OperationNotSupported),
           }
         }
-        OpCode::Subtract => {
-          let value = binary_op!(self, f64, |a, b| a - b)?;
-          self.push(Value::Number(value));
-        }
+        OpCode::Subtract => self.arithmetic(Arithmetic::Subtract)?,
         OpCode::Multiply => {
           let b = self.peek(0).ok_or(RuntimeError::Unknown)?;
           let a = self.peek(1).ok_or(RuntimeError::Unknown)?;
 
           match (a, b) {
-            (Value::Number(_), Value::Number(_)) => {
-              let value = binary_op!(self, f64, |a, b| a * b)?;
-              self.push(Value::Number(value));
+            (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+              self.arithmetic(Arithmetic::Multiply)?
             }
-            (Value::String(_), Value::Number(_)) => {
+            (Value::String(_), Value::Int(_) | Value::Number(_)) => {
               let b = self.pop_as::<f64>()?;
               let a = self.pop_as::<String>()?;
-              let value: String = repeat(a).take(b.round() as usize).collect();
+              let count = b.round() as usize;
+              self.reserve(a.len().saturating_mul(count))?;
+              let value = a.repeat(count);
               self.push(Value::String(value));
             }
-            _ => break Err(RuntimeError::OperationNotSupported.into()),
+            _ => break Err(RuntimeError::OperationNotSupported),
           }
         }
         OpCode::Divide => {
-          let value = binary_op!(self, f64, |a, b| a / b)?;
-          self.push(Value::Number(value));
+          if self.divisor_is_zero() {
+            break Err(RuntimeError::DivideByZero);
+          }
+          self.arithmetic(Arithmetic::Divide)?;
         }
-        OpCode::Exponent => {
-          let value = binary_op!(self, f64, |a, b| f64::powf(a, b))?;
-          self.push(Value::Number(value));
+        OpCode::Modulo => {
+          if self.divisor_is_zero() {
+            break Err(RuntimeError::DivideByZero);
+          }
+          self.arithmetic(Arithmetic::Modulo)?;
         }
+        OpCode::Exponent => self.arithmetic(Arithmetic::Exponent)?,
         OpCode::Not => {
           let value = self.peek_and_pop_as::<bool>()?;
           self.push(Value::Boolean(!value));
         }
         OpCode::Negate => {
-          let value = self.peek_and_pop_as::<f64>()?;
-          self.push(Value::Number(-value));
+          let value = self.pop().ok_or(RuntimeError::Unknown)?;
+          match value.negate() {
+            Some(value) => self.push(value),
+            None => {
+              break Err(RuntimeError::TypeError {
+                expected: "number",
+                found: value,
+              })
+            }
+          }
         }
-        OpCode::Log => {
-          let value = self.peek(0).unwrap().clone();
+        OpCode::Log(count) => {
+          let value = if count == 1 {
+            self.pop().unwrap()
+          } else {
+            let mut parts = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+              let part = self.pop().ok_or(RuntimeError::Unknown)?;
+              parts.push(part.to_string_with(&self.number_format));
+            }
+            parts.reverse();
+            Value::String(parts.join(" "))
+          };
+          self.push(value.clone());
           if let Some(handler) = &mut self.log_handler {
             (handler)(value);
+            self.run_callbacks()?;
           } else {
-            println!("{}", value);
+            println!("{}", value.display_with(&self.number_format));
           }
         }
         OpCode::Jump(direction, offset) => match direction {
@@ -430,7 +1128,11 @@ impl VM {
           JumpDirection::Backwards => self.frame_mut().ip -= offset,
         },
         OpCode::JumpIfFalse(offset) => {
-          let condition: bool = self.peek(0).unwrap().clone().try_into().unwrap();
+          let condition: bool = self
+            .peek(0)
+            .ok_or(RuntimeError::Unknown)?
+            .clone()
+            .try_into()?;
           if !condition {
             self.frame_mut().ip += offset;
           }
@@ -440,27 +1142,216 @@ impl VM {
           let object = self.pop().unwrap();
 
           match (&object, &index) {
-            (Value::Tuple(tuple), Value::Number(num)) => {
-              let num = num.round() as usize;
-              if num > 0 && num < tuple.len() {
-                self.push(tuple[num].clone());
-                Ok(())
+            (Value::Tuple(tuple), Value::Int(_) | Value::Number(_)) => {
+              // floats may be used as indices, as long as they're whole numbers
+              let num: i64 = index.clone().try_into()?;
+              match resolve_index(num, tuple.len()) {
+                Some(idx) => {
+                  self.push(tuple[idx].clone());
+                  Ok(())
+                }
+                None => Err(RuntimeError::IndexOutOfBounds {
+                  index: num as isize,
+                  len: tuple.len(),
+                }),
+              }
+            }
+            (Value::String(string), Value::Int(_) | Value::Number(_)) => {
+              let num: i64 = index.clone().try_into()?;
+              // strings are indexed by character, so multibyte characters are never split,
+              // and negative indices are found from the end so the string is only walked once
+              let char = if num < 0 {
+                usize::try_from(-(num + 1))
+                  .ok()
+                  .and_then(|idx| string.chars().rev().nth(idx))
               } else {
-                Err(RuntimeError::InvalidIndex(
-                  object.to_string(),
-                  index.to_string(),
-                ))
+                usize::try_from(num)
+                  .ok()
+                  .and_then(|idx| string.chars().nth(idx))
+              };
+              match char {
+                Some(char) => {
+                  self.push(Value::String(char.to_string()));
+                  Ok(())
+                }
+                None => Err(RuntimeError::IndexOutOfBounds {
+                  index: num as isize,
+                  len: string.chars().count(),
+                }),
               }
             }
+            (Value::Record(record), _) => match record.get(&RecordKey::try_from(index.clone())?) {
+              Some(value) => {
+                self.push(value.clone());
+                Ok(())
+              }
+              None => Err(RuntimeError::UndefinedProperty(index.to_string())),
+            },
             (_, _) => Err(RuntimeError::InvalidIndex(
               object.to_string(),
               index.to_string(),
             )),
           }?;
         }
-        OpCode::Call(args) => {
-          self.call_value(self.peek(args as usize).unwrap().clone(), args)?;
+        OpCode::SetIndex => {
+          let value = self.pop().unwrap();
+          let index = self.pop().unwrap();
+          let tuple = match self.pop().unwrap() {
+            Value::Tuple(tuple) => tuple,
+            Value::Record(record) => {
+              let index = match RecordKey::try_from(index) {
+                Ok(index) => index,
+                Err(error) => break Err(error),
+              };
+              // just like setting a property, the field is set (or added to the end) in a copy
+              let mut fields = (*record).clone();
+              fields.insert(index, value.clone());
+              self.push(value);
+              self.allocate(Value::Record(Rc::new(fields)))?;
+              continue;
+            }
+            found => {
+              break Err(RuntimeError::TypeError {
+                expected: "tuple",
+                found,
+              })
+            }
+          };
+          let num: i64 = match index {
+            Value::Int(_) | Value::Number(_) => index.clone().try_into()?,
+            _ => {
+              break Err(RuntimeError::InvalidIndex(
+                Value::Tuple(tuple).to_string(),
+                index.to_string(),
+              ))
+            }
+          };
+          let idx = match resolve_index(num, tuple.len()) {
+            Some(idx) => idx,
+            None => {
+              break Err(RuntimeError::IndexOutOfBounds {
+                index: num as isize,
+                len: tuple.len(),
+              })
+            }
+          };
+          // tuples are immutable, so the element is replaced in a copy,
+          // which is left on top for the compiler to store back into the variable
+          let mut elements = tuple.to_vec();
+          elements[idx] = value.clone();
+          self.push(value);
+          self.allocate(Value::Tuple(elements.into()))?;
+        }
+        OpCode::Append => {
+          let value = self.pop().ok_or(RuntimeError::Unknown)?;
+          let tuple = match self.pop().ok_or(RuntimeError::Unknown)? {
+            Value::Tuple(tuple) => tuple,
+            found => {
+              break Err(RuntimeError::TypeError {
+                expected: "tuple",
+                found,
+              })
+            }
+          };
+          let tuple = tuple
+            .iter()
+            .cloned()
+            .chain(std::iter::once(value))
+            .collect();
+          self.allocate(Value::Tuple(tuple))?;
         }
+        OpCode::Extend => {
+          let items = self.pop().ok_or(RuntimeError::Unknown)?;
+          let extended = match (self.pop().ok_or(RuntimeError::Unknown)?, items) {
+            (Value::Tuple(tuple), Value::Tuple(items)) => {
+              Value::Tuple(tuple.iter().chain(items.iter()).cloned().collect())
+            }
+            (Value::Record(record), Value::Record(fields)) => {
+              let mut record = (*record).clone();
+              for (name, value) in fields.iter() {
+                record.insert(name.clone(), value.clone());
+              }
+              Value::Record(Rc::new(record))
+            }
+            (Value::Tuple(_), found) => {
+              break Err(RuntimeError::TypeError {
+                expected: "tuple",
+                found,
+              })
+            }
+            (_, found) => {
+              break Err(RuntimeError::TypeError {
+                expected: "record",
+                found,
+              })
+            }
+          };
+          self.allocate(extended)?;
+        }
+        OpCode::MatchTuple(len) => {
+          let value = self.pop().unwrap();
+          let matches = matches!(value, Value::Tuple(tuple) if tuple.len() == len as usize);
+          self.push(Value::Boolean(matches));
+        }
+        OpCode::IsUnit => {
+          let is_unit = matches!(self.peek(0), Some(Value::Unit));
+          self.push(Value::Boolean(is_unit));
+        }
+        OpCode::MatchSome => {
+          let value = self.pop().unwrap();
+          self.push(Value::Boolean(matches!(value, Value::Some(_))));
+        }
+        OpCode::Unwrap => match self.pop().unwrap() {
+          Value::Some(value) => self.push(*value),
+          found => {
+            break Err(RuntimeError::TypeError {
+              expected: "some",
+              found,
+            })
+          }
+        },
+        OpCode::HasProperty(name) => {
+          let value = self.pop().unwrap();
+          let has =
+            matches!(value, Value::Record(record) if record.contains_key(&RecordKey::from(name)));
+          self.push(Value::Boolean(has));
+        }
+        OpCode::RecordLen => {
+          let value = self.pop().unwrap();
+          self.push(match value {
+            Value::Record(record) => Value::Int(record.len() as i64),
+            _ => Value::Unit,
+          });
+        }
+        OpCode::NoMatch => break Err(RuntimeError::NonExhaustiveMatch),
+        OpCode::Try(offset) => {
+          let catch_ip = self.frame().ip + offset;
+          self.handlers.push(Handler {
+            frame_count: self.frames.len(),
+            stack_len: self.stack.len(),
+            catch_ip,
+          });
+        }
+        OpCode::EndTry => {
+          self.handlers.pop();
+        }
+        OpCode::Call(args) => match self.peek(args as usize).unwrap().clone() {
+          // the script's own frame is never reused, so that it's always at the bottom,
+          // and neither is one that's within a `try` block, so that it can still catch errors
+          Value::Closure(closure)
+            if self.frames.len() > 1 && !self.in_try() && self.returns_next() =>
+          {
+            self.tail_call(closure, args)?;
+          }
+          callee => {
+            let frames = self.frames.len();
+            self.call_value(callee, args)?;
+            if self.frames.len() == frames {
+              // natives run immediately, so they may have scheduled calls
+              self.run_callbacks()?;
+            }
+          }
+        },
         OpCode::Closure(idx, upvalues) => {
           let function: Rc<Function> = self.frame().closure.function.chunk.constants[idx]
             .clone()
@@ -472,13 +1363,17 @@ impl VM {
               .iter()
               .map(|up| match up {
                 crate::chunk::Upvalue::Local(idx) => {
-                  self.capture_upvalue(self.frame().slots_start + idx)
-                }
-                crate::chunk::Upvalue::Upvalue(idx) => {
-                  Rc::clone(&self.frame().closure.upvalues[*idx])
+                  Ok(self.capture_upvalue(self.frame().slots_start + idx))
                 }
+                crate::chunk::Upvalue::Upvalue(idx) => self
+                  .frame()
+                  .closure
+                  .upvalues
+                  .get(*idx)
+                  .cloned()
+                  .ok_or(RuntimeError::CorruptBytecode),
               })
-              .collect(),
+              .collect::<Result<_, _>>()?,
           };
           self.push(Value::Closure(closure));
         }
@@ -486,21 +1381,27 @@ impl VM {
           self.close_upvalues(self.stack.len() - 1);
           self.pop();
         }
-        OpCode::Return => {
-          let result = self.pop().unwrap_or_else(Value::get_unit);
+        OpCode::Return | OpCode::ReturnUnit => {
+          let result = if let OpCode::Return = instruction {
+            self.pop().unwrap_or_else(Value::get_unit)
+          } else {
+            Value::Unit
+          };
           self.close_upvalues(self.frame().slots_start);
-          if self.frames.len() == 1 {
-            // if this is the last frame, pop it and break the loop
-            self.frames.pop();
-            self.pop();
-            break Ok(result);
-          }
 
-          // otherwise, pop everything in that frame's stack window and push the result back
+          // pop everything in that frame's stack window, along with any `try` blocks it's returning from
           let pop_count = self.stack.len() - self.frame().slots_start;
           self.pop_n(pop_count);
-          self.push(result);
+          while self.in_try() {
+            self.handlers.pop();
+          }
           self.frames.pop();
+
+          if self.frames.len() == depth {
+            // if we've returned from the frame we started in, we're done here
+            break Ok(result);
+          }
+          self.push(result);
         }
       }
     }
@@ -1,9 +1,16 @@
 use std::{
   convert::TryInto,
+  env, fs,
   io::{self, Write},
+  process,
 };
 
-use bobascript::{compiler::compile, value::Value, vm::VM, InterpretError, InterpretResult};
+use bobascript::{
+  compiler::{self, compile},
+  value::Value,
+  vm::VM,
+  InterpretError, InterpretResult,
+};
 use rustyline::{
   error::ReadlineError,
   validate::{MatchingBracketValidator, ValidationContext, ValidationResult, Validator},
@@ -29,6 +36,13 @@ fn log_handler(value: Value) {
 }
 
 fn main() -> InterpretResult<()> {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if args.iter().any(|arg| arg == "--check") {
+    let json = args.iter().any(|arg| arg == "--json");
+    let path = args.iter().find(|arg| !arg.starts_with("--"));
+    process::exit(check_file(path, json));
+  }
+
   // set up editor
   let helper = InputValidator {
     brackets: MatchingBracketValidator::new(),
@@ -47,7 +61,7 @@ fn main() -> InterpretResult<()> {
         rl.add_history_entry(&input);
         let function = compile(input)?;
         match vm.interpret(function) {
-          Ok(value) => println!("< {}", value),
+          Ok(value) => println!("< {}", vm.display(&value)),
           Err(err) => print_error(format!("{}", err)).map_err(|_| InterpretError::Unknown)?,
         }
       }
@@ -64,6 +78,84 @@ fn main() -> InterpretResult<()> {
   }
 }
 
+/// Compiles the given file without running it, printing every error and warning found.
+/// Returns the exit code, which is nonzero if there were any errors.
+fn check_file(path: Option<&String>, json: bool) -> i32 {
+  let path = match path {
+    Some(path) => path,
+    None => {
+      let _ = print_error("Expected a file to check.".to_string());
+      return 2;
+    }
+  };
+  let source = match fs::read_to_string(path) {
+    Ok(source) => source,
+    Err(err) => {
+      let _ = print_error(format!("Could not read \"{}\": {}", path, err));
+      return 2;
+    }
+  };
+
+  let (errors, warnings) = compiler::check(source);
+  let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+  let warnings: Vec<String> = warnings.iter().map(|warn| warn.to_string()).collect();
+
+  if json {
+    println!(
+      "{{\"errors\":[{}],\"warnings\":[{}]}}",
+      errors
+        .iter()
+        .map(|s| json_string(s))
+        .collect::<Vec<_>>()
+        .join(","),
+      warnings
+        .iter()
+        .map(|s| json_string(s))
+        .collect::<Vec<_>>()
+        .join(",")
+    );
+  } else {
+    for err in errors.iter() {
+      let _ = print_error(err.clone());
+    }
+    for warn in warnings.iter() {
+      let _ = print_warning(warn.clone());
+    }
+  }
+
+  if errors.is_empty() {
+    0
+  } else {
+    1
+  }
+}
+
+/// Quotes and escapes a string for use in JSON output.
+fn json_string(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+fn print_warning(msg: String) -> io::Result<()> {
+  let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+  stderr.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+  writeln!(&mut stderr, "[?] {}", msg)?;
+  stderr.reset()
+}
+
 fn print_error(msg: String) -> io::Result<()> {
   let mut stderr = StandardStream::stderr(ColorChoice::Auto);
   stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
@@ -0,0 +1,144 @@
+use std::rc::Rc;
+
+use bobascript::{
+  asm::{assemble, to_assembly, AsmError},
+  compiler::compile,
+  value::{Function, Value},
+  vm::VM,
+};
+
+#[test]
+fn round_trip() {
+  let function = compile(
+    r#"
+    let limit = 10;
+    fn counter() {
+      let count = 0;
+      fn inc() { count += 1; count };
+      inc
+    };
+    let next = counter();
+    let total = while true {
+      let n = next();
+      if n > limit { break n; };
+    };
+    let kind = match #[total, "a; b\n"] {
+      #[0, _] => "zero",
+      #[n, s] => s,
+    };
+    log(kind, -0.5, 1.0, #{ x: ()}.x);
+    try { 1 / 0 } catch err { err.kind };
+    "#,
+  )
+  .unwrap();
+
+  let text = to_assembly(&function.chunk);
+  let chunk = assemble(&text).unwrap();
+  assert_eq!(to_assembly(&chunk), text);
+  assert_eq!(chunk.code.len(), function.chunk.code.len());
+  assert_eq!(chunk.constants.len(), function.chunk.constants.len());
+}
+
+#[test]
+fn labels_resolve_to_jumps() {
+  let chunk = assemble(
+    r#"
+    ; counts down from 3, leaving 0 on the stack
+    .const 3
+    .const 0
+    .const 1
+    .line 1
+      Constant 0
+    loop:
+      GetLocal 1
+      Constant 1
+      GreaterThan
+      JumpIfFalse done
+      Pop
+      GetLocal 1
+      Constant 2
+      Subtract
+      SetLocal 1
+      Pop
+      Jump loop
+    done:
+      Pop
+      Return
+    "#,
+  )
+  .unwrap();
+
+  let text = to_assembly(&chunk);
+  assert!(text.contains("JumpIfFalse L1"));
+  assert!(text.contains("Jump L0"));
+
+  let function = Rc::new(Function {
+    chunk,
+    ..Default::default()
+  });
+  let mut vm = VM::default();
+  vm.set_verify(true);
+  assert!(matches!(vm.interpret(function), Ok(Value::Int(0))));
+}
+
+#[test]
+fn functions() {
+  let chunk = assemble(
+    r#"
+    .function "double" 1
+      .line 1
+        GetLocal 1
+        Constant 0
+        Multiply
+        Return
+      .const 2
+    .end
+    .const 21
+      Closure 0
+      Constant 1
+      Call 1
+      Return
+    "#,
+  )
+  .unwrap();
+  let function = Rc::new(Function {
+    chunk,
+    ..Default::default()
+  });
+  let mut vm = VM::default();
+  vm.set_verify(true);
+  assert!(matches!(vm.interpret(function), Ok(Value::Int(42))));
+}
+
+#[test]
+fn errors() {
+  assert!(matches!(
+    assemble("Frobnicate"),
+    Err(AsmError::UnknownInstruction(1, _))
+  ));
+  assert!(matches!(
+    assemble("Unit\nConstant"),
+    Err(AsmError::MissingOperand(2))
+  ));
+  assert!(matches!(
+    assemble("Pop 1"),
+    Err(AsmError::InvalidOperand(1, _))
+  ));
+  assert!(matches!(
+    assemble(".const what"),
+    Err(AsmError::InvalidConstant(1, _))
+  ));
+  assert!(matches!(
+    assemble("Jump nowhere"),
+    Err(AsmError::UndefinedLabel(1, _))
+  ));
+  assert!(matches!(
+    assemble("a:\na:"),
+    Err(AsmError::DuplicateLabel(2, _))
+  ));
+  assert!(matches!(
+    assemble(".function \"f\" 0\nReturnUnit"),
+    Err(AsmError::UnterminatedFunction(1))
+  ));
+  assert!(matches!(assemble(".end"), Err(AsmError::UnexpectedEnd(1))));
+}
@@ -56,7 +56,30 @@ fn infix_operator() {
   );
   println!("result: {:?}", result);
   assert!(result.is_err());
-  assert_compile_err!(result, CompileError::InvalidAssignmentTarget);
+  assert!(matches!(
+    result,
+    Err(CompileError::InvalidAssignmentTarget {
+      line: 4,
+      col: 5,
+      ..
+    })
+  ));
+}
+
+#[test]
+fn invalid_target_is_quoted() {
+  let result = compile(
+    r#"
+    let a = "a";
+    let b = "b";
+    (a + b) * 2 = "value";
+    "#,
+  );
+  let message = result.unwrap_err().to_string();
+  assert_eq!(
+    message,
+    r#"Invalid assignment target "(a + b) * 2" on line 4, col 5."#
+  );
 }
 
 #[test]
@@ -92,7 +115,10 @@ fn prefix_operator() {
   );
   println!("result: {:?}", result);
   assert!(result.is_err());
-  assert_compile_err!(result, CompileError::InvalidAssignmentTarget);
+  assert!(matches!(
+    result,
+    Err(CompileError::InvalidAssignmentTarget { .. })
+  ));
 }
 
 #[test]
@@ -112,6 +138,91 @@ fn syntax() {
   assert_eval!(vm, "c", Value::String("var".to_string()));
 }
 
+#[test]
+fn short_circuit_global() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let calls = 0;
+    fn bump(value) {
+      calls += 1;
+      value
+    };
+
+    let a = false;
+    let b = true;
+    "#,
+  )
+  .unwrap();
+  assert!(vm.interpret(function).is_ok());
+
+  // the right-hand side is only evaluated when the target is false
+  assert_eval!(vm, "b ||= bump(false)", Value::Boolean(true));
+  assert_eval!(vm, "b", Value::Boolean(true));
+  assert_eval!(vm, "calls", Value::Int(0));
+  assert_eval!(vm, "a ||= bump(true)", Value::Boolean(true));
+  assert_eval!(vm, "a", Value::Boolean(true));
+  assert_eval!(vm, "calls", Value::Int(1));
+
+  // and when the target is true
+  assert_eval!(vm, "a &&= bump(false)", Value::Boolean(false));
+  assert_eval!(vm, "a", Value::Boolean(false));
+  assert_eval!(vm, "calls", Value::Int(2));
+  assert_eval!(vm, "a &&= bump(true)", Value::Boolean(false));
+  assert_eval!(vm, "a", Value::Boolean(false));
+  assert_eval!(vm, "calls", Value::Int(2));
+}
+
+#[test]
+fn short_circuit_local() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    {
+      let calls = 0;
+      fn bump(value) {
+        calls += 1;
+        value
+      };
+
+      let a = false;
+      let b = true;
+      let first = a ||= bump(true);
+      let second = b ||= bump(false);
+      let third = b &&= bump(false);
+      let fourth = b &&= bump(true);
+      #[calls, a, b, first, second, third, fourth]
+    }
+    "#,
+    Value::Tuple(
+      vec![
+        Value::Int(2),
+        Value::Boolean(true),
+        Value::Boolean(false),
+        Value::Boolean(true),
+        Value::Boolean(true),
+        Value::Boolean(false),
+        Value::Boolean(false),
+      ]
+      .into()
+    )
+  );
+}
+
+#[test]
+fn short_circuit_requires_boolean() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "let a = 1; a ||= true;",
+    RuntimeError::TypeError {
+      expected: "boolean",
+      found: Value::Unit,
+    }
+  );
+}
+
 #[test]
 fn undefined() {
   let mut vm = VM::default();
@@ -123,3 +234,40 @@ fn undefined() {
     RuntimeError::UndefinedVariable("unknown".to_string())
   );
 }
+
+#[test]
+fn compound_element_assignment() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(
+    vm,
+    r#"
+    let calls = 0;
+    fn side_effect() {
+      calls += 1;
+      1
+    };
+    let arr = #[1, 2, 3];
+    let rec = #{count: 1, enabled: false};
+    "#,
+  );
+  assert_eval!(vm, "arr[side_effect()] += 10", Value::Int(12));
+  assert_eval!(vm, "calls", Value::Int(1));
+  assert_eval!(
+    vm,
+    "arr[0] *= 2; arr[-1] -= 1; arr",
+    vec![Value::Int(2), Value::Int(12), Value::Int(2)].into()
+  );
+
+  assert_eval!(vm, "rec.count += 1; rec.count", Value::Int(2));
+  assert_eval!(vm, "rec.enabled ||= true", Value::Boolean(true));
+  assert_eval!(
+    vm,
+    "rec.enabled &&= false; rec.enabled",
+    Value::Boolean(false)
+  );
+  assert_eval!(
+    vm,
+    "{ let local = #[2]; local[0] ^= 3; local[0] }",
+    Value::Int(8)
+  );
+}
@@ -0,0 +1,30 @@
+use bobascript::{
+  ast::{mk, Ast, BinaryOp},
+  compiler::compile_ast,
+  value::Value,
+  vm::VM,
+};
+
+#[test]
+fn hand_built_ast() {
+  let mut vm = VM::default();
+  let ast = Ast(
+    vec![],
+    Some(mk::binary(mk::number(1.0), BinaryOp::Add, mk::number(2.0)).into()),
+  );
+  let function = compile_ast(&ast).unwrap();
+  let value = vm.interpret(function).unwrap();
+  assert!(Value::equal(&value, &Value::Number(3.0)));
+}
+
+#[test]
+fn hand_built_statements() {
+  let mut vm = VM::default();
+  let ast = Ast(
+    vec![mk::let_stmt("greeting", Some(mk::string("howdy"))).into()],
+    Some(mk::binary(mk::ident("greeting"), BinaryOp::Add, mk::string("!")).into()),
+  );
+  let function = compile_ast(&ast).unwrap();
+  let value = vm.interpret(function).unwrap();
+  assert!(Value::equal(&value, &Value::String("howdy!".to_string())));
+}
@@ -1,43 +1,40 @@
-use bobascript::{compiler::compile, value::Value, vm::VM};
+use bobascript::{value::Value, vm::VM};
 
 mod common;
 
 #[test]
 fn concatenation() {
   let mut vm = VM::default();
-  let function = compile(
+  assert_runtime_ok!(
+    vm,
     r#"
     let test = "1" + {
       let test2 = 15;
       test2 / 3
     };
     "#,
-  )
-  .unwrap();
-  let result = vm.interpret(function);
-  assert!(result.is_ok());
+  );
   assert_eval!(vm, "test", Value::String("15".to_string()));
 }
 
 #[test]
 fn empty() {
   let mut vm = VM::default();
-  let function = compile(
+  assert_runtime_ok!(
+    vm,
     r#"
     {};
     if true {};
     if false {} else {};
     "#,
-  )
-  .unwrap();
-  let result = vm.interpret(function);
-  assert!(result.is_ok());
+  );
 }
 
 #[test]
 fn scope() {
   let mut vm = VM::default();
-  let function = compile(
+  assert_runtime_ok!(
+    vm,
     r#"
     let result = true;
     let a = "outer";
@@ -51,9 +48,55 @@ fn scope() {
     log(a);
     result &&= a == "outer";
     "#,
-  )
-  .unwrap();
-  let result = vm.interpret(function);
-  assert!(result.is_ok());
+  );
   assert_eval!(vm, "result", Value::Boolean(true));
 }
+
+#[test]
+fn statements_without_semicolons() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(
+    vm,
+    r#"
+    let count = 0;
+    if true {
+      count += 1;
+    }
+    while count < 3 {
+      count += 1;
+    }
+    {
+      count += 1;
+    }
+    "#,
+  );
+  assert_eval!(vm, "count", Value::Int(4));
+}
+
+#[test]
+fn else_if_chains() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(
+    vm,
+    r#"
+    fn classify(n) => if n < 0 {
+      "negative"
+    } else if n == 0 {
+      "zero"
+    } else {
+      "positive"
+    };
+    "#
+  );
+  assert_eval!(
+    vm,
+    "#[classify(-5), classify(0), classify(5)]",
+    Value::tuple_from(vec![
+      Value::String("negative".to_string()),
+      Value::String("zero".to_string()),
+      Value::String("positive".to_string()),
+    ])
+  );
+  // a chain without a final `else` is unit when no branch is taken
+  assert_eval!(vm, "if false { 1 } else if false { 2 }", Value::Unit);
+}
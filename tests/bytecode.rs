@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use bobascript::{
+  bytecode::{BytecodeError, FORMAT_VERSION},
+  compiler::compile,
+  value::{Function, Value},
+  vm::VM,
+};
+
+const FIB: &str = r#"
+  fn fib(n) {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+  };
+  let names = #{ label: "fib", missing: none, flags: #[true, false, 1.5] };
+  #[fib(15), names.label]
+"#;
+
+#[test]
+fn round_trip() {
+  let function = compile(FIB).unwrap();
+  let bytes = function.to_bytes();
+  let loaded = Function::from_bytes(&bytes).unwrap();
+  assert_eq!(loaded.to_bytes(), bytes);
+  assert_eq!(loaded.chunk.code.len(), function.chunk.code.len());
+  assert_eq!(loaded.chunk.constants.len(), function.chunk.constants.len());
+
+  let expected = VM::default().interpret(function).unwrap();
+  let actual = VM::default().interpret(Rc::new(loaded)).unwrap();
+  assert_eq!(actual, expected);
+  assert_eq!(
+    actual,
+    Value::tuple_from(vec![Value::Int(610), Value::String("fib".to_string())])
+  );
+}
+
+#[test]
+fn rejects_bad_input() {
+  assert!(matches!(
+    Function::from_bytes(b"not bytecode"),
+    Err(BytecodeError::NotBytecode)
+  ));
+
+  let bytes = compile("1 + 2").unwrap().to_bytes();
+  let mut newer = bytes.clone();
+  newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+  assert!(matches!(
+    Function::from_bytes(&newer),
+    Err(BytecodeError::UnsupportedVersion(_))
+  ));
+  assert!(matches!(
+    Function::from_bytes(&bytes[..bytes.len() - 1]),
+    Err(BytecodeError::UnexpectedEnd)
+  ));
+  let mut extra = bytes;
+  extra.push(0);
+  assert!(matches!(
+    Function::from_bytes(&extra),
+    Err(BytecodeError::TrailingBytes(1))
+  ));
+}
+
+#[test]
+fn rejects_deeply_nested_constants() {
+  let mut bytes = b"BOBA".to_vec();
+  bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+  // an unnamed function taking no arguments, with a single constant that's `some` a million times over
+  bytes.extend_from_slice(&[0, 0, 1]);
+  bytes.extend_from_slice(&vec![2; 1_000_000]);
+  assert!(matches!(
+    Function::from_bytes(&bytes),
+    Err(BytecodeError::TooDeep(_))
+  ));
+}
+
+#[test]
+fn rejects_unhashable_record_keys() {
+  let mut bytes = b"BOBA".to_vec();
+  bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+  // an unnamed function taking no arguments, with a single constant that's a record keyed by NaN
+  bytes.extend_from_slice(&[0, 0, 1, 4, 1, 6]);
+  bytes.extend_from_slice(&f64::NAN.to_le_bytes());
+  bytes.push(0);
+  assert!(matches!(
+    Function::from_bytes(&bytes),
+    Err(BytecodeError::InvalidRecordKey(_))
+  ));
+}
@@ -1,4 +1,8 @@
-use bobascript::vm::{RuntimeError, VM};
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::{RuntimeError, VM},
+};
 
 mod common;
 
@@ -61,3 +65,42 @@ fn string() {
     RuntimeError::InvalidCallSignature
   );
 }
+
+#[test]
+fn keyword_arguments() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn point(x, y, z) => #[x, y, z];
+    #[point(z = 3, x = 1, y = 2), point(1, z = 3, y = 2)]
+    "#,
+    Value::tuple_from(vec![
+      Value::tuple_from(vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
+      Value::tuple_from(vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
+    ])
+  );
+  assert_eval!(
+    vm,
+    "{ fn sub(a, b) => a - b; sub(b = 1, a = 10) }",
+    Value::Int(9)
+  );
+}
+
+#[test]
+fn invalid_keyword_arguments() {
+  let source = "fn sub(a, b) => a - b;";
+  let result = compile(format!("{} sub(1, b = 2, c = 3);", source));
+  assert!(matches!(
+    result,
+    Err(CompileError::UnknownKeywordArgument(function, name)) if function == "sub" && name == "c"
+  ));
+  let result = compile(format!("{} sub(1, a = 2, b = 3);", source));
+  assert!(matches!(result, Err(CompileError::DuplicateArgument(name)) if name == "a"));
+  let result = compile(format!("{} sub(b = 2);", source));
+  assert!(matches!(result, Err(CompileError::MissingArgument(name)) if name == "a"));
+  let result = compile(format!("{} sub(a = 1, 2, b = 3);", source));
+  assert!(matches!(result, Err(CompileError::PositionalAfterKeyword)));
+  let result = compile("let sub = fn(a, b) => a - b; sub(b = 1, a = 2);");
+  assert!(matches!(result, Err(CompileError::UnknownSignature(name)) if name == "sub"));
+}
@@ -0,0 +1,66 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+#[test]
+fn log_handler_schedules_callback() {
+  let logs = Rc::new(RefCell::new(Vec::new()));
+
+  let mut vm = VM::default();
+  let queue = vm.callback_queue();
+  let handler_logs = Rc::clone(&logs);
+  vm.add_log_handler(Box::new(move |value| {
+    if let Value::Closure(_) = value {
+      // run whatever function was logged, which logs again
+      queue.schedule(value, vec![Value::String("again".to_string())]);
+    } else {
+      handler_logs.borrow_mut().push(value.to_string());
+    }
+  }));
+
+  let function = compile(
+    r#"
+    fn callback(message) {
+      log(message);
+    };
+    log(callback);
+    log("after");
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eq!(
+    *logs.borrow(),
+    vec!["\"again\"".to_string(), "\"after\"".to_string()]
+  );
+}
+
+#[test]
+fn call_function_returns_result() {
+  let mut vm = VM::default();
+  let function = compile("fn double(a) => a * 2; double").unwrap();
+  let double = vm.interpret(function).unwrap();
+
+  let result = vm.call_function(double, vec![Value::Number(21.0)]).unwrap();
+  assert!(Value::equal(&result, &Value::Number(42.0)));
+}
+
+#[test]
+fn failed_call_function_is_unwound() {
+  let logs = Rc::new(RefCell::new(Vec::new()));
+
+  let mut vm = VM::default();
+  let handler_logs = Rc::clone(&logs);
+  vm.add_log_handler(Box::new(move |value| {
+    handler_logs.borrow_mut().push(value.to_string());
+  }));
+
+  let function = compile(r#"fn bad(a) { let x = a + #[1]; log("STALE"); 7 }; bad"#).unwrap();
+  let bad = vm.interpret(function).unwrap();
+  assert!(vm.call_function(bad, vec![Value::Int(1)]).is_err());
+
+  let result = vm.interpret(compile("40 + 2").unwrap()).unwrap();
+  assert!(Value::equal(&result, &Value::Int(42)));
+  assert!(logs.borrow().is_empty());
+}
@@ -0,0 +1,56 @@
+use std::{env, fs, process::Command};
+
+fn check(name: &str, source: &str, args: &[&str]) -> (i32, String, String) {
+  let path = env::temp_dir().join(format!("bobascript-{}-{}.boba", name, std::process::id()));
+  fs::write(&path, source).unwrap();
+
+  let output = Command::new(env!("CARGO_BIN_EXE_bobascript-cli"))
+    .arg("--check")
+    .args(args)
+    .arg(&path)
+    .output()
+    .unwrap();
+  fs::remove_file(&path).unwrap();
+
+  (
+    output.status.code().unwrap(),
+    String::from_utf8(output.stdout).unwrap(),
+    String::from_utf8(output.stderr).unwrap(),
+  )
+}
+
+const SOURCE: &str = r#"
+{
+  let unused = 1;
+};
+return 2;
+"#;
+
+#[test]
+fn check_reports_errors_and_warnings() {
+  let (code, _, stderr) = check("human", SOURCE, &[]);
+  assert_eq!(code, 1);
+  assert!(stderr.contains("Cannot return from top-level code."));
+  assert!(stderr.contains(r#"Unused variable "unused"."#));
+}
+
+#[test]
+fn check_json() {
+  let (code, stdout, _) = check("json", SOURCE, &["--json"]);
+  assert_eq!(code, 1);
+  // debug builds of the CLI print disassembled chunks before the results
+  assert_eq!(
+    stdout.lines().last().unwrap(),
+    r#"{"errors":["Cannot return from top-level code."],"warnings":["Unused variable \"unused\". Prefix it with an underscore if this is intentional."]}"#
+  );
+}
+
+#[test]
+fn check_without_errors() {
+  let (code, stdout, _) = check("ok", "let a = 1;", &["--json"]);
+  assert_eq!(code, 0);
+  assert_eq!(
+    stdout.lines().last().unwrap(),
+    r#"{"errors":[],"warnings":[]}"#
+  );
+}
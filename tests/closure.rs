@@ -1,4 +1,8 @@
-use bobascript::{compiler::compile, value::Value, vm::VM};
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::VM,
+};
 
 mod common;
 
@@ -43,3 +47,54 @@ fn closures_with_closed_upvalues_work() {
   assert!(result.is_ok());
   assert_eval!(vm, "closure()", Value::String("outside".to_string()));
 }
+
+#[test]
+fn many_upvalues() {
+  fn source(count: usize) -> String {
+    let locals: String = (0..count).map(|i| format!("let v{} = {};", i, i)).collect();
+    let sum = (0..count)
+      .map(|i| format!("v{}", i))
+      .collect::<Vec<_>>()
+      .join(" + ");
+    format!("fn outer() {{ {} fn inner() => {}; inner }};", locals, sum)
+  }
+
+  let mut vm = VM::default();
+  let function = compile(source(256)).unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "outer()()", Value::Number(32640.0));
+
+  let result = compile(source(257));
+  assert_compile_err!(result, CompileError::TooManyUpvalues);
+}
+
+#[test]
+fn anonymous_closures() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn counter() {
+      let count = 0;
+      fn() {
+        count += 1;
+        count
+      }
+    };
+    let next = counter();
+    next();
+    next();
+    next()
+    "#,
+    Value::Int(3)
+  );
+  assert_eval!(
+    vm,
+    r#"
+    fn adder(n) => fn(x) => x + n;
+    adder(2)(40)
+    "#,
+    Value::Int(42)
+  );
+}
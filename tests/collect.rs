@@ -0,0 +1,31 @@
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+#[test]
+fn collects_expression_statements() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let x = 2;
+    x * 3;
+    { let y = 1; y + 1 };
+    "done";
+    "#,
+  )
+  .unwrap();
+  let results = vm.interpret_collecting(function).unwrap();
+  assert_eq!(results.len(), 3);
+  assert!(Value::equal(&results[0], &Value::Number(6.0)));
+  assert!(Value::equal(&results[1], &Value::Number(2.0)));
+  assert!(Value::equal(
+    &results[2],
+    &Value::String("done".to_string())
+  ));
+}
+
+#[test]
+fn interpret_does_not_collect() {
+  let mut vm = VM::default();
+  let function = compile("1; 2; 3").unwrap();
+  let result = vm.interpret(function).unwrap();
+  assert!(Value::equal(&result, &Value::Number(3.0)));
+}
@@ -5,10 +5,31 @@ macro_rules! assert_eval {
     let result = $vm.interpret(function);
     println!("eval result: {:?}", result);
     let value = result.unwrap();
-    assert!(Value::equal(&value, &$expected));
+    let expected = $expected;
+    let diff = value.diff(&expected).unwrap_or_default();
+    assert_eq!(value, expected, "{}", diff);
   };
 }
 
+#[macro_export]
+macro_rules! assert_compiles {
+  ($script:expr $(,)?) => {{
+    let result = bobascript::compiler::compile($script);
+    println!("compile result: {:?}", result);
+    result.unwrap()
+  }};
+}
+
+#[macro_export]
+macro_rules! assert_runtime_ok {
+  ($vm:expr, $script:expr $(,)?) => {{
+    let function = assert_compiles!($script);
+    let result = $vm.interpret(function);
+    println!("result: {:?}", result);
+    result.unwrap()
+  }};
+}
+
 #[macro_export]
 macro_rules! assert_runtime_err {
   ($vm:expr, $script:expr, $expected:expr) => {
@@ -17,7 +38,7 @@ macro_rules! assert_runtime_err {
     println!("result: {:?}", result);
     assert!(result.is_err());
     assert!(
-      if let Err(bobascript::InterpretError::RuntimeError(result)) = result {
+      if let Err(bobascript::InterpretError::RuntimeError { error: result, .. }) = result {
         std::mem::discriminant(&result) == std::mem::discriminant(&$expected)
       } else {
         false
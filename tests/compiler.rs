@@ -0,0 +1,176 @@
+use bobascript::{
+  chunk::OpCode,
+  compiler::{compile, compile_pooled, CompileError, Compiler},
+  value::Value,
+  vm::VM,
+};
+
+mod common;
+
+#[test]
+fn reused_compiler() {
+  let mut compiler = Compiler::new();
+  let mut vm = VM::default();
+
+  let function = compiler
+    .compile_source("fn double(a) => a * 2; let x = double(2);")
+    .unwrap();
+  assert!(vm.interpret(function).is_ok());
+
+  // nothing from the first script (like "double" being a function) carries over
+  let function = compiler
+    .compile_source("let double = 3; double = 4; double + x")
+    .unwrap();
+  let result = vm.interpret(function).unwrap();
+  assert!(Value::equal(&result, &Value::Number(8.0)));
+}
+
+#[test]
+fn reused_after_error() {
+  let mut compiler = Compiler::new();
+
+  let result = compiler.compile_source("return 1;");
+  assert_compile_err!(result, CompileError::TopLevelReturn);
+
+  let function = compiler.compile_source("1 + 2").unwrap();
+  let mut vm = VM::default();
+  let result = vm.interpret(function).unwrap();
+  assert!(Value::equal(&result, &Value::Number(3.0)));
+}
+
+#[test]
+fn pooled_compiles_are_independent() {
+  let mut vm = VM::default();
+
+  // the first script leaves behind a closure's context, with locals, upvalues, and a loop
+  let function = compile_pooled(
+    r#"
+    fn counter(start) {
+      let count = start;
+      fn next() {
+        while count < start + 2 { count = count + 1; };
+        count
+      };
+      next
+    };
+    counter(1)()
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function).unwrap();
+  assert!(Value::equal(&result, &Value::Int(3)));
+
+  // none of which may leak into the next one
+  let function = compile_pooled("fn next(a, b) => a - b; let count = next(10, 4); count").unwrap();
+  let mut vm = VM::default();
+  let result = vm.interpret(function).unwrap();
+  assert!(Value::equal(&result, &Value::Int(6)));
+
+  let result = compile_pooled("fn f() { return; }; return 1;");
+  assert_compile_err!(result, CompileError::TopLevelReturn);
+  // `next` was only known to be a function by the script that declared it
+  let result = compile_pooled("next(a = 10, b = 4)");
+  assert!(matches!(result, Err(CompileError::UnknownSignature(name)) if name == "next"));
+}
+
+#[test]
+fn folds_number_arithmetic() {
+  let function = compile("-(10 + 5 * 2) ^ 2").unwrap();
+  let code = &function.chunk.code;
+  assert_eq!(code.len(), 2);
+  assert!(matches!(code[0].0, OpCode::Constant(_)));
+  assert!(Value::equal(
+    &function.chunk.constants[0],
+    &Value::Number(400.0)
+  ));
+}
+
+#[test]
+fn does_not_fold_division_by_zero() {
+  let function = compile("1 / 0").unwrap();
+  assert!(function
+    .chunk
+    .code
+    .iter()
+    .any(|(opcode, _)| matches!(opcode, OpCode::Divide)));
+}
+
+#[test]
+fn balanced_constructs_compile() {
+  // each of these is checked for an unbalanced stack as it's compiled
+  // (the checks themselves are tested against a hand-built context in the compiler's unit tests)
+  let function = compile(
+    r#"
+    let a = if true { let b = 1; b } else if false { 2 } else { 3 };
+    if a > 1 { a; };
+    fn f(x) {
+      let y = {
+        let z = x;
+        z + 1
+      };
+      while y < 10 {
+        let w = y;
+        if w > 5 { break; };
+        y = y + 1;
+      };
+      match #[y, 2] {
+        #[1, n] => n,
+        #[m, _] => { let k = m; k },
+      }
+    };
+    'outer: while true { while true { break 'outer f(a); }; };
+    "#,
+  );
+  assert!(function.is_ok());
+}
+
+#[test]
+fn scope_locals_are_popped_together() {
+  let function = compile(
+    r#"
+    {
+      let a = 1;
+      let b = 2;
+      let c = 3;
+      let d = 4;
+      let e = 5;
+      log(a + b + c + d + e);
+    };
+    "#,
+  )
+  .unwrap();
+  let code = &function.chunk.code;
+  assert!(code
+    .iter()
+    .any(|(opcode, _)| matches!(opcode, OpCode::PopN(5))));
+
+  // captured locals are closed over in between the runs of pops
+  let function = compile(
+    r#"
+    {
+      let a = 1;
+      let b = 2;
+      let c = 3;
+      let f = fn() => b;
+      log(a + c, f());
+    };
+    "#,
+  )
+  .unwrap();
+  let opcodes: Vec<&OpCode> = function.chunk.code.iter().map(|(op, _)| op).collect();
+  let end = opcodes
+    .iter()
+    .position(|op| matches!(op, OpCode::PopN(2)))
+    .unwrap();
+  assert!(matches!(
+    opcodes[end..end + 3],
+    [OpCode::PopN(2), OpCode::CloseUpvalue, OpCode::Pop]
+  ));
+
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let t = { let a = 1; let b = 2; let c = 3; a + b + c }; t",
+    Value::Int(6)
+  );
+}
@@ -0,0 +1,57 @@
+use bobascript::{value::Value, vm::VM};
+
+mod common;
+
+#[test]
+fn picks_a_branch() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "let c = true; let x = c ? 1 : 2; x", Value::Int(1));
+  assert_eval!(vm, "let y = !c ? 1 : 2; y", Value::Int(2));
+  // the condition binds looser than any binary operator
+  assert_eval!(vm, "1 + 1 == 2 ? 3 * 2 : 4 + 5", Value::Int(6));
+  assert_eval!(
+    vm,
+    "#[false ? 1 : 2, true ? #[3] : #[4]]",
+    Value::tuple_from(vec![Value::Int(2), Value::tuple_from(vec![Value::Int(3)])])
+  );
+}
+
+#[test]
+fn nesting() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(
+    vm,
+    r#"fn sign(n) => n < 0 ? "negative" : n == 0 ? "zero" : "positive";"#
+  );
+  assert_eval!(
+    vm,
+    "#[sign(-2), sign(0), sign(2)]",
+    Value::tuple_from(vec![
+      Value::String("negative".to_string()),
+      Value::String("zero".to_string()),
+      Value::String("positive".to_string()),
+    ])
+  );
+  // the middle branch can be anything, including another conditional
+  assert_eval!(vm, "true ? false ? 1 : 2 : 3", Value::Int(2));
+  assert_eval!(vm, "let z = 0; z = true ? 5 : 6; z", Value::Int(5));
+}
+
+#[test]
+fn only_evaluates_one_branch() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    let calls = 0;
+    fn hit(value) {
+      calls += 1;
+      value
+    };
+    let a = true ? hit(1) : hit(2);
+    let b = false ? hit(3) : hit(4);
+    #[a, b, calls]
+    "#,
+    Value::tuple_from(vec![Value::Int(1), Value::Int(4), Value::Int(2)])
+  );
+}
@@ -0,0 +1,52 @@
+use bobascript::{
+  chunk::OpCode,
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::VM,
+};
+
+mod common;
+
+#[test]
+fn const_is_readable() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "const x = 3; x + 1", Value::Number(4.0));
+  assert_eval!(vm, "{ const y = 3; y * 2 }", Value::Number(6.0));
+}
+
+#[test]
+fn const_cannot_be_assigned() {
+  let result = compile("const x = 3; x = 4;");
+  assert!(matches!(result, Err(CompileError::AssignToConst { .. })));
+
+  let result = compile("{ const x = 3; x += 4; }");
+  assert!(matches!(result, Err(CompileError::AssignToConst { .. })));
+
+  let result = compile("const x = 3; fn f() { x = 4; };");
+  assert!(matches!(result, Err(CompileError::AssignToConst { .. })));
+}
+
+#[test]
+fn const_is_folded() {
+  let function = compile("const X = 10; X + 5").unwrap();
+  let code = &function.chunk.code;
+  // the const is still defined, but its use is a single constant
+  assert!(matches!(code[2].0, OpCode::Constant(_)));
+  assert!(matches!(code[3].0, OpCode::Return));
+  if let OpCode::Constant(idx) = code[2].0 {
+    assert!(Value::equal(
+      &function.chunk.constants[idx],
+      &Value::Number(15.0)
+    ));
+  }
+}
+
+#[test]
+fn let_is_not_folded() {
+  let function = compile("let x = 10; x + 5").unwrap();
+  assert!(function
+    .chunk
+    .code
+    .iter()
+    .any(|(opcode, _)| matches!(opcode, OpCode::Add)));
+}
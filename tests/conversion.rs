@@ -0,0 +1,105 @@
+use std::{collections::HashMap, convert::TryInto};
+
+use bobascript::{
+  compiler::compile,
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn record_to_map() {
+  let mut vm = VM::default();
+  let value = vm.interpret(compile("#{a: 1, b: 2}").unwrap()).unwrap();
+  let map: HashMap<String, Value> = value.try_into().unwrap();
+  assert_eq!(map.len(), 2);
+  assert!(Value::equal(&map["a"], &Value::Number(1.0)));
+  assert!(Value::equal(&map["b"], &Value::Number(2.0)));
+
+  let value: Value = map.into();
+  assert!(matches!(value, Value::Record(record) if record.len() == 2));
+}
+
+#[test]
+fn tuple_to_vec() {
+  let mut vm = VM::default();
+  let value = vm.interpret(compile("#[1, 2, 3]").unwrap()).unwrap();
+  let vec: Vec<Value> = value.try_into().unwrap();
+  assert_eq!(vec.len(), 3);
+  assert!(Value::equal(&vec[2], &Value::Number(3.0)));
+
+  let value: Value = vec.into();
+  let mut vm = VM::default();
+  let expected = vm.interpret(compile("#[1, 2, 3]").unwrap()).unwrap();
+  assert!(Value::equal(&value, &expected));
+}
+
+#[test]
+fn wrong_types() {
+  let result: Result<HashMap<String, Value>, _> = Value::Number(1.0).try_into();
+  assert!(matches!(result, Err(RuntimeError::TypeError { .. })));
+
+  let result: Result<Vec<Value>, _> = Value::Unit.try_into();
+  assert!(matches!(result, Err(RuntimeError::TypeError { .. })));
+}
+
+#[test]
+fn from_iterators() {
+  let mut vm = VM::default();
+
+  let tuple = Value::tuple_from((1..=3).map(|i| Value::Number(i as f64)));
+  let expected = vm.interpret(compile("#[1, 2, 3]").unwrap()).unwrap();
+  assert!(Value::equal(&tuple, &expected));
+
+  let record = Value::record_from(vec![
+    ("a".to_string(), Value::Number(1.0)),
+    ("b".to_string(), Value::Boolean(true)),
+  ]);
+  let expected = vm.interpret(compile("#{a: 1, b: true}").unwrap()).unwrap();
+  assert!(Value::equal(&record, &expected));
+  assert_eq!(record.to_string(), expected.to_string());
+}
+
+#[test]
+fn parse_number() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#"parse_number("3.14") == 3.14"#, Value::Boolean(true));
+  assert_eval!(vm, r#"parse_number("42")"#, Value::Int(42));
+  assert_eval!(vm, r#"parse_number(" -7\n")"#, Value::Int(-7));
+  assert_runtime_err!(
+    vm,
+    r#"parse_number("xyz")"#,
+    RuntimeError::ParseError("xyz".to_string())
+  );
+  assert_runtime_err!(
+    vm,
+    r#"parse_number("inf")"#,
+    RuntimeError::ParseError("inf".to_string())
+  );
+  assert_runtime_err!(
+    vm,
+    "parse_number(1)",
+    RuntimeError::TypeError {
+      expected: "string",
+      found: Value::Int(1),
+    }
+  );
+}
+
+#[test]
+fn to_string() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "to_string(#[1, 2])",
+    Value::String("#[1, 2]".to_string())
+  );
+  assert_eval!(vm, "to_string(1.5)", Value::String("1.5".to_string()));
+  assert_eval!(vm, r#"to_string("hi")"#, Value::String("hi".to_string()));
+  assert_eval!(
+    vm,
+    r#"parse_number(to_string(2.5)) * 2"#,
+    Value::Number(5.0)
+  );
+}
@@ -0,0 +1,38 @@
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+mod common;
+
+#[test]
+fn extra_semicolons() {
+  let mut vm = VM::default();
+  let function = compile(";;; let x = 1 ;;;").unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "x", Value::Number(1.0));
+  assert_eval!(vm, "{ ; let y = 2;; y }", Value::Number(2.0));
+}
+
+#[test]
+fn only_empty_statements() {
+  let mut vm = VM::default();
+  assert_eval!(vm, ";;;", Value::Unit);
+}
+
+#[test]
+fn empty_script() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "", Value::Unit);
+
+  let mut vm = VM::new();
+  assert_eval!(vm, "", Value::Unit);
+}
+
+#[test]
+fn minimal_scripts_run_repeatedly() {
+  // the VM checks (in debug builds) that each script leaves the stack balanced
+  let mut vm = VM::default();
+  for _ in 0..3 {
+    assert_eval!(vm, "1", Value::Int(1));
+    assert_eval!(vm, "", Value::Unit);
+  }
+}
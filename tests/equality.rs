@@ -0,0 +1,84 @@
+use bobascript::value::Value;
+
+fn nested_tuple(depth: usize) -> Value {
+  let mut value = Value::Number(1.0);
+  for _ in 0..depth {
+    value = Value::Tuple(vec![value].into());
+  }
+  value
+}
+
+#[test]
+fn deeply_nested_equality() {
+  let a = nested_tuple(5000);
+  let b = nested_tuple(5000);
+  assert!(a.equal(&b));
+
+  let c = nested_tuple(4999);
+  assert!(!a.equal(&c));
+}
+
+#[test]
+fn diff_scalars() {
+  assert_eq!(Value::Int(3).diff(&Value::Number(3.0)), None);
+  assert_eq!(
+    Value::Int(4).diff(&Value::Int(3)),
+    Some("value: expected 3, got 4".to_string())
+  );
+  assert_eq!(
+    Value::Boolean(true).diff(&Value::String("true".to_string())),
+    Some(r#"value: expected "true", got true"#.to_string())
+  );
+}
+
+#[test]
+fn diff_tuples() {
+  let expected = Value::Tuple(vec![Value::Int(1), Value::Int(2), Value::Int(3)].into());
+  let actual = Value::Tuple(vec![Value::Int(1), Value::Int(2), Value::Int(4)].into());
+  assert_eq!(
+    actual.diff(&expected),
+    Some("value[2]: expected 3, got 4".to_string())
+  );
+  assert_eq!(
+    Value::Tuple(vec![Value::Int(1)].into()).diff(&expected),
+    Some("value: expected a tuple of length 3, got one of length 1".to_string())
+  );
+  assert_eq!(nested_tuple(100).diff(&nested_tuple(100)), None);
+}
+
+#[test]
+fn diff_records() {
+  let expected = Value::record_from(vec![
+    ("x".to_string(), Value::Int(1)),
+    (
+      "y".to_string(),
+      Value::Tuple(vec![Value::Unit, Value::Int(2)].into()),
+    ),
+  ]);
+  let actual = Value::record_from(vec![
+    ("x".to_string(), Value::Int(1)),
+    (
+      "y".to_string(),
+      Value::Tuple(vec![Value::Unit, Value::Int(5)].into()),
+    ),
+  ]);
+  assert_eq!(
+    actual.diff(&expected),
+    Some("value.y[1]: expected 2, got 5".to_string())
+  );
+
+  let missing = Value::record_from(vec![("y".to_string(), Value::Unit)]);
+  assert_eq!(
+    missing.diff(&expected),
+    Some("value.x: missing from record".to_string())
+  );
+  let extra = Value::record_from(vec![
+    ("x".to_string(), Value::Int(1)),
+    ("y".to_string(), Value::Unit),
+    ("z".to_string(), Value::Unit),
+  ]);
+  assert_eq!(
+    extra.diff(&expected),
+    Some("value.z: not expected in record".to_string())
+  );
+}
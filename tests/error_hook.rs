@@ -0,0 +1,73 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bobascript::{
+  compiler::compile,
+  vm::{RuntimeError, VM},
+};
+
+#[test]
+fn hook_sees_error_and_frames() {
+  let seen = Rc::new(RefCell::new(None));
+
+  let mut vm = VM::default();
+  let hook_seen = Rc::clone(&seen);
+  vm.add_error_hook(Box::new(move |error, state| {
+    let names: Vec<String> = state
+      .frames()
+      .iter()
+      .map(|frame| frame.function().name.clone())
+      .collect();
+    let is_type_error = matches!(error, RuntimeError::TypeError { .. });
+    *hook_seen.borrow_mut() = Some((is_type_error, names));
+  }));
+
+  let function = compile(
+    r#"
+    fn oops() => 1 - true;
+    oops();
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_err());
+
+  let (is_type_error, frames) = seen.borrow_mut().take().unwrap();
+  assert!(is_type_error);
+  assert_eq!(frames, vec!["".to_string(), "oops".to_string()]);
+}
+
+#[test]
+fn hook_not_called_on_success() {
+  let called = Rc::new(RefCell::new(false));
+
+  let mut vm = VM::default();
+  let hook_called = Rc::clone(&called);
+  vm.add_error_hook(Box::new(move |_, _| *hook_called.borrow_mut() = true));
+
+  let result = vm.interpret(compile("1 + 2").unwrap());
+  assert!(result.is_ok());
+  assert!(!*called.borrow());
+}
+
+#[test]
+fn script_is_in_slot_zero() {
+  let seen = Rc::new(RefCell::new(None));
+
+  let mut vm = VM::default();
+  let hook_seen = Rc::clone(&seen);
+  vm.add_error_hook(Box::new(move |_, state| {
+    let stack: Vec<String> = state
+      .stack()
+      .iter()
+      .map(|value| value.to_string())
+      .collect();
+    *hook_seen.borrow_mut() = Some((state.frames().len(), stack));
+  }));
+
+  let function = compile("1 - true").unwrap();
+  assert!(vm.interpret(function).is_err());
+
+  let (frames, stack) = seen.borrow_mut().take().unwrap();
+  assert_eq!(frames, 1);
+  assert_eq!(stack, vec!["<script>"]);
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, convert::TryInto};
+
+use bobascript::{compiler::compile, value::Value, vm::VM, InterpretError};
+
+mod common;
+
+#[test]
+fn error_value_describes_error() {
+  let mut vm = VM::default();
+  let function = compile("let a = 1;\na - true").unwrap();
+  let (error, line) = match vm.interpret(function) {
+    Err(InterpretError::RuntimeError { error, line }) => (error, line),
+    result => panic!("expected a runtime error, got {:?}", result),
+  };
+
+  let fields: HashMap<String, Value> = error.to_value(line).try_into().unwrap();
+  assert!(Value::equal(
+    &fields["kind"],
+    &Value::String("TypeError".to_string())
+  ));
+  assert!(Value::equal(
+    &fields["message"],
+    &Value::String(error.to_string())
+  ));
+  assert!(Value::equal(&fields["line"], &Value::Number(2.0)));
+}
+
+#[test]
+fn caught_error_value() {
+  let mut vm = VM::default();
+  let value = assert_runtime_ok!(
+    vm,
+    r#"
+    let zero = 0;
+    try {
+      1 / zero
+    } catch err {
+      err
+    }
+    "#
+  );
+  let fields: HashMap<String, Value> = value.try_into().unwrap();
+  assert!(Value::equal(
+    &fields["kind"],
+    &Value::String("DivisionByZero".to_string())
+  ));
+  assert!(Value::equal(
+    &fields["message"],
+    &Value::String("Cannot divide by zero.".to_string())
+  ));
+  assert!(Value::equal(&fields["line"], &Value::Number(4.0)));
+
+  assert_eval!(
+    vm,
+    r#"try { 1 / zero } catch err { err.kind == "DivisionByZero" }"#,
+    Value::Boolean(true)
+  );
+}
@@ -1,7 +1,8 @@
 use std::{cell::RefCell, convert::TryInto, rc::Rc};
 
 use bobascript::{
-  compiler::compile,
+  chunk::OpCode,
+  compiler::{compile, CompileError},
   value::{NativeFunction, Value},
   vm::{RuntimeError, VM},
 };
@@ -19,6 +20,53 @@ fn body_must_be_block() {
   // assert_compile_err!(result, CompileError::Expected("block after parameters"));
 }
 
+#[test]
+fn arrow_body() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    fn double(x) => x * 2;
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "double(21)", Value::Number(42.0));
+}
+
+#[test]
+fn cannot_assign_to_function() {
+  let result = compile(
+    r#"
+    fn f() {};
+    f = 5;
+    "#,
+  );
+  assert!(result.is_err());
+  assert!(matches!(result, Err(CompileError::AssignToFunction { .. })));
+
+  let result = compile(
+    r#"
+    {
+      fn f() {};
+      f += 5;
+    };
+    "#,
+  );
+  assert!(result.is_err());
+  assert!(matches!(result, Err(CompileError::AssignToFunction { .. })));
+
+  // redeclaring the name as a variable makes it assignable again
+  let result = compile(
+    r#"
+    fn f() {};
+    let f = 3;
+    f = 5;
+    "#,
+  );
+  assert!(result.is_ok());
+}
+
 #[test]
 fn empty_body() {
   let mut vm = VM::default();
@@ -72,7 +120,221 @@ fn native_fns_work() {
   let mut vm = VM::default();
   vm.define_native(
     "test".to_owned(),
-    Rc::new(RefCell::new(NativeFunction { function: test })),
+    Rc::new(RefCell::new(NativeFunction::new(test))),
   );
   assert_eval!(vm, "test(10, 20)", Value::String("1020".to_string()));
 }
+
+#[test]
+fn native_closure() {
+  let counter = Rc::new(RefCell::new(0));
+  let captured = Rc::clone(&counter);
+
+  let mut vm = VM::default();
+  vm.define_native(
+    "tick".to_owned(),
+    Rc::new(RefCell::new(NativeFunction::new(
+      move |params: &[Value]| {
+        let by: i64 = match params.first() {
+          Some(value) => value.clone().try_into()?,
+          None => 1,
+        };
+        *captured.borrow_mut() += by as i32;
+        Ok(Value::Int(*captured.borrow() as i64))
+      },
+    ))),
+  );
+  assert_eval!(vm, "tick()", Value::Int(1));
+  assert_eval!(vm, "tick(); tick(5)", Value::Int(7));
+  assert_eq!(*counter.borrow(), 7);
+
+  // plain functions still work, too
+  fn zero(_: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Int(0))
+  }
+  let zero: fn(&[Value]) -> Result<Value, RuntimeError> = zero;
+  vm.define_native("zero".to_owned(), Rc::new(RefCell::new(zero.into())));
+  assert_eval!(vm, "zero()", Value::Int(0));
+}
+
+#[test]
+fn return_from_if() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    fn sign(n) {
+      if n < 0 {
+        return -1;
+      };
+      1
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "sign(-5)", Value::Number(-1.0));
+  assert_eval!(vm, "sign(5)", Value::Number(1.0));
+}
+
+#[test]
+fn bare_and_value_returns() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    fn bare() {
+      let x = 5;
+      return;
+    };
+    fn fallthrough() { 5 };
+    fn explicit() { return 5; };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "bare()", Value::Unit);
+  assert_eval!(vm, "fallthrough()", Value::Number(5.0));
+  assert_eval!(vm, "explicit()", Value::Number(5.0));
+}
+
+#[test]
+fn bare_return_is_return_unit() {
+  let function = compile("fn f() { return; };").unwrap();
+  let f = function
+    .chunk
+    .constants
+    .iter()
+    .find_map(|constant| match constant {
+      Value::Function(f) => Some(f.clone()),
+      _ => None,
+    })
+    .unwrap();
+  assert!(f
+    .chunk
+    .code
+    .iter()
+    .any(|(opcode, _)| matches!(opcode, OpCode::ReturnUnit)));
+}
+
+#[test]
+fn native_result_is_used() {
+  fn add(params: &[Value]) -> Result<Value, RuntimeError> {
+    let a: f64 = params[0].clone().try_into()?;
+    let b: f64 = params[1].clone().try_into()?;
+    Ok(Value::Number(a + b))
+  }
+
+  let mut vm = VM::default();
+  vm.define_native(
+    "add".to_owned(),
+    Rc::new(RefCell::new(NativeFunction::new(add))),
+  );
+  assert_eval!(vm, "add(1, 2) + 1", Value::Number(4.0));
+}
+
+#[test]
+fn functions_display_their_names() {
+  let mut vm = VM::default();
+  let function = compile("fn foo() {}; foo").unwrap();
+  let value = vm.interpret(function).unwrap();
+  assert_eq!(value.to_string(), "<fn foo>");
+
+  let function = compile("fn bar() { fn baz() {}; baz }; bar()").unwrap();
+  let value = vm.interpret(function).unwrap();
+  assert_eq!(value.to_string(), "<fn baz>");
+
+  let function = compile("").unwrap();
+  assert_eq!(Value::Function(function).to_string(), "<script>");
+}
+
+#[test]
+fn pure_functions() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    pure fn square(x) => x * x;
+    pure fn sum_of_squares(a, b) => square(a) + square(b);
+    pure fn factorial(n) => if n <= 1 { 1 } else { n * factorial(n - 1) };
+    sum_of_squares(3, 4) + factorial(3)
+    "#,
+    Value::Int(31)
+  );
+}
+
+#[test]
+fn pure_functions_cannot_have_effects() {
+  assert!(matches!(
+    compile(r#"pure fn greet(name) { log("hi", name); };"#),
+    Err(CompileError::ImpureFunction(name)) if name == "greet"
+  ));
+  assert!(matches!(
+    compile("fn double(x) => x * 2; pure fn quadruple(x) => double(double(x));"),
+    Err(CompileError::ImpureFunction(_))
+  ));
+  assert!(matches!(
+    compile("pure fn apply(f, x) => f(x);"),
+    Err(CompileError::ImpureFunction(_))
+  ));
+}
+
+#[test]
+fn anonymous_functions() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let double = fn(x) => x * 2; double(21)",
+    Value::Int(42)
+  );
+  assert_eval!(
+    vm,
+    r#"
+    fn apply(f, x) => f(x);
+    apply(fn(x) { x + 1 }, 2)
+    "#,
+    Value::Int(3)
+  );
+  assert_eval!(vm, "(fn(a, b) => a - b)(5, 3)", Value::Int(2));
+
+  let value = assert_runtime_ok!(vm, "fn(x) => x");
+  assert_eq!(value.to_string(), "<fn anonymous>");
+}
+
+#[test]
+fn tail_calls_reuse_frames() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn countdown(n) => if n == 0 { "done" } else { countdown(n - 1) };
+    countdown(10000)
+    "#,
+    Value::String("done".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#"
+    fn is_even(n) => if n == 0 { true } else { is_odd(n - 1) };
+    fn is_odd(n) => if n == 0 { false } else { is_even(n - 1) };
+    is_even(10001)
+    "#,
+    Value::Boolean(false)
+  );
+  // each closure keeps the `n` it captured, even once its frame has been reused
+  assert_eval!(
+    vm,
+    r#"
+    fn make(n, fns) => if n == 0 { fns } else { make(n - 1, #[...fns, fn() => n]) };
+    let fns = make(3, #[]);
+    #[fns[0](), fns[1](), fns[2]()]
+    "#,
+    Value::tuple_from(vec![Value::Int(3), Value::Int(2), Value::Int(1)])
+  );
+  // calls that aren't returned straight away still use a frame each
+  assert_runtime_err!(
+    vm,
+    "fn sum(n) => if n == 0 { 0 } else { n + sum(n - 1) }; sum(10000)",
+    RuntimeError::StackOverflow
+  );
+}
@@ -0,0 +1,44 @@
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+#[test]
+fn returns_defined_globals() {
+  let mut vm = VM::default();
+  let function = compile(r#"let port = 8080; let host = "localhost"; port + 1"#).unwrap();
+  let (result, globals) = vm.interpret_with_globals(function).unwrap();
+  assert!(Value::equal(&result, &Value::Number(8081.0)));
+  assert!(Value::equal(&globals["port"], &Value::Number(8080.0)));
+  assert!(Value::equal(
+    &globals["host"],
+    &Value::String("localhost".to_string())
+  ));
+
+  // the snapshot doesn't change as the VM keeps running
+  let function = compile("let port = 1;").unwrap();
+  assert!(vm.interpret(function).is_ok());
+  assert!(Value::equal(&globals["port"], &Value::Number(8080.0)));
+}
+
+#[test]
+fn errors_are_returned() {
+  let mut vm = VM::default();
+  let function = compile("let a = 1; let b = a - true;").unwrap();
+  assert!(vm.interpret_with_globals(function).is_err());
+}
+
+#[test]
+fn get_and_set_globals() {
+  let mut vm = VM::default();
+  let function = compile("let answer = 42;").unwrap();
+  assert!(vm.interpret(function).is_ok());
+  assert!(matches!(vm.get_global("answer"), Some(Value::Int(42))));
+  assert!(vm.get_global("question").is_none());
+
+  // setting a global that doesn't exist yet defines it
+  vm.set_global("question", Value::String("six times nine".to_string()));
+  vm.set_global("answer", Value::Int(54));
+  let function = compile("question + \" is \" + answer").unwrap();
+  assert!(matches!(
+    vm.interpret(function),
+    Ok(Value::String(string)) if string == "six times nine is 54"
+  ));
+}
@@ -0,0 +1,57 @@
+use bobascript::{
+  compiler::compile,
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn heap_estimate_grows() {
+  let mut vm = VM::default();
+  let before = vm.heap_estimate();
+  let function = compile(r#"let s = "a string of some length";"#).unwrap();
+  assert!(vm.interpret(function).is_ok());
+  assert!(vm.heap_estimate() >= before + "a string of some length".len());
+}
+
+#[test]
+fn large_tuple_runs_out_of_memory() {
+  let tuple = format!("#[{}]", vec!["1"; 200].join(", "));
+
+  let mut vm = VM::default();
+  vm.set_max_heap(vm.heap_estimate() + 1024);
+  assert_eval!(
+    vm,
+    "#[1, 2, 3]",
+    Value::Tuple(vec![Value::Int(1), Value::Int(2), Value::Int(3)].into())
+  );
+  assert_runtime_err!(vm, &tuple, RuntimeError::OutOfMemory);
+  assert_runtime_err!(vm, r#""abc" * 1000"#, RuntimeError::OutOfMemory);
+
+  // without a limit, the same tuple is fine
+  let mut vm = VM::default();
+  let function = compile(&tuple).unwrap();
+  assert!(vm.interpret(function).is_ok());
+}
+
+#[test]
+fn dropped_values_free_up_the_heap() {
+  let mut vm = VM::default();
+  vm.set_max_heap(vm.heap_estimate() + 4096);
+  // each string is dropped before the next is made, so this never holds more than a couple at once
+  assert_runtime_ok!(
+    vm,
+    r#"let i = 0; while i < 1000 { let s = "x" * 1000; i = i + 1; };"#
+  );
+  // while keeping every one of them doesn't fit
+  assert_runtime_err!(
+    vm,
+    r#"let kept = #[]; let i = 0; while i < 1000 { kept = #[...kept, "x" * 1000]; i = i + 1; };"#,
+    RuntimeError::OutOfMemory
+  );
+
+  // a global the host sets counts as soon as the script runs
+  vm.set_global("big", Value::String("x".repeat(8192)));
+  assert_runtime_err!(vm, r#""abc" * 2"#, RuntimeError::OutOfMemory);
+}
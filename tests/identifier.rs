@@ -0,0 +1,68 @@
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::VM,
+};
+
+mod common;
+
+#[test]
+fn unicode_identifiers() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "let π = 3.5; π * 2", Value::Number(7.0));
+  assert_eval!(vm, "let café = 1; café + 1", Value::Number(2.0));
+  assert_eval!(vm, "fn 倍(x) => x * 2; 倍(4)", Value::Number(8.0));
+  assert_eval!(
+    vm,
+    "'외부: while true { break '외부 5; }",
+    Value::Number(5.0)
+  );
+}
+
+#[test]
+fn identifiers_cannot_start_with_a_digit() {
+  assert!(matches!(
+    compile("let 1a = 1;"),
+    Err(CompileError::SyntaxError(_))
+  ));
+}
+
+#[test]
+fn nul_is_an_error() {
+  // a NUL byte is an invalid token, rather than the end of the source
+  assert!(matches!(
+    compile("let a = 1;\0let b = 2;"),
+    Err(CompileError::SyntaxError(_))
+  ));
+  assert!(compile("let a = 1;\0").is_err());
+}
+
+#[test]
+fn underscore_discards() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(vm, "let _ = 5;");
+  assert!(vm.get_global("_").is_none());
+
+  assert_eval!(
+    vm,
+    "{ let a = 1; let _ = 2; let _ = 3; const _ = 4; a }",
+    Value::Int(1)
+  );
+  assert_eval!(
+    vm,
+    "fn second(_, b, _) => b; second(1, 2, 3)",
+    Value::Int(2)
+  );
+}
+
+#[test]
+fn underscore_cannot_be_read() {
+  assert!(matches!(
+    compile("let _ = 5; _ + 1"),
+    Err(CompileError::ReadDiscard)
+  ));
+  assert!(matches!(
+    compile("fn f(_) => _;"),
+    Err(CompileError::ReadDiscard)
+  ));
+}
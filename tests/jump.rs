@@ -0,0 +1,78 @@
+use bobascript::{
+  chunk::{JumpDirection, OpCode},
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::VM,
+};
+
+mod common;
+
+#[test]
+fn if_jumps_forwards() {
+  let function = compile("if true { 1 } else { 2 }").unwrap();
+  let code = &function.chunk.code;
+
+  // the condition jumps past the "true" branch to the pop before the "false" branch
+  assert!(matches!(code[1].0, OpCode::JumpIfFalse(3)));
+  assert!(matches!(code[5].0, OpCode::Pop));
+  // the "true" branch jumps past the "false" branch to the return
+  assert!(matches!(
+    code[4].0,
+    OpCode::Jump(JumpDirection::Forwards, 2)
+  ));
+  assert!(matches!(code[7].0, OpCode::Return));
+
+  let mut vm = VM::default();
+  assert_eval!(vm, "if true { 1 } else { 2 }", Value::Number(1.0));
+  assert_eval!(vm, "if false { 1 } else { 2 }", Value::Number(2.0));
+}
+
+#[test]
+fn while_jumps_backwards() {
+  let function = compile("while false {}").unwrap();
+  let code = &function.chunk.code;
+
+  // the end of the body loops back around to the condition at the start
+  assert!(matches!(code[1].0, OpCode::False));
+  assert!(matches!(
+    code[4].0,
+    OpCode::Jump(JumpDirection::Backwards, 4)
+  ));
+  // the condition exits the loop to the pop right after the loop
+  assert!(matches!(code[2].0, OpCode::JumpIfFalse(2)));
+  assert!(matches!(code[5].0, OpCode::Pop));
+
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let i = 0;
+    while i < 5 {
+      i += 1;
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "i", Value::Number(5.0));
+}
+
+#[test]
+fn large_loop_body() {
+  let mut vm = VM::default();
+  let function = compile(format!(
+    "let i = 0; while i < 1 {{ {} }};",
+    "i += 1;".repeat(1000)
+  ))
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "i", Value::Number(1000.0));
+
+  let result = compile(format!(
+    "let i = 0; while i < 1 {{ {} }};",
+    "i += 1;".repeat(20000)
+  ));
+  assert!(result.is_err());
+  assert_compile_err!(result, CompileError::JumpTooLarge);
+}
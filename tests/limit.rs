@@ -0,0 +1,86 @@
+use bobascript::{
+  compiler::{compile, compile_with_limit, CompileError},
+  value::Value,
+  vm::{RuntimeError, VM},
+  InterpretError,
+};
+
+mod common;
+
+#[test]
+fn program_too_large() {
+  let source = "let i = 0;".to_string() + &"i += 1;".repeat(100);
+
+  let result = compile_with_limit(source.clone(), 1000);
+  assert!(result.is_ok());
+
+  let result = compile_with_limit(source, 50);
+  assert_compile_err!(result, CompileError::ProgramTooLarge);
+}
+
+#[test]
+fn max_call_depth() {
+  let source = "fn depth(n) => if n == 0 { 0 } else { depth(n - 1) + 1 }; depth(100)";
+
+  let mut vm = VM::default();
+  assert_runtime_err!(vm, source, RuntimeError::StackOverflow);
+
+  vm.set_max_call_depth(8);
+  assert_eval!(vm, "depth(6)", Value::Int(6));
+  assert_runtime_err!(vm, "depth(7)", RuntimeError::StackOverflow);
+
+  vm.set_max_call_depth(128);
+  assert_eval!(vm, source, Value::Int(100));
+}
+
+#[test]
+fn instruction_limit() {
+  let mut vm = VM::default();
+  vm.set_instruction_limit(Some(10_000));
+  assert_runtime_err!(vm, "while true {};", RuntimeError::InstructionLimitExceeded);
+
+  // the limit is per script, rather than shared between them
+  assert_eval!(
+    vm,
+    "let i = 0; while i < 100 { i += 1; }; i",
+    Value::Int(100)
+  );
+  assert_eval!(
+    vm,
+    "let j = 0; while j < 100 { j += 1; }; j",
+    Value::Int(100)
+  );
+
+  vm.set_instruction_limit(None);
+  assert_eval!(
+    vm,
+    "let k = 0; while k < 10000 { k += 1; }; k",
+    Value::Int(10000)
+  );
+}
+
+#[test]
+fn instruction_limit_per_call() {
+  let mut vm = VM::default();
+  let function = vm
+    .interpret(compile("fn count(n) { let i = 0; while i < n { i += 1; }; i }; count").unwrap())
+    .unwrap();
+
+  // the script used up some of its budget, but each call gets a fresh one
+  vm.set_instruction_limit(Some(2_000));
+  for _ in 0..10 {
+    let result = vm
+      .call_function(function.clone(), vec![Value::Int(100)])
+      .unwrap();
+    assert!(Value::equal(&result, &Value::Int(100)));
+  }
+
+  let result = vm.call_function(function, vec![Value::Int(10_000)]);
+  assert!(matches!(
+    result,
+    Err(InterpretError::RuntimeError {
+      error: RuntimeError::InstructionLimitExceeded,
+      ..
+    })
+  ));
+}
@@ -0,0 +1,61 @@
+use bobascript::{
+  compiler::{compile, CompileError},
+  vm::{RuntimeError, VM},
+  InterpretError,
+};
+
+#[test]
+fn type_error_reports_line() {
+  let mut vm = VM::default();
+  let function = compile("let a = 1;\n\nlet b = a - true;\n").unwrap();
+  let result = vm.interpret(function);
+  assert!(matches!(
+    result,
+    Err(InterpretError::RuntimeError {
+      error: RuntimeError::TypeError { .. },
+      line: 3,
+    })
+  ));
+}
+
+#[test]
+fn error_in_function_reports_its_line() {
+  let mut vm = VM::default();
+  let function = compile("fn oops() {\n  1 - true\n};\noops();").unwrap();
+  let error = vm.interpret(function).unwrap_err();
+  assert!(matches!(
+    error,
+    InterpretError::RuntimeError { line: 2, .. }
+  ));
+  assert!(error.to_string().contains("on line 2"));
+}
+
+#[test]
+fn compile_error_reports_line_and_col() {
+  let result = compile("let a = 1;\nfn f() {\n  let b = 2;\n  a + b = 3;\n};\n");
+  let error = result.unwrap_err();
+  assert!(matches!(
+    error,
+    CompileError::InvalidAssignmentTarget {
+      line: 4,
+      col: 3,
+      ..
+    }
+  ));
+
+  let error = InterpretError::from(error);
+  assert!(error.to_string().contains("line 4, col 3"));
+}
+
+#[test]
+fn uninitialized_local_reports_line_and_col() {
+  let result = compile("{\n  let x = 1;\n  { let x = x; };\n};");
+  assert!(matches!(
+    result,
+    Err(CompileError::VariableDoesNotExist {
+      line: 3,
+      col: 5,
+      ..
+    })
+  ));
+}
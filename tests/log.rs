@@ -0,0 +1,55 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+mod common;
+
+/// Creates a VM whose logs are each collected into the returned list.
+fn logging_vm() -> (VM, Rc<RefCell<Vec<Value>>>) {
+  let logs = Rc::new(RefCell::new(Vec::new()));
+  let mut vm = VM::default();
+  let handler_logs = Rc::clone(&logs);
+  vm.add_log_handler(Box::new(move |value| {
+    handler_logs.borrow_mut().push(value);
+  }));
+  (vm, logs)
+}
+
+#[test]
+fn single_value_log() {
+  let (mut vm, logs) = logging_vm();
+  assert_eval!(
+    vm,
+    "log(#[1, 2])",
+    Value::from(vec![Value::Number(1.0), Value::Number(2.0)])
+  );
+  assert_eval!(vm, r#"log("hi")"#, Value::String("hi".to_string()));
+
+  let logs = logs.borrow();
+  assert_eq!(logs.len(), 2);
+  assert!(matches!(&logs[0], Value::Tuple(_)));
+  assert!(Value::equal(&logs[1], &Value::String("hi".to_string())));
+}
+
+#[test]
+fn multi_value_log() {
+  let (mut vm, logs) = logging_vm();
+  let function = compile(r#"let x = 5; log("x =", x, true, #[1, "a"]);"#).unwrap();
+  assert!(vm.interpret(function).is_ok());
+  assert_eval!(vm, "log(1, 2)", Value::String("1 2".to_string()));
+
+  // the handler is called once per log, with every value combined
+  let logs = logs.borrow();
+  assert_eq!(logs.len(), 2);
+  assert!(Value::equal(
+    &logs[0],
+    &Value::String(r#"x = 5 true #[1, "a"]"#.to_string())
+  ));
+}
+
+#[test]
+fn empty_log() {
+  let (mut vm, logs) = logging_vm();
+  assert_eval!(vm, "log()", Value::String("".to_string()));
+  assert_eq!(logs.borrow().len(), 1);
+}
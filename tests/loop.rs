@@ -0,0 +1,174 @@
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::VM,
+};
+
+mod common;
+
+#[test]
+fn break_from_if() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let i = 0;
+    while true {
+      i += 1;
+      if i == 3 {
+        break;
+      };
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "i", Value::Number(3.0));
+}
+
+#[test]
+fn continue_skips_iteration() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let i = 0;
+    let sum = 0;
+    while i < 5 {
+      i += 1;
+      let _skip = i == 2;
+      if _skip {
+        continue;
+      };
+      sum += i;
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "sum", Value::Number(13.0));
+}
+
+#[test]
+fn labeled_break() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let outer = 0;
+    let inner = 0;
+    'outer: while true {
+      outer += 1;
+      while true {
+        inner += 1;
+        break 'outer;
+      };
+      outer += 100;
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "outer", Value::Number(1.0));
+  assert_eval!(vm, "inner", Value::Number(1.0));
+}
+
+#[test]
+fn labeled_continue() {
+  let mut vm = VM::default();
+  let function = compile(
+    r#"
+    let count = 0;
+    let i = 0;
+    'outer: while i < 3 {
+      i += 1;
+      let j = 0;
+      while true {
+        j += 1;
+        count += 1;
+        if j == 2 {
+          continue 'outer;
+        };
+      };
+    };
+    "#,
+  )
+  .unwrap();
+  let result = vm.interpret(function);
+  assert!(result.is_ok());
+  assert_eval!(vm, "count", Value::Number(6.0));
+}
+
+#[test]
+fn break_with_value() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "while true { break 5; }", Value::Number(5.0));
+  assert_eval!(vm, "while true { break; }", Value::Unit);
+  assert_eval!(
+    vm,
+    "let i = 0; while true { i += 1; if i == 3 { let doubled = i * 2; break doubled; }; }",
+    Value::Number(6.0)
+  );
+}
+
+#[test]
+fn labeled_break_with_value() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "'outer: while true { while true { break 'outer 7; }; }",
+    Value::Number(7.0)
+  );
+}
+
+#[test]
+fn undefined_label() {
+  let result = compile("while true { break 'nope; };");
+  assert_compile_err!(result, CompileError::UndefinedLabel(String::new()));
+}
+
+#[test]
+fn break_outside_loop() {
+  let result = compile("break;");
+  assert_compile_err!(result, CompileError::BreakOutsideLoop);
+}
+
+#[test]
+fn collect_while() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let i = 0; collect while i < 5 { i += 1; i * i }",
+    Value::Tuple(
+      vec![
+        Value::Int(1),
+        Value::Int(4),
+        Value::Int(9),
+        Value::Int(16),
+        Value::Int(25),
+      ]
+      .into()
+    )
+  );
+  assert_eval!(vm, "collect while false { 1 }", Value::Tuple(vec![].into()));
+
+  // a bare `break` keeps what has been collected, while `continue` skips an iteration
+  assert_eval!(
+    vm,
+    r#"
+    let n = 0;
+    collect while true {
+      n += 1;
+      if n == 2 { continue; };
+      if n > 3 { break; };
+      n
+    }
+    "#,
+    Value::Tuple(vec![Value::Int(1), Value::Int(3)].into())
+  );
+  assert_eval!(
+    vm,
+    "'outer: collect while true { break 'outer 5; }",
+    Value::Int(5)
+  );
+}
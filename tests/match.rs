@@ -0,0 +1,78 @@
+use bobascript::{
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn match_numbers() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"match 2 { 1 => "one", 2 => "two", _ => "many" }"#,
+    Value::String("two".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#"match 7 { 1 => "one", 2 => "two", _ => "many" }"#,
+    Value::String("many".to_string())
+  );
+}
+
+#[test]
+fn match_binding() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "match 20 { 1 => 0, n => n + 1 }", Value::Number(21.0));
+}
+
+#[test]
+fn match_tuples() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "match #[1, 2] { #[a, b] => a + b, _ => 0 }",
+    Value::Number(3.0)
+  );
+  assert_eval!(
+    vm,
+    "match #[1, 2, 3] { #[a, b] => a + b, _ => 0 }",
+    Value::Number(0.0)
+  );
+  assert_eval!(
+    vm,
+    "match #[0, #[5, 6]] { #[1, _] => 1, #[0, #[x, 6]] => x, _ => 2 }",
+    Value::Number(5.0)
+  );
+  assert_eval!(vm, "match 3 { #[a] => a, _ => 4 }", Value::Number(4.0));
+}
+
+#[test]
+fn match_inside_function() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn describe(point) {
+      let prefix = "at ";
+      match point {
+        #[0, 0] => prefix + "origin",
+        #[_, 0] => prefix + "x axis",
+        _ => prefix + "somewhere",
+      }
+    };
+    describe(#[3, 0])
+    "#,
+    Value::String("at x axis".to_string())
+  );
+}
+
+#[test]
+fn non_exhaustive_match() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "match 3 { 1 => 1, 2 => 2 }",
+    RuntimeError::NonExhaustiveMatch
+  );
+}
@@ -0,0 +1,70 @@
+use bobascript::{
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+fn math_vm() -> VM {
+  let mut vm = VM::default();
+  vm.register_math();
+  vm
+}
+
+#[test]
+fn not_defined_by_default() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "sqrt(9)",
+    RuntimeError::UndefinedVariable(String::new())
+  );
+}
+
+#[test]
+fn sqrt_and_pow() {
+  let mut vm = math_vm();
+  assert_eval!(vm, "sqrt(9)", Value::Number(3.0));
+  assert_eval!(vm, "sqrt(2.25)", Value::Number(1.5));
+  assert_eval!(vm, "pow(2, 10)", Value::Int(1024));
+  assert_eval!(vm, "pow(4, 0.5)", Value::Number(2.0));
+  assert_eval!(vm, "pow(2, -1)", Value::Number(0.5));
+}
+
+#[test]
+fn rounding() {
+  let mut vm = math_vm();
+  assert_eval!(vm, "abs(-3)", Value::Int(3));
+  assert_eval!(vm, "abs(-2.5)", Value::Number(2.5));
+  assert_eval!(vm, "floor(2.7)", Value::Number(2.0));
+  assert_eval!(vm, "floor(-2.2)", Value::Number(-3.0));
+  assert_eval!(vm, "ceil(2.2)", Value::Number(3.0));
+  assert_eval!(vm, "round(2.5)", Value::Number(3.0));
+  assert_eval!(vm, "round(-2.5)", Value::Number(-3.0));
+  assert_eval!(vm, "floor(7)", Value::Int(7));
+}
+
+#[test]
+fn min_and_max() {
+  let mut vm = math_vm();
+  assert_eval!(vm, "min(3, 1, 2)", Value::Int(1));
+  assert_eval!(vm, "max(3, 1, 2)", Value::Int(3));
+  assert_eval!(vm, "min(3)", Value::Int(3));
+  assert_eval!(vm, "max(1, 2.5, 2)", Value::Number(2.5));
+  assert_eval!(vm, "min(1, -0.5)", Value::Number(-0.5));
+  assert_runtime_err!(vm, "min()", RuntimeError::IncorrectParameterCount(1, 0));
+}
+
+#[test]
+fn arguments_are_checked() {
+  let mut vm = math_vm();
+  let type_error = RuntimeError::TypeError {
+    expected: "number",
+    found: Value::Unit,
+  };
+  assert_runtime_err!(vm, r#"sqrt("9")"#, type_error);
+  assert_runtime_err!(vm, r#"max(1, "2")"#, type_error);
+  assert_runtime_err!(vm, "pow(2, #[])", type_error);
+  assert_runtime_err!(vm, "abs(1, 2)", RuntimeError::IncorrectParameterCount(1, 2));
+  assert_runtime_err!(vm, "pow(2)", RuntimeError::IncorrectParameterCount(2, 1));
+}
@@ -0,0 +1,149 @@
+use std::{cell::RefCell, convert::TryInto, rc::Rc};
+
+use bobascript::{
+  compiler::compile,
+  stdlib::StdlibRegistry,
+  value::{NumberFormat, Value},
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn zero_equality() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "0 == -0", Value::Boolean(true));
+  assert_eval!(vm, "0.1 + 0.2 == 0.3", Value::Boolean(false));
+}
+
+#[test]
+fn nan_equality() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let nan = (-1) ^ 0.5; nan == nan",
+    Value::Boolean(false)
+  );
+  assert_eval!(vm, "let nan = (-1) ^ 0.5; nan != nan", Value::Boolean(true));
+}
+
+#[test]
+fn divide_by_zero() {
+  let mut vm = VM::default();
+  assert_runtime_err!(vm, "1 / 0", RuntimeError::DivideByZero);
+  assert_runtime_err!(vm, "0 / 0", RuntimeError::DivideByZero);
+  assert_runtime_err!(vm, "let a = 10; a /= 0;", RuntimeError::DivideByZero);
+  assert_runtime_err!(vm, "10 % 0", RuntimeError::DivideByZero);
+  assert_eval!(vm, "1.0 / 4", Value::Number(0.25));
+}
+
+#[test]
+fn modulo() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "10 % 3", Value::Number(1.0));
+  assert_eval!(vm, "let a = 7.5; a % 2", Value::Number(1.5));
+  assert_eval!(vm, "-7 % 3", Value::Number(-1.0));
+  assert_eval!(vm, "2 + 9 % 4 * 3", Value::Number(5.0));
+}
+
+#[test]
+fn integers() {
+  let mut vm = VM::default();
+  let result = vm.interpret(compile("3 + 4").unwrap());
+  assert!(matches!(result, Ok(Value::Int(7))));
+  let result = vm.interpret(compile("3 + 4.0").unwrap());
+  assert!(matches!(result, Ok(Value::Number(n)) if n == 7.0));
+  let result = vm.interpret(compile("let a = 7; a / 2").unwrap());
+  assert!(matches!(result, Ok(Value::Int(3))));
+  let result = vm.interpret(compile("let a = 2; a ^ 10 - -a").unwrap());
+  assert!(matches!(result, Ok(Value::Int(1026))));
+  let result = vm.interpret(compile("let a = 2; a ^ -1").unwrap());
+  assert!(matches!(result, Ok(Value::Number(n)) if n == 0.5));
+
+  // integers that would overflow become floats instead
+  let result = vm.interpret(compile("let a = 9223372036854775807; a + 1").unwrap());
+  assert!(matches!(result, Ok(Value::Number(_))));
+  assert_eval!(vm, "3 == 3.0", Value::Boolean(true));
+  assert_eval!(vm, "2 < 2.5", Value::Boolean(true));
+}
+
+#[test]
+fn integer_conversion() {
+  let result: Result<i64, RuntimeError> = Value::Int(3).try_into();
+  assert!(matches!(result, Ok(3)));
+  let result: Result<i64, RuntimeError> = Value::Number(4.0).try_into();
+  assert!(matches!(result, Ok(4)));
+  let result: Result<i64, RuntimeError> = Value::Number(4.5).try_into();
+  assert!(matches!(
+    result,
+    Err(RuntimeError::TypeError {
+      expected: "integer",
+      ..
+    })
+  ));
+}
+
+#[test]
+fn number_format() {
+  let mut vm = VM::default();
+  vm.set_number_format(NumberFormat {
+    decimal_separator: ',',
+    ..Default::default()
+  });
+  assert_eval!(vm, "str(3.5)", Value::String("3,5".to_string()));
+  assert_eval!(vm, "str(#[1.5, 2])", Value::String("#[1,5, 2]".to_string()));
+  assert_eval!(vm, "to_string(3.5)", Value::String("3,5".to_string()));
+  // only stringification is affected
+  assert_eval!(vm, "3.5 * 2", Value::Number(7.0));
+
+  vm.set_number_format(NumberFormat {
+    decimal_separator: ',',
+    thousands_separator: Some('.'),
+    precision: Some(2),
+  });
+  assert_eval!(
+    vm,
+    "str(1234567.891)",
+    Value::String("1.234.567,89".to_string())
+  );
+  assert_eval!(vm, "str(-1234)", Value::String("-1.234".to_string()));
+  assert_eval!(vm, "str(123)", Value::String("123".to_string()));
+
+  let logged = Rc::new(RefCell::new(Vec::new()));
+  let handler_logged = Rc::clone(&logged);
+  vm.add_log_handler(Box::new(move |value| {
+    handler_logged.borrow_mut().push(value);
+  }));
+  assert!(vm
+    .interpret(compile(r#"log("total:", 1000.5)"#).unwrap())
+    .is_ok());
+  assert!(Value::equal(
+    &logged.borrow()[0],
+    &Value::String("total: 1.000,50".to_string())
+  ));
+  assert_eq!(vm.display(&Value::Number(1000.5)), "1.000,50");
+}
+
+#[test]
+fn number_format_keeps_globals() {
+  let format = NumberFormat {
+    decimal_separator: ',',
+    ..Default::default()
+  };
+
+  // the format is read when `str` is called, so it isn't lost when the natives are defined again
+  let mut vm = VM::default();
+  vm.set_number_format(format.clone());
+  vm.register_std();
+  assert_eval!(vm, "str(3.5)", Value::String("3,5".to_string()));
+
+  // and globals of the same name are left alone
+  let mut vm = VM::default();
+  vm.set_global("to_string", Value::Int(1));
+  vm.set_number_format(format.clone());
+  assert_eval!(vm, "to_string", Value::Int(1));
+
+  let mut vm = VM::with_shared_stdlib(Rc::new(StdlibRegistry::default()));
+  vm.set_number_format(format);
+  assert!(vm.get_global("str").is_none());
+}
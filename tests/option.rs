@@ -0,0 +1,74 @@
+use bobascript::{
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn some_and_none() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "some(5)", Value::Some(Box::new(Value::Int(5))));
+  assert_eval!(vm, "none", Value::None);
+  assert_eval!(vm, "some(2 + 3) == some(5)", Value::Boolean(true));
+  assert_eval!(vm, "some(5) == some(6)", Value::Boolean(false));
+  assert_eval!(vm, "none == none", Value::Boolean(true));
+  assert_eval!(vm, "some(()) == none", Value::Boolean(false));
+}
+
+#[test]
+fn none_is_not_unit() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "none == ()", Value::Boolean(false));
+  assert_ne!(Value::None, Value::Unit);
+  assert_eq!(Value::None.to_string(), "none");
+  assert_eq!(Value::Unit.to_string(), "()");
+
+  let value = assert_runtime_ok!(vm, r#"some(#["a", none])"#);
+  assert_eq!(value.to_string(), r#"some(#["a", none])"#);
+}
+
+#[test]
+fn option_natives() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "unwrap_or(none, 0)", Value::Int(0));
+  assert_eval!(vm, "unwrap_or(some(5), 0)", Value::Int(5));
+  assert_eval!(vm, "is_some(some(5))", Value::Boolean(true));
+  assert_eval!(vm, "is_some(none)", Value::Boolean(false));
+  assert_runtime_err!(
+    vm,
+    "is_some(())",
+    RuntimeError::TypeError {
+      expected: "option",
+      found: Value::Unit,
+    }
+  );
+}
+
+#[test]
+fn match_options() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn describe(option) => match option {
+      none => "nothing",
+      some(0) => "zero",
+      some(#[x, _]) => x,
+      some(n) => n * 2,
+    };
+    #[describe(none), describe(some(0)), describe(some(#["pair", 1])), describe(some(4))]
+    "#,
+    Value::tuple_from(vec![
+      Value::String("nothing".to_string()),
+      Value::String("zero".to_string()),
+      Value::String("pair".to_string()),
+      Value::Int(8),
+    ])
+  );
+  assert_eval!(
+    vm,
+    "match #[some(1), none] { #[some(a), some(b)] => a + b, #[some(a), _] => a, _ => 0 }",
+    Value::Int(1)
+  );
+}
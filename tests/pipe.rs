@@ -0,0 +1,64 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bobascript::{value::Value, vm::VM};
+
+mod common;
+
+fn strings(strings: &[&str]) -> Value {
+  Value::tuple_from(strings.iter().map(|s| Value::String(s.to_string())))
+}
+
+#[test]
+fn pipes_into_natives() {
+  let mut vm = VM::default();
+  vm.register_string_lib();
+  assert_eval!(vm, r#""a,b" |> upper |> split(",")"#, strings(&["A", "B"]));
+  assert_eval!(vm, r#""a,b,c" |> split(",") |> len"#, Value::Int(3));
+  // the same as calling them inside out
+  assert_eval!(
+    vm,
+    r#"("a,b" |> upper |> split(",")) == split(upper("a,b"), ",")"#,
+    Value::Boolean(true)
+  );
+}
+
+#[test]
+fn pipes_into_script_functions() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    fn sub(a, b) => a - b;
+    let double = fn(x) => x * 2;
+    #[10 |> sub(3) |> double, 10 |> sub(b = 4)]
+    "#,
+    Value::tuple_from(vec![Value::Int(14), Value::Int(6)])
+  );
+  // the pipe binds looser than arithmetic, but tighter than comparisons
+  assert_eval!(vm, "1 + 2 |> double", Value::Int(6));
+  assert_eval!(vm, "2 |> double == 4 ? 1 : 0", Value::Int(1));
+}
+
+#[test]
+fn runs_left_to_right() {
+  let logs = Rc::new(RefCell::new(Vec::new()));
+  let mut vm = VM::default();
+  let handler_logs = Rc::clone(&logs);
+  vm.add_log_handler(Box::new(move |value| {
+    handler_logs.borrow_mut().push(value);
+  }));
+
+  assert_eval!(
+    vm,
+    r#"
+    fn step(value, name) {
+      log(name);
+      value + 1
+    };
+    0 |> step("first") |> step("second") |> step("third")
+    "#,
+    Value::Int(3)
+  );
+  let logs: Vec<String> = logs.borrow().iter().map(Value::to_string).collect();
+  assert_eq!(logs, vec![r#""first""#, r#""second""#, r#""third""#]);
+}
@@ -0,0 +1,89 @@
+use std::rc::Rc;
+
+use bobascript::{
+  stdlib::StdlibRegistry,
+  value::{NativeProperty, Value},
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+fn shout(value: &Value) -> Result<Value, RuntimeError> {
+  match value {
+    Value::String(string) => Ok(Value::String(string.to_uppercase())),
+    value => Err(RuntimeError::TypeError {
+      expected: "string",
+      found: value.clone(),
+    }),
+  }
+}
+
+#[test]
+fn prelude_properties() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#""café".length"#, Value::Number(4.0));
+  assert_eval!(vm, "#[1, 2, 3].length", Value::Number(3.0));
+  assert_runtime_err!(
+    vm,
+    "1.length",
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+#[test]
+fn custom_property() {
+  let mut vm = VM::default();
+  vm.define_property(
+    "string",
+    "shout".to_owned(),
+    NativeProperty { getter: shout },
+  );
+  assert_eval!(
+    vm,
+    r#"let word = "hey"; word.shout"#,
+    Value::String("HEY".to_string())
+  );
+  assert_runtime_err!(
+    vm,
+    r#""hey".whisper"#,
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+#[test]
+fn record_fields_come_first() {
+  fn size(value: &Value) -> Result<Value, RuntimeError> {
+    match value {
+      Value::Record(record) => Ok(Value::Number(record.len() as f64)),
+      _ => Ok(Value::Unit),
+    }
+  }
+
+  let mut vm = VM::default();
+  vm.define_property("record", "size".to_owned(), NativeProperty { getter: size });
+  assert_eval!(vm, "#{ a: 1, b: 2 }.size", Value::Number(2.0));
+  assert_eval!(vm, "#{ size: 7 }.size", Value::Number(7.0));
+  assert_runtime_err!(
+    vm,
+    "#{ a: 1 }.b",
+    RuntimeError::UndefinedProperty(String::new())
+  );
+}
+
+#[test]
+fn shared_properties() {
+  let mut registry = StdlibRegistry::default();
+  registry.define_property(
+    "string",
+    "shout".to_owned(),
+    NativeProperty { getter: shout },
+  );
+  let mut vm = VM::with_shared_stdlib(Rc::new(registry));
+  assert_eval!(vm, r#""a".shout"#, Value::String("A".to_string()));
+}
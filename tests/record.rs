@@ -0,0 +1,334 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bobascript::{
+  asm::assemble,
+  compiler::compile,
+  value::{Function, NativeFunction, Value},
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn fields_keep_insertion_order() {
+  fn keys(params: &[Value]) -> Result<Value, RuntimeError> {
+    match &params[0] {
+      Value::Record(record) => Ok(Value::Tuple(
+        record
+          .keys()
+          .map(|key| key.value().clone())
+          .collect::<Vec<Value>>()
+          .into(),
+      )),
+      value => Err(RuntimeError::TypeError {
+        expected: "record",
+        found: value.clone(),
+      }),
+    }
+  }
+
+  let mut vm = VM::default();
+  vm.define_native(
+    "keys".to_owned(),
+    Rc::new(RefCell::new(NativeFunction::new(keys))),
+  );
+  assert_eval!(
+    vm,
+    "keys(#{ b: 1, a: 2 })",
+    Value::Tuple(
+      vec![
+        Value::String("b".to_string()),
+        Value::String("a".to_string())
+      ]
+      .into()
+    )
+  );
+
+  let function = compile("#{ b: 1, a: 2, c: 3 }").unwrap();
+  let value = vm.interpret(function).unwrap();
+  assert_eq!(value.to_string(), r#"#{"b": 1, "a": 2, "c": 3}"#);
+}
+
+#[test]
+fn equality_ignores_order() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "#{ a: 1, b: 2 } == #{ b: 2, a: 1 }",
+    Value::Boolean(true)
+  );
+  assert_eval!(
+    vm,
+    "#{ a: 1, b: 2 } == #{ a: 1, b: 3 }",
+    Value::Boolean(false)
+  );
+}
+
+#[test]
+fn equality_checks_size_and_nesting() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "#{ a: 1 } == #{ a: 1, b: 2 }", Value::Boolean(false));
+  assert_eval!(vm, "#{ a: 1, b: 2 } == #{ a: 1 }", Value::Boolean(false));
+  assert_eval!(vm, "#{ a: 1, b: 2 } != #{ b: 2 }", Value::Boolean(true));
+  assert_eval!(
+    vm,
+    "#{ a: #[1, #{ x: 2, y: 3 }], b: #{ c: () } } == #{ b: #{ c: () }, a: #[1, #{ y: 3, x: 2 }] }",
+    Value::Boolean(true)
+  );
+  assert_eval!(
+    vm,
+    "#{ a: #[1, #{ x: 2 }] } == #{ a: #[1, #{ x: 4 }] }",
+    Value::Boolean(false)
+  );
+}
+
+#[test]
+fn property_access() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "#{a: 1, b: 2}.a", Value::Number(1.0));
+  assert_eval!(vm, "#{a: 1, b: #{c: 3}}.b.c", Value::Number(3.0));
+  assert_runtime_err!(
+    vm,
+    "#{a: 1, b: 2}.c",
+    RuntimeError::UndefinedProperty(String::new())
+  );
+  assert_runtime_err!(
+    vm,
+    "(1).a",
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+#[test]
+fn duplicate_keys_keep_last_value() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "#{a: 1, b: 2, a: 3}.a", Value::Number(3.0));
+  let value = vm
+    .interpret(compile("#{a: 1, b: 2, a: 3}").unwrap())
+    .unwrap();
+  assert_eq!(value.to_string(), r#"#{"a": 3, "b": 2}"#);
+}
+
+#[test]
+fn property_assignment() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(vm, "let r = #{a: 1, b: 2}; let alias = r;");
+  assert_eval!(vm, "r.a = 3", Value::Int(3));
+  assert_eval!(vm, "r.a", Value::Int(3));
+  // the record is copied, so other variables holding it don't change
+  assert_eval!(vm, "alias.a", Value::Int(1));
+
+  // new fields are added to the end
+  assert_runtime_ok!(vm, "r.c = 4;");
+  assert_eq!(
+    vm.get_global("r").unwrap().to_string(),
+    r#"#{"a": 3, "b": 2, "c": 4}"#
+  );
+
+  assert_runtime_err!(
+    vm,
+    "let t = #[1]; t.a = 2;",
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+/// Runs an assembled chunk that builds `#{a: 1, b: 2}` and then runs the given instructions on it.
+fn run_on_record(instructions: &str) -> Value {
+  let chunk = assemble(&format!(
+    r#"
+    .const 1
+    .const "a"
+    .const 2
+    .const "b"
+      Constant 0
+      Constant 1
+      Constant 2
+      Constant 3
+      Record 2
+      {}
+      Return
+    "#,
+    instructions
+  ))
+  .unwrap();
+  let function = Rc::new(Function {
+    chunk,
+    ..Default::default()
+  });
+  let mut vm = VM::default();
+  vm.set_verify(true);
+  vm.interpret(function).unwrap()
+}
+
+#[test]
+fn has_property() {
+  assert_eq!(run_on_record(r#"HasProperty "a""#), Value::Boolean(true));
+  assert_eq!(run_on_record(r#"HasProperty "c""#), Value::Boolean(false));
+  assert_eq!(
+    run_on_record("Pop\n      Unit\n      HasProperty \"a\""),
+    Value::Boolean(false)
+  );
+}
+
+#[test]
+fn record_len() {
+  assert_eq!(run_on_record("RecordLen"), Value::Int(2));
+  assert_eq!(
+    run_on_record("Pop\n      True\n      RecordLen"),
+    Value::Unit
+  );
+}
+
+#[test]
+fn spread() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let base = #{ x: 0, y: 2 }; #{ ...base, x: 1 }",
+    Value::record_from(vec![
+      ("x".to_string(), Value::Int(1)),
+      ("y".to_string(), Value::Int(2)),
+    ])
+  );
+  // whichever comes last wins
+  assert_eval!(
+    vm,
+    "#{ x: 1, z: 3, ...base }",
+    Value::record_from(vec![
+      ("x".to_string(), Value::Int(0)),
+      ("z".to_string(), Value::Int(3)),
+      ("y".to_string(), Value::Int(2)),
+    ])
+  );
+  assert_eval!(vm, "#{ ...base } == base", Value::Boolean(true));
+  assert_runtime_err!(
+    vm,
+    "#{ ...#[1, 2] }",
+    RuntimeError::TypeError {
+      expected: "record",
+      found: Value::Unit,
+    }
+  );
+}
+
+#[test]
+fn number_keys() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"let names = #{ [1]: "one", [2.5]: "two and a half", "1": "string one" }; names[1]"#,
+    Value::String("one".to_string())
+  );
+  // keys are compared like `==`, so an int and a whole float are the same key
+  assert_eval!(vm, "names[1.0]", Value::String("one".to_string()));
+  assert_eval!(
+    vm,
+    "names[2.5]",
+    Value::String("two and a half".to_string())
+  );
+  assert_eval!(vm, r#"names["1"]"#, Value::String("string one".to_string()));
+  assert_eval!(
+    vm,
+    "#{ [0]: 1, [-0.0]: 2 } == #{ [0]: 2 }",
+    Value::Boolean(true)
+  );
+  assert_runtime_err!(
+    vm,
+    "names[3]",
+    RuntimeError::UndefinedProperty(String::new())
+  );
+  assert_eq!(
+    assert_runtime_ok!(vm, "names").to_string(),
+    r#"#{[1]: "one", [2.5]: "two and a half", "1": "string one"}"#
+  );
+}
+
+#[test]
+fn tuple_keys() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    let grid = #{ [#[0, 0]]: "origin", [#[1, 2]]: "x" };
+    grid[#[1, 2]] = "y";
+    grid[#[2, 1]] = "z";
+    #[grid[#[1, 2]], grid[#[2, 1]], len(grid)]
+    "#,
+    Value::tuple_from(vec![
+      Value::String("y".to_string()),
+      Value::String("z".to_string()),
+      Value::Int(3),
+    ])
+  );
+  assert_eval!(vm, "grid[#[1.0, 2]]", Value::String("y".to_string()));
+  assert_runtime_err!(
+    vm,
+    "grid[#[1, 2, 3]]",
+    RuntimeError::UndefinedProperty(String::new())
+  );
+  assert_eval!(
+    vm,
+    r#"#{ [#[1, 2]]: "x" } == #{ [#[1, 2]]: "x" }"#,
+    Value::Boolean(true)
+  );
+  assert_eval!(
+    vm,
+    r#"#{ [#[1, 2]]: "x" } == #{ [#[2, 1]]: "x" }"#,
+    Value::Boolean(false)
+  );
+}
+
+#[test]
+fn unhashable_keys() {
+  let mut vm = VM::default();
+  let error = RuntimeError::UnhashableKey(Value::Unit);
+  assert_runtime_err!(vm, "#{ [(-1) ^ 0.5]: 1 }", error);
+  assert_runtime_err!(vm, "#{ [#{ a: 1 }]: 1 }", error);
+  assert_runtime_err!(vm, "fn f() {}; #{ [#[f]]: 1 }", error);
+  assert_runtime_err!(vm, "#{ a: 1 }[#{}]", error);
+}
+
+#[test]
+fn number_keys_are_exact() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#"#{ [1]: "a" }[1.0]"#, Value::String("a".to_string()));
+  assert_eval!(vm, r#"#{ [-0.0]: "a" }[0]"#, Value::String("a".to_string()));
+  assert_eval!(vm, r#"len(#{ [1]: "a", [1.0]: "b" })"#, Value::Int(1));
+  // 2^53 + 1 is `==` to 2^53 as a float, since that's the closest float to it, but isn't the same key
+  assert_eval!(
+    vm,
+    "9007199254740993 == 9007199254740992.0",
+    Value::Boolean(true)
+  );
+  assert_eval!(
+    vm,
+    "len(#{ [9007199254740993]: 1, [9007199254740992.0]: 2, [9007199254740992]: 3 })",
+    Value::Int(2)
+  );
+}
+
+#[test]
+fn display_is_stable() {
+  let source = "#{ zeta: 1, alpha: 2, mu: #{ y: 3, x: 4 }, beta: 5, [#[1]]: 6 }";
+  let expected = r#"#{"zeta": 1, "alpha": 2, "mu": #{"y": 3, "x": 4}, "beta": 5, [#[1]]: 6}"#;
+  for _ in 0..20 {
+    let mut vm = VM::default();
+    assert_eq!(assert_runtime_ok!(vm, source).to_string(), expected);
+  }
+}
+
+#[test]
+fn long_literal() {
+  let mut vm = VM::default();
+  let fields: Vec<String> = (0..300).map(|i| format!("[{}]: {}", i, i)).collect();
+  let source = format!("len(#{{{}}})", fields.join(", "));
+  assert_eval!(vm, &source, Value::Int(300));
+  let source = format!("#{{{}}}[299]", fields.join(", "));
+  assert_eval!(vm, &source, Value::Int(299));
+}
@@ -0,0 +1,42 @@
+use bobascript::{value::Value, vm::VM};
+
+mod common;
+
+#[test]
+fn serialize_record() {
+  let mut vm = VM::default();
+  let value = assert_runtime_ok!(vm, "#{a: 1, b: #[2, 3]}");
+  assert_eq!(
+    serde_json::to_string(&value).unwrap(),
+    r#"{"a":1,"b":[2,3]}"#
+  );
+
+  let value = assert_runtime_ok!(vm, r#"#[(), true, 1.5, "boba"]"#);
+  assert_eq!(
+    serde_json::to_string(&value).unwrap(),
+    r#"[null,true,1.5,"boba"]"#
+  );
+}
+
+#[test]
+fn serialize_function() {
+  let mut vm = VM::default();
+  let value = assert_runtime_ok!(vm, "fn add(a, b) => a + b; add");
+  assert!(serde_json::to_string(&value).is_err());
+}
+
+#[test]
+fn deserialize_record() {
+  let mut vm = VM::default();
+  let order: Value =
+    serde_json::from_str(r#"{"flavor": "taro", "toppings": ["boba", "jelly"], "size": 2}"#)
+      .unwrap();
+  vm.set_global("order", order);
+  assert_eval!(vm, "order.flavor", Value::String("taro".to_string()));
+  assert_eval!(vm, "order.toppings[1]", Value::String("jelly".to_string()));
+  assert_eval!(vm, "order.size", Value::Int(2));
+
+  // integers too large for an `Int` are kept as best they can be
+  let value: Value = serde_json::from_str("18446744073709551615").unwrap();
+  assert_eq!(value, Value::Number(u64::MAX as f64));
+}
@@ -0,0 +1,238 @@
+use std::{cell::RefCell, convert::TryInto, rc::Rc};
+
+use bobascript::{
+  compiler::compile,
+  stdlib::StdlibRegistry,
+  value::{NativeFunction, Value},
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn shared_stdlib() {
+  fn double(params: &[Value]) -> Result<Value, RuntimeError> {
+    let num: f64 = params[0].clone().try_into()?;
+    Ok(Value::Number(num * 2.0))
+  }
+
+  let mut registry = StdlibRegistry::default();
+  registry.define_native(
+    "double".to_owned(),
+    Rc::new(RefCell::new(NativeFunction::new(double))),
+  );
+  let registry = Rc::new(registry);
+
+  let mut vm1 = VM::with_shared_stdlib(registry.clone());
+  let mut vm2 = VM::with_shared_stdlib(registry);
+  assert_eval!(vm1, "double(2)", Value::Number(4.0));
+  assert_eval!(vm2, "double(21)", Value::Number(42.0));
+}
+
+#[test]
+fn shared_std() {
+  let registry = Rc::new(StdlibRegistry::with_std());
+  let mut vm1 = VM::with_shared_stdlib(registry.clone());
+  let mut vm2 = VM::with_shared_stdlib(registry.clone());
+
+  assert_eval!(vm1, "len(#[1, 2, 3])", Value::Int(3));
+  assert_eval!(vm2, r#"type("a")"#, Value::String("string".to_string()));
+  assert_eval!(vm1, "sqrt(16)", Value::Number(4.0));
+  assert_eval!(vm2, r#"upper("boba")"#, Value::String("BOBA".to_string()));
+  assert_eval!(
+    vm1,
+    "map(#[1, 2], fn(x) { x * 2 })",
+    Value::tuple_from(vec![Value::Int(2), Value::Int(4)])
+  );
+
+  // both VMs call the very same natives
+  let len1 = vm1.interpret(compile("len").unwrap()).unwrap();
+  let len2 = vm2.interpret(compile("len").unwrap()).unwrap();
+  match (len1, len2) {
+    (Value::NativeFunction(a), Value::NativeFunction(b)) => assert!(Rc::ptr_eq(&a, &b)),
+    values => panic!("expected natives, got {:?}", values),
+  }
+}
+
+#[test]
+fn shared_clock() {
+  let registry = Rc::new(StdlibRegistry::with_std());
+  let mut vm1 = VM::with_shared_stdlib(registry.clone());
+  std::thread::sleep(std::time::Duration::from_millis(50));
+  let mut vm2 = VM::with_shared_stdlib(registry);
+
+  // each VM counts from when it was created, rather than from when the registry was
+  let time2 = vm2.interpret(compile("clock()").unwrap()).unwrap();
+  let time1 = vm1.interpret(compile("clock()").unwrap()).unwrap();
+  match (time1, time2) {
+    (Value::Number(time1), Value::Number(time2)) => assert!(time1 >= 0.05 && time2 < time1),
+    times => panic!("expected two numbers, got {:?}", times),
+  }
+}
+
+#[test]
+fn len() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "len(#[])", Value::Int(0));
+  assert_eval!(vm, "len(#[1, #[2, 3], 4])", Value::Int(3));
+  assert_eval!(vm, "len(#{ a: 1, b: 2 })", Value::Int(2));
+  assert_eval!(vm, r#"len("café")"#, Value::Int(4));
+  assert_runtime_err!(vm, "len(1.5)", RuntimeError::OperationNotSupported);
+  assert_runtime_err!(vm, "len(true)", RuntimeError::OperationNotSupported);
+  assert_runtime_err!(
+    vm,
+    "len(#[], #[])",
+    RuntimeError::IncorrectParameterCount(1, 2)
+  );
+}
+
+#[test]
+fn register_builtins() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "len = 5; len", Value::Int(5));
+  vm.register_builtins();
+  assert_eval!(vm, "len(#[1])", Value::Int(1));
+}
+
+#[test]
+fn type_of() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "type(1)",
+    RuntimeError::UndefinedVariable(String::new())
+  );
+
+  vm.register_std();
+  assert_eval!(vm, "type(())", Value::String("unit".to_string()));
+  assert_eval!(vm, "type(1)", Value::String("number".to_string()));
+  assert_eval!(vm, "type(1.5)", Value::String("number".to_string()));
+  assert_eval!(vm, "type(false)", Value::String("boolean".to_string()));
+  assert_eval!(vm, r#"type("a")"#, Value::String("string".to_string()));
+  assert_eval!(vm, "type(#[1, 2])", Value::String("tuple".to_string()));
+  assert_eval!(vm, "type(#{ a: 1 })", Value::String("record".to_string()));
+  assert_eval!(vm, "type(len)", Value::String("function".to_string()));
+  assert_eval!(
+    vm,
+    "fn f() => 1; type(f)",
+    Value::String("function".to_string())
+  );
+  assert_eval!(
+    vm,
+    "{ let a = 1; fn g() => a; type(g) }",
+    Value::String("function".to_string())
+  );
+}
+
+#[test]
+fn clock() {
+  let mut vm = VM::default();
+  vm.register_std();
+  let value = assert_runtime_ok!(
+    vm,
+    r#"
+    let start = clock();
+    let i = 0;
+    while i < 1000 { i += 1; };
+    #[start, clock()]
+    "#
+  );
+  let times: Vec<Value> = value.try_into().unwrap();
+  match (&times[0], &times[1]) {
+    (Value::Number(start), Value::Number(end)) => assert!(*start >= 0.0 && end >= start),
+    _ => panic!("expected two numbers, got {:?}", times),
+  }
+  assert_runtime_err!(vm, "clock(1)", RuntimeError::IncorrectParameterCount(0, 1));
+}
+
+#[test]
+fn map() {
+  let mut vm = VM::default();
+  vm.register_std();
+  assert_eval!(
+    vm,
+    "map(#[1, 2, 3], fn(x) { x * 2 })",
+    Value::tuple_from(vec![Value::Int(2), Value::Int(4), Value::Int(6)])
+  );
+  assert_eval!(vm, "map(#[], fn(x) { x * 2 })", Value::tuple_from(vec![]));
+  assert_eval!(
+    vm,
+    r#"map(#["a", "b"], fn(s) { map(#[1, 2], fn(n) { s + str(n) }) })"#,
+    Value::tuple_from(vec![
+      Value::tuple_from(vec![
+        Value::String("a1".to_string()),
+        Value::String("a2".to_string())
+      ]),
+      Value::tuple_from(vec![
+        Value::String("b1".to_string()),
+        Value::String("b2".to_string())
+      ]),
+    ])
+  );
+  assert_runtime_err!(
+    vm,
+    "map(1, fn(x) { x })",
+    RuntimeError::TypeError {
+      expected: "tuple",
+      found: Value::Int(1),
+    }
+  );
+}
+
+#[test]
+fn filter() {
+  let mut vm = VM::default();
+  vm.register_std();
+  assert_eval!(
+    vm,
+    "filter(#[1, 2, 3, 4, 5, 6], fn(x) { x % 2 == 0 })",
+    Value::tuple_from(vec![Value::Int(2), Value::Int(4), Value::Int(6)])
+  );
+  assert_runtime_err!(
+    vm,
+    "filter(#[1, 2], fn(x) { x })",
+    RuntimeError::TypeError {
+      expected: "boolean",
+      found: Value::Int(1),
+    }
+  );
+}
+
+#[test]
+fn reduce() {
+  let mut vm = VM::default();
+  vm.register_std();
+  assert_eval!(
+    vm,
+    "reduce(#[1, 2, 3, 4], fn(sum, x) { sum + x }, 0)",
+    Value::Int(10)
+  );
+  assert_eval!(vm, "reduce(#[], fn(sum, x) { sum + x }, 0)", Value::Int(0));
+  assert_runtime_err!(
+    vm,
+    "reduce(#[1, 2], fn(sum, x) { sum + x })",
+    RuntimeError::IncorrectParameterCount(3, 2)
+  );
+}
+
+#[test]
+fn callback_errors_propagate() {
+  let mut vm = VM::default();
+  vm.register_std();
+  assert_runtime_err!(
+    vm,
+    "map(#[1, 0], fn(x) { 1 / x })",
+    RuntimeError::DivideByZero
+  );
+  assert_runtime_err!(
+    vm,
+    "reduce(#[1], fn(sum) { sum }, 0)",
+    RuntimeError::IncorrectParameterCount(1, 2)
+  );
+  // the VM is left usable afterwards
+  assert_eval!(
+    vm,
+    "map(#[2], fn(x) { x + 1 })",
+    Value::tuple_from(vec![Value::Int(3)])
+  );
+}
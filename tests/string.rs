@@ -0,0 +1,244 @@
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::Value,
+  vm::{RuntimeError, VM},
+  InterpretError,
+};
+
+mod common;
+
+#[test]
+fn len_counts_chars() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#"len("cafe")"#, Value::Number(4.0));
+  assert_eval!(vm, r#"len("café")"#, Value::Number(4.0));
+  assert_eval!(vm, r#"byte_len("café")"#, Value::Number(5.0));
+  assert_runtime_err!(vm, "len(1)", RuntimeError::OperationNotSupported);
+}
+
+#[test]
+fn index_by_char() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#""café"[0]"#, Value::String("c".to_string()));
+  assert_eval!(vm, r#""café"[3]"#, Value::String("é".to_string()));
+  assert_eval!(vm, r#""café"[-1]"#, Value::String("é".to_string()));
+  assert_eval!(vm, r#""café"[-4]"#, Value::String("c".to_string()));
+  assert_eval!(vm, r#""café"[1.0]"#, Value::String("a".to_string()));
+  assert_runtime_err!(
+    vm,
+    r#""café"[4]"#,
+    RuntimeError::IndexOutOfBounds { index: 0, len: 0 }
+  );
+  assert_runtime_err!(
+    vm,
+    r#""café"[-5]"#,
+    RuntimeError::IndexOutOfBounds { index: 0, len: 0 }
+  );
+  assert_runtime_err!(
+    vm,
+    r#""abc"[1.6]"#,
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+
+  let error = vm.interpret(compile(r#""café"[4]"#).unwrap()).unwrap_err();
+  assert!(matches!(
+    error,
+    InterpretError::RuntimeError {
+      error: RuntimeError::IndexOutOfBounds { index: 4, len: 4 },
+      ..
+    }
+  ));
+}
+
+#[test]
+fn compare_strings() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#""apple" < "banana""#, Value::Boolean(true));
+  assert_eval!(vm, r#""b" > "a""#, Value::Boolean(true));
+  assert_eval!(vm, r#""b" < "a""#, Value::Boolean(false));
+  assert_eval!(vm, r#""ab" >= "ab""#, Value::Boolean(true));
+  assert_eval!(vm, r#""ab" <= "abc""#, Value::Boolean(true));
+  assert_eval!(vm, r#""Z" < "a""#, Value::Boolean(true));
+  assert_runtime_err!(
+    vm,
+    r#""a" < 1"#,
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+#[test]
+fn str_and_repr() {
+  let mut vm = VM::default();
+  let str = vm.interpret(compile("str").unwrap()).unwrap();
+  let repr = vm.interpret(compile("repr").unwrap()).unwrap();
+  let value = Value::String("a\nb \"quoted\"".to_string());
+
+  let result = vm.call_function(str, vec![value.clone()]).unwrap();
+  assert!(Value::equal(&result, &value));
+  let result = vm.call_function(repr, vec![value]).unwrap();
+  assert!(Value::equal(
+    &result,
+    &Value::String(r#""a\nb \"quoted\"""#.to_string())
+  ));
+
+  assert_eval!(vm, "str(1.5)", Value::String("1.5".to_string()));
+  assert_eval!(vm, "repr(1.5)", Value::String("1.5".to_string()));
+  assert_eval!(vm, r#"str("hi")"#, Value::String("hi".to_string()));
+  assert_eval!(vm, r#"repr("hi")"#, Value::String(r#""hi""#.to_string()));
+  assert_eval!(
+    vm,
+    r#"repr(#[1, "a", #{ b: "c" }, ()])"#,
+    Value::String(r#"#[1, "a", #{"b": "c"}, ()]"#.to_string())
+  );
+}
+
+#[test]
+fn escape_sequences() {
+  let mut vm = VM::default();
+  assert_eval!(vm, r#"len("a\nb")"#, Value::Int(3));
+  assert_eval!(vm, r#""a\nb""#, Value::String("a\nb".to_string()));
+  assert_eval!(vm, r#""\t\r\\\"""#, Value::String("\t\r\\\"".to_string()));
+  assert_eval!(vm, r#""caf\u{e9}""#, Value::String("café".to_string()));
+  assert_eval!(
+    vm,
+    r#"match "\u{1F9CB}" { "🧋" => true, _ => false }"#,
+    Value::Boolean(true)
+  );
+  assert_eval!(
+    vm,
+    r#"repr(#{ "a\tb": 1 })"#,
+    Value::String(r#"#{"a\tb": 1}"#.to_string())
+  );
+
+  for invalid in &[r#""\q""#, r#""\u{}""#, r#""\u{110000}""#, r#""\u{e9""#] {
+    assert_compile_err!(
+      compile(*invalid),
+      CompileError::InvalidEscape(String::new())
+    );
+  }
+}
+
+#[test]
+fn interpolation() {
+  let mut vm = VM::default();
+  let function = compile(r#"let name = "boba"; let age = 3;"#).unwrap();
+  assert!(vm.interpret(function).is_ok());
+
+  assert_eval!(
+    vm,
+    r#""hello ${name}!""#,
+    Value::String("hello boba!".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#""hello ${name}, you are ${age} years old""#,
+    Value::String("hello boba, you are 3 years old".to_string())
+  );
+  assert_eval!(vm, r#""${age}${age * 2}""#, Value::String("36".to_string()));
+  assert_eval!(
+    vm,
+    r#""next year: ${ { let next = age + 1; next } }""#,
+    Value::String("next year: 4".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#""cost: \${age}""#,
+    Value::String("cost: ${age}".to_string())
+  );
+  assert!(matches!(
+    compile(r#""${age""#),
+    Err(CompileError::SyntaxError(_))
+  ));
+  assert!(matches!(
+    compile(r#""${let a = 1;}""#),
+    Err(CompileError::SyntaxError(_))
+  ));
+}
+
+fn string_lib_vm() -> VM {
+  let mut vm = VM::default();
+  vm.register_string_lib();
+  vm
+}
+
+fn strings(strings: &[&str]) -> Value {
+  Value::tuple_from(strings.iter().map(|s| Value::String(s.to_string())))
+}
+
+#[test]
+fn changing_case() {
+  let mut vm = string_lib_vm();
+  assert_eval!(
+    vm,
+    r#"upper("Boba tea")"#,
+    Value::String("BOBA TEA".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#"lower("Boba TEA")"#,
+    Value::String("boba tea".to_string())
+  );
+  assert_eval!(
+    vm,
+    r#"upper("straße")"#,
+    Value::String("STRASSE".to_string())
+  );
+}
+
+#[test]
+fn trim() {
+  let mut vm = string_lib_vm();
+  assert_eval!(
+    vm,
+    "trim(\"  boba \\n\")",
+    Value::String("boba".to_string())
+  );
+  assert_eval!(vm, r#"trim("")"#, Value::String(String::new()));
+}
+
+#[test]
+fn contains() {
+  let mut vm = string_lib_vm();
+  assert_eval!(vm, r#"contains("boba tea", "a t")"#, Value::Boolean(true));
+  assert_eval!(
+    vm,
+    r#"contains("boba tea", "coffee")"#,
+    Value::Boolean(false)
+  );
+  assert_eval!(vm, r#"contains("boba", "")"#, Value::Boolean(true));
+}
+
+#[test]
+fn split() {
+  let mut vm = string_lib_vm();
+  assert_eval!(vm, r#"split("a,b,c", ",")"#, strings(&["a", "b", "c"]));
+  assert_eval!(vm, r#"len(split("a,b,c", ","))"#, Value::Int(3));
+  assert_eval!(vm, r#"split("a, b", ", ")"#, strings(&["a", "b"]));
+  assert_eval!(vm, r#"split(",a,", ",")"#, strings(&["", "a", ""]));
+  assert_eval!(vm, r#"split("abc", "-")"#, strings(&["abc"]));
+  // an empty separator splits a string into its characters
+  assert_eval!(vm, r#"split("café", "")"#, strings(&["c", "a", "f", "é"]));
+}
+
+#[test]
+fn string_lib_checks_types() {
+  let mut vm = string_lib_vm();
+  let type_error = RuntimeError::TypeError {
+    expected: "string",
+    found: Value::Unit,
+  };
+  assert_runtime_err!(vm, "upper(1)", type_error);
+  assert_runtime_err!(vm, r#"split("a,b", 1)"#, type_error);
+  assert_runtime_err!(vm, r#"contains(#["a"], "a")"#, type_error);
+  assert_runtime_err!(
+    vm,
+    r#"trim("a", "b")"#,
+    RuntimeError::IncorrectParameterCount(1, 2)
+  );
+}
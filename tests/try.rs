@@ -0,0 +1,210 @@
+use std::rc::Rc;
+
+use bobascript::{
+  compiler::{compile, CompileError},
+  value::{Function, Value},
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+fn string(string: &str) -> Value {
+  Value::String(string.to_string())
+}
+
+#[test]
+fn evaluates_to_body_without_errors() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "try { 1 + 2 } catch err { 0 }", Value::Int(3));
+  assert_eval!(vm, "let x = try { 1 } catch _ { 2 } + 1; x", Value::Int(2));
+  // the catch block's bindings and locals don't leak out
+  assert_eval!(
+    vm,
+    "let a = 1; try { let b = 2; a + b } catch err { a }",
+    Value::Int(3)
+  );
+}
+
+#[test]
+fn catches_errors() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "try { let a = 1; a - true } catch err { err.kind }",
+    string("TypeError")
+  );
+  assert_eval!(
+    vm,
+    "let x = try { let a = 1; a / 0 } catch _ { 5 } + 1; x",
+    Value::Int(6)
+  );
+  // including those raised by functions called within it
+  assert_eval!(
+    vm,
+    r#"
+    fn inverse(x) => 1 / x;
+    fn twice(x) => inverse(x) * 2;
+    try { twice(0) } catch err { err.kind }
+    "#,
+    string("DivisionByZero")
+  );
+  // only the innermost catches
+  assert_eval!(
+    vm,
+    r#"
+    try {
+      let inner = try { #[1][5] } catch err { err.kind };
+      inner + ": " + str(1 / 0)
+    } catch err {
+      "outer " + err.kind
+    }
+    "#,
+    string("outer DivisionByZero")
+  );
+  // errors in a catch block are raised as usual
+  assert_runtime_err!(
+    vm,
+    "try { 1 / 0 } catch err { err.kind - 1 }",
+    RuntimeError::TypeError {
+      expected: "number",
+      found: string("DivisionByZero"),
+    }
+  );
+}
+
+#[test]
+fn catches_errors_in_callbacks() {
+  let mut vm = VM::default();
+  vm.register_std();
+  assert_eval!(
+    vm,
+    "try { map(#[1, 0], fn(x) { 1 / x }) } catch err { err.kind }",
+    string("DivisionByZero")
+  );
+  assert_eval!(
+    vm,
+    "map(#[2], fn(x) { x + 1 })",
+    Value::tuple_from(vec![Value::Int(3)])
+  );
+}
+
+#[test]
+fn closes_over_unwound_locals() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    let f = ();
+    try {
+      let a = 5;
+      f = fn() => a;
+      a / 0
+    } catch _ {
+      f()
+    }
+    "#,
+    Value::Int(5)
+  );
+}
+
+#[test]
+fn leaving_a_try_stops_catching() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    r#"
+    while true {
+      try { break; } catch _ { () };
+    };
+    1 / 0
+    "#,
+    RuntimeError::DivideByZero
+  );
+  assert_runtime_err!(
+    vm,
+    r#"
+    let i = 0;
+    while i < 2 {
+      i += 1;
+      try { continue; } catch _ { () };
+    };
+    1 / 0
+    "#,
+    RuntimeError::DivideByZero
+  );
+  assert_runtime_err!(
+    vm,
+    r#"
+    fn early() {
+      try { return 1; } catch _ { 2 };
+    };
+    early() / 0
+    "#,
+    RuntimeError::DivideByZero
+  );
+  // a call returned from a try block still runs within it
+  assert_eval!(
+    vm,
+    r#"
+    fn fail() => 1 / 0;
+    fn call() {
+      try { return fail(); } catch err { err.kind }
+    };
+    call()
+    "#,
+    string("DivisionByZero")
+  );
+}
+
+#[test]
+fn limits_cannot_be_caught() {
+  let mut vm = VM::default();
+  vm.set_instruction_limit(Some(100));
+  assert_runtime_err!(
+    vm,
+    "try { while true {}; } catch _ { 1 }",
+    RuntimeError::InstructionLimitExceeded
+  );
+}
+
+#[test]
+fn verifies_and_round_trips() {
+  let source = r#"
+    let i = 0;
+    let kinds = #[];
+    while i < 3 {
+      i += 1;
+      kinds = #[...kinds, try { 1 / (i - 2) } catch err { err.kind }];
+    };
+    kinds
+  "#;
+  let expected = Value::tuple_from(vec![
+    Value::Number(-1.0),
+    string("DivisionByZero"),
+    Value::Number(1.0),
+  ]);
+
+  let mut vm = VM::default();
+  vm.set_verify(true);
+  assert_eval!(vm, source, expected.clone());
+
+  let function = compile(source).unwrap();
+  let loaded = Function::from_bytes(&function.to_bytes()).unwrap();
+  assert_eq!(vm.interpret(Rc::new(loaded)).unwrap(), expected);
+}
+
+#[test]
+fn try_and_catch_are_still_names() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "let try = 1; let catch = 2; try + catch", Value::Int(3));
+  assert_eval!(vm, "fn twice(try) => try * 2; twice(4)", Value::Int(8));
+  assert_eval!(
+    vm,
+    "fn catch(x) => x + 1; try { catch(1) } catch catch { 0 }",
+    Value::Int(2)
+  );
+  assert!(matches!(
+    compile("try { 1 } except err { 2 }"),
+    Err(CompileError::SyntaxError(_))
+  ));
+}
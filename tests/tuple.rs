@@ -0,0 +1,156 @@
+use bobascript::{
+  value::Value,
+  vm::{RuntimeError, VM},
+};
+
+mod common;
+
+#[test]
+fn index() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "#[1, 2, 3][0]", Value::Number(1.0));
+  assert_eval!(vm, "#[1, 2, 3][2]", Value::Number(3.0));
+  assert_eval!(
+    vm,
+    r#"#[1, 3, 5, #["test", "I hope this works!!"]][3][1]"#,
+    Value::String("I hope this works!!".to_string())
+  );
+}
+
+#[test]
+fn index_out_of_bounds() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "#[1, 2, 3][3]",
+    RuntimeError::IndexOutOfBounds { index: 0, len: 0 }
+  );
+}
+
+#[test]
+fn negative_index() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "#[1, 2, 3][-1]", Value::Number(3.0));
+  assert_eval!(vm, "#[1, 2, 3][-3]", Value::Number(1.0));
+  assert_runtime_err!(
+    vm,
+    "#[1, 2, 3][-4]",
+    RuntimeError::IndexOutOfBounds { index: 0, len: 0 }
+  );
+}
+
+#[test]
+fn invalid_index() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "#[1, 2, 3][0.5]",
+    RuntimeError::TypeError {
+      expected: "",
+      found: Value::Unit
+    }
+  );
+}
+
+#[test]
+fn index_assignment() {
+  let mut vm = VM::default();
+  assert_runtime_ok!(vm, "let t = #[1, 2, 3]; let alias = t;");
+  assert_eval!(vm, "t[1] = 5", Value::Int(5));
+  assert_eval!(
+    vm,
+    "t[-1] = 6; t",
+    vec![Value::Int(1), Value::Int(5), Value::Int(6)].into()
+  );
+  // the tuple is copied, so other variables holding it don't change
+  assert_eval!(
+    vm,
+    "alias",
+    vec![Value::Int(1), Value::Int(2), Value::Int(3)].into()
+  );
+
+  assert_eval!(
+    vm,
+    "{ let local = #[1, 2]; local[0] = 0; local }",
+    vec![Value::Int(0), Value::Int(2)].into()
+  );
+}
+
+#[test]
+fn index_assignment_out_of_bounds() {
+  let mut vm = VM::default();
+  assert_runtime_err!(
+    vm,
+    "let t = #[1, 2, 3]; t[3] = 4;",
+    RuntimeError::IndexOutOfBounds { index: 0, len: 0 }
+  );
+}
+
+#[test]
+fn spread() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "let rest = #[2, 3]; #[1, ...rest, 4]",
+    Value::tuple_from((1..=4).map(Value::Int))
+  );
+  assert_eval!(
+    vm,
+    "#[...rest, ...rest]",
+    Value::tuple_from(vec![2, 3, 2, 3].into_iter().map(Value::Int))
+  );
+  assert_eval!(vm, "#[...#[]]", Value::tuple_from(vec![]));
+  assert_eval!(vm, "#[1]", Value::tuple_from(vec![Value::Int(1)]));
+  assert_runtime_err!(
+    vm,
+    r#"#[1, ..."not a tuple"]"#,
+    RuntimeError::TypeError {
+      expected: "tuple",
+      found: Value::Unit,
+    }
+  );
+}
+
+#[test]
+fn concatenation() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    "#[1, 2] + #[3, 4]",
+    Value::tuple_from(vec![
+      Value::Int(1),
+      Value::Int(2),
+      Value::Int(3),
+      Value::Int(4)
+    ])
+  );
+  assert_eval!(
+    vm,
+    "#[1, 2] + #[3, 4] == #[1, 2, 3, 4]",
+    Value::Boolean(true)
+  );
+  assert_eval!(vm, "#[] + #[] == #[]", Value::Boolean(true));
+  // nested tuples are kept as elements, rather than being flattened
+  assert_eval!(
+    vm,
+    "let t = #[#[1], 2]; t += #[#[3, #[4]]]; t",
+    Value::tuple_from(vec![
+      Value::tuple_from(vec![Value::Int(1)]),
+      Value::Int(2),
+      Value::tuple_from(vec![Value::Int(3), Value::tuple_from(vec![Value::Int(4)])]),
+    ])
+  );
+  assert_runtime_err!(vm, "#[1] + 2", RuntimeError::OperationNotSupported);
+  assert_runtime_err!(vm, "2 + #[1]", RuntimeError::OperationNotSupported);
+  assert_runtime_err!(vm, r#"#[1] + "a""#, RuntimeError::OperationNotSupported);
+}
+
+#[test]
+fn long_literal() {
+  let mut vm = VM::default();
+  let items: Vec<String> = (0..300).map(|i| i.to_string()).collect();
+  let source = format!("#[{}]", items.join(", "));
+  assert_eval!(vm, &source, Value::tuple_from((0..300).map(Value::Int)));
+  let source = format!("#[{}, ...#[300, 301]]", items.join(", "));
+  assert_eval!(vm, &source, Value::tuple_from((0..302).map(Value::Int)));
+}
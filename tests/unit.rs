@@ -0,0 +1,60 @@
+use bobascript::{compiler::compile, value::Value, vm::VM};
+
+mod common;
+
+#[test]
+fn equality() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "() == ()", Value::Boolean(true));
+  assert_eval!(vm, "#[] == #[]", Value::Boolean(true));
+  assert_eval!(vm, "() == #[]", Value::Boolean(false));
+}
+
+#[test]
+fn display() {
+  let mut vm = VM::default();
+  let value = vm.interpret(compile("()").unwrap()).unwrap();
+  assert_eq!(value.to_string(), "()");
+  let value = vm.interpret(compile("#[]").unwrap()).unwrap();
+  assert_eq!(value.to_string(), "#[]");
+}
+
+#[test]
+fn no_value_is_unit() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "{}", Value::Unit);
+  assert_eval!(vm, "let a; a", Value::Unit);
+}
+
+#[test]
+fn coalesce() {
+  let mut vm = VM::default();
+  assert_eval!(vm, "() ?? 5", Value::Int(5));
+  assert_eval!(vm, "3 ?? 5", Value::Int(3));
+  // only unit is replaced, not other "empty" values
+  assert_eval!(vm, "#[] ?? 5", Value::tuple_from(vec![]));
+  assert_eval!(vm, "false ?? 5", Value::Boolean(false));
+  assert_eval!(vm, "none ?? 5", Value::None);
+  assert_eval!(vm, "() ?? () ?? 7", Value::Int(7));
+  // it binds looser than `||`, but tighter than a conditional
+  assert_eval!(vm, "() ?? false || true", Value::Boolean(true));
+  assert_eval!(vm, "() ?? false ? 1 : 2", Value::Int(2));
+  assert_eval!(vm, "let x = if false { 1 } ?? 2; x", Value::Int(2));
+}
+
+#[test]
+fn coalesce_short_circuits() {
+  let mut vm = VM::default();
+  assert_eval!(
+    vm,
+    r#"
+    let calls = 0;
+    fn hit(value) {
+      calls += 1;
+      value
+    };
+    #[1 ?? hit(2), () ?? hit(3), calls]
+    "#,
+    Value::tuple_from(vec![Value::Int(1), Value::Int(3), Value::Int(1)])
+  );
+}
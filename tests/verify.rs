@@ -0,0 +1,166 @@
+use std::rc::Rc;
+
+use bobascript::{
+  chunk::{Chunk, JumpDirection, OpCode},
+  compiler::compile,
+  value::{Function, Value},
+  verify::{verify, VerifyError},
+  vm::VM,
+  InterpretError,
+};
+
+fn build_chunk(code: Vec<OpCode>, constants: Vec<Value>) -> Chunk {
+  let mut chunk = Chunk::default();
+  for opcode in code {
+    chunk.write(opcode, 1);
+  }
+  for constant in constants {
+    chunk.add_constant(constant);
+  }
+  chunk
+}
+
+#[test]
+fn compiled_code_verifies() {
+  let function = compile(
+    r#"
+    const limit = 10;
+    fn counter() {
+      let count = 0;
+      fn inc() { count = count + 1; count };
+      inc
+    };
+    let next = counter();
+    let total = 'outer: while true {
+      let n = next();
+      if n > limit { break 'outer n; };
+      while false { continue; };
+    };
+    let kind = match #[total, "a"] {
+      #[0, _] => "zero",
+      #[n, s] => s,
+    };
+    log(kind);
+    "#,
+  )
+  .unwrap();
+  assert!(verify(&function.chunk).is_ok());
+}
+
+#[test]
+fn jump_out_of_bounds() {
+  let chunk = build_chunk(
+    vec![OpCode::Jump(JumpDirection::Forwards, 5), OpCode::ReturnUnit],
+    vec![],
+  );
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::JumpOutOfBounds(0))
+  ));
+
+  let chunk = build_chunk(
+    vec![
+      OpCode::Unit,
+      OpCode::Jump(JumpDirection::Backwards, 3),
+      OpCode::Return,
+    ],
+    vec![],
+  );
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::JumpOutOfBounds(1))
+  ));
+}
+
+#[test]
+fn invalid_constant() {
+  let chunk = build_chunk(vec![OpCode::Constant(1), OpCode::Return], vec![Value::Unit]);
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::InvalidConstant(0))
+  ));
+
+  // globals must be named by a string constant
+  let chunk = build_chunk(
+    vec![OpCode::GetGlobal(0), OpCode::Return],
+    vec![Value::Number(1.0)],
+  );
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::InvalidConstant(0))
+  ));
+}
+
+#[test]
+fn invalid_local() {
+  let chunk = build_chunk(vec![OpCode::GetLocal(1), OpCode::Return], vec![]);
+  assert!(matches!(verify(&chunk), Err(VerifyError::InvalidLocal(0))));
+}
+
+#[test]
+fn stack_underflow() {
+  let chunk = build_chunk(vec![OpCode::Unit, OpCode::Add, OpCode::Return], vec![]);
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::StackUnderflow(1))
+  ));
+}
+
+#[test]
+fn inconsistent_stack() {
+  // one branch pushes an extra value before both meet at the return
+  let chunk = build_chunk(
+    vec![
+      OpCode::True,
+      OpCode::JumpIfFalse(1),
+      OpCode::Unit,
+      OpCode::Return,
+    ],
+    vec![],
+  );
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::InconsistentStack(_))
+  ));
+}
+
+#[test]
+fn missing_return() {
+  let chunk = build_chunk(vec![OpCode::Unit, OpCode::Pop], vec![]);
+  assert!(matches!(verify(&chunk), Err(VerifyError::MissingReturn)));
+}
+
+#[test]
+fn invalid_nested_function() {
+  let inner = Function {
+    arity: 0,
+    chunk: build_chunk(vec![OpCode::GetUpvalue(0), OpCode::Return], vec![]),
+    name: "inner".to_string(),
+  };
+  let chunk = build_chunk(
+    vec![OpCode::Closure(0, vec![]), OpCode::Return],
+    vec![Value::Function(Rc::new(inner))],
+  );
+  assert!(matches!(
+    verify(&chunk),
+    Err(VerifyError::InvalidUpvalue(0))
+  ));
+}
+
+#[test]
+fn vm_verifies_when_asked() {
+  let function = Rc::new(Function {
+    chunk: build_chunk(vec![OpCode::Pop, OpCode::ReturnUnit], vec![]),
+    ..Default::default()
+  });
+
+  let mut vm = VM::default();
+  vm.set_verify(true);
+  assert!(matches!(
+    vm.interpret(function),
+    Err(InterpretError::VerifyError(VerifyError::StackUnderflow(0)))
+  ));
+
+  let function = compile("let a = 1; a + 2").unwrap();
+  assert!(matches!(vm.interpret(function), Ok(Value::Int(3))));
+}
@@ -0,0 +1,83 @@
+use bobascript::compiler::{compile_with_warnings, CompileWarning};
+
+#[test]
+fn unused_variable() {
+  let (_, warnings) = compile_with_warnings(
+    r#"
+    {
+      let used = 1;
+      let unused = 2;
+      let _ignored = 3;
+      used
+    };
+    "#,
+  )
+  .unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert!(matches!(&warnings[0], CompileWarning::UnusedVariable(name) if name == "unused"));
+}
+
+#[test]
+fn unused_parameter() {
+  let (_, warnings) = compile_with_warnings(
+    r#"
+    fn f(a, b, _c) {
+      a
+    };
+    "#,
+  )
+  .unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert!(matches!(&warnings[0], CompileWarning::UnusedVariable(name) if name == "b"));
+}
+
+#[test]
+fn if_without_else_in_value_position() {
+  let (_, warnings) = compile_with_warnings("let c = true; let x = if c { 1 };").unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert!(matches!(
+    &warnings[0],
+    CompileWarning::IfWithoutElseInValuePosition
+  ));
+
+  let (_, warnings) = compile_with_warnings("fn f() {}; let c = true; if c { f(); };").unwrap();
+  assert!(warnings.is_empty());
+}
+
+#[test]
+fn if_without_else_in_discarded_tail() {
+  for source in &[
+    "let c = true; { if c { log(1); } };",
+    "let c = true; if c { log(1); }",
+    "fn f(c) { if c { log(1); } };",
+    "let c = true; if c { if c { log(1); } } else { if c { log(2); } };",
+  ] {
+    let (_, warnings) = compile_with_warnings(*source).unwrap();
+    assert!(warnings.is_empty(), "{}", source);
+  }
+
+  let (_, warnings) = compile_with_warnings("let c = true; let x = { if c { 1 } };").unwrap();
+  assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn assignment_in_condition() {
+  let (_, warnings) = compile_with_warnings("let x = false; if x = true {};").unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert!(matches!(
+    &warnings[0],
+    CompileWarning::AssignmentInCondition
+  ));
+
+  let (_, warnings) = compile_with_warnings("let x = false; while x = false {};").unwrap();
+  assert_eq!(warnings.len(), 1);
+  assert!(matches!(
+    &warnings[0],
+    CompileWarning::AssignmentInCondition
+  ));
+
+  let (_, warnings) = compile_with_warnings("let x = 5; if x == 5 {};").unwrap();
+  assert!(warnings.is_empty());
+  let (_, warnings) = compile_with_warnings("let x = false; if { x = true } {};").unwrap();
+  assert!(warnings.is_empty());
+}